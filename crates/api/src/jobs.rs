@@ -0,0 +1,154 @@
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use bonsai_types::{ConsistencyResponse, IntegrityResponse, JobResponse, JobState, StorageContractsResponse, TrieStatsResponse};
+
+/// What a finished job produced; each kind fills its own field of `JobResponse`.
+pub enum JobOutput {
+    Integrity(IntegrityResponse),
+    Consistency(ConsistencyResponse),
+    Stats(TrieStatsResponse),
+    Contracts(StorageContractsResponse),
+}
+
+struct Job {
+    kind: &'static str,
+    progress: Arc<AtomicU64>,
+    outcome: Option<Result<JobOutput, String>>,
+}
+
+/// Long-running checks started over the API. Finished jobs are kept until the process exits.
+#[derive(Clone, Default)]
+pub struct Jobs {
+    next_id: Arc<AtomicU64>,
+    jobs: Arc<Mutex<BTreeMap<u64, Job>>>,
+}
+
+impl Jobs {
+    /// Runs `run` on the blocking pool and returns the job in its `running` state.
+    pub fn spawn<F>(&self, kind: &'static str, run: F) -> JobResponse
+    where
+        F: FnOnce(&AtomicU64) -> JobOutput + Send + 'static,
+    {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+        let progress = Arc::new(AtomicU64::new(0));
+        self.lock().insert(id, Job { kind, progress: Arc::clone(&progress), outcome: None });
+
+        let jobs = self.clone();
+        tokio::spawn(async move {
+            let outcome = tokio::task::spawn_blocking(move || run(&progress))
+                .await
+                .map_err(|err| format!("job panicked: {err}"));
+            if let Some(job) = jobs.lock().get_mut(&id) {
+                job.outcome = Some(outcome);
+            }
+        });
+        self.get(id).unwrap_or_else(|| unknown_job(id))
+    }
+
+    pub fn get(&self, id: u64) -> Option<JobResponse> {
+        self.lock().get(&id).map(|job| job_response(id, job))
+    }
+
+    pub fn list(&self) -> Vec<JobResponse> {
+        self.lock().iter().map(|(id, job)| job_response(*id, job)).collect()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, BTreeMap<u64, Job>> {
+        self.jobs.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+pub fn unknown_job(id: u64) -> JobResponse {
+    failed_job(id, "unknown", format!("unknown job {id}"))
+}
+
+pub fn failed_job(id: u64, kind: &str, error: String) -> JobResponse {
+    JobResponse {
+        id,
+        kind: kind.to_string(),
+        state: JobState::Failed,
+        progress: 0,
+        error: Some(error),
+        integrity: None,
+        consistency: None,
+        stats: None,
+        contracts: None,
+    }
+}
+
+fn job_response(id: u64, job: &Job) -> JobResponse {
+    let mut resp = JobResponse {
+        id,
+        kind: job.kind.to_string(),
+        state: JobState::Running,
+        progress: job.progress.load(Ordering::Relaxed),
+        error: None,
+        integrity: None,
+        consistency: None,
+        stats: None,
+        contracts: None,
+    };
+    match &job.outcome {
+        None => {}
+        Some(Err(err)) => {
+            resp.state = JobState::Failed;
+            resp.error = Some(err.clone());
+        }
+        Some(Ok(JobOutput::Integrity(report))) => {
+            resp.state = if report.error.is_some() { JobState::Failed } else { JobState::Done };
+            resp.error = report.error.clone();
+            resp.integrity = Some(report.clone());
+        }
+        Some(Ok(JobOutput::Consistency(report))) => {
+            resp.state = if report.error.is_some() { JobState::Failed } else { JobState::Done };
+            resp.error = report.error.clone();
+            resp.consistency = Some(report.clone());
+        }
+        Some(Ok(JobOutput::Stats(report))) => {
+            resp.state = if report.error.is_some() { JobState::Failed } else { JobState::Done };
+            resp.error = report.error.clone();
+            resp.stats = Some(report.clone());
+        }
+        Some(Ok(JobOutput::Contracts(report))) => {
+            resp.state = if report.error.is_some() { JobState::Failed } else { JobState::Done };
+            resp.error = report.error.clone();
+            resp.contracts = Some(report.clone());
+        }
+    }
+    resp
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use bonsai_db_reader::testing::{spec, TestDb};
+    use bonsai_types::TrieKind;
+    use starknet_types_core::felt::Felt;
+
+    #[tokio::test]
+    async fn stats_jobs_report_one_storage_contract() {
+        let test_db = TestDb::new();
+        test_db.commit(5, &spec(TrieKind::Storage, Some("0x11")), TrieKind::Storage, &[(Felt::ONE, Felt::TWO), (Felt::TWO, Felt::THREE)]);
+        let db = test_db.open();
+
+        let jobs = Jobs::default();
+        let started = jobs.spawn("stats", move |progress| {
+            JobOutput::Stats(bonsai_db_reader::trie_stats(&db, TrieKind::Storage, Some("0x11".to_string()), None, progress))
+        });
+        let mut job = started.clone();
+        while job.state == JobState::Running {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            job = jobs.get(started.id).unwrap();
+        }
+        // A root edge, a binary node and a one-bit edge down to each leaf.
+        assert_eq!((job.state, job.kind.as_str(), job.progress), (JobState::Done, "stats", 4));
+        let stats = job.stats.unwrap();
+        assert_eq!((stats.identifier.as_deref(), stats.leaves, stats.binary_nodes, stats.edge_nodes), (Some("0x11"), 2, 1, 3));
+        assert_eq!(jobs.list().len(), 1);
+        assert_eq!(jobs.get(started.id + 1), None);
+    }
+}
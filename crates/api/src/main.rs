@@ -1,5 +1,9 @@
+mod jobs;
+mod rpc;
+
 use std::net::SocketAddr;
 use std::sync::Arc;
+use std::time::Duration;
 
 use axum::{
     extract::{Query, State},
@@ -7,12 +11,13 @@ use axum::{
     routing::{get, post},
     Json, Router,
 };
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use tokio::sync::RwLock;
 use tower_http::cors::{Any, CorsLayer};
 
 use bonsai_db_reader as reader;
-use bonsai_types::{CfsResponse, DiffResponse, LeafResponse, NodeResponse, ProofResponse, RootResponse, TrieKind};
+use jobs::{JobOutput, Jobs};
+use bonsai_types::{ActivityResponse, BatchProofResponse, BisectRequest, BisectResponse, CfsResponse, ClassResponse, ClassesResponse, ContractResponse, ContractStorageProofResponse, DiffResponse, FollowResponse, HistoryResponse, JobResponse, LeafResponse, LeavesResponse, NodeResponse, ProofDocument, ProofResponse, RangeDiffResponse, RetentionResponse, RootResponse, StateRootResponse, StorageLabelsRequest, StorageLabelsResponse, TrieKind, VerifyProofRequest, VerifyProofResponse};
 
 #[derive(Debug, Parser)]
 #[command(name = "bonsai-api")]
@@ -22,36 +27,123 @@ struct Args {
 
     #[arg(long, default_value_t = 4010)]
     port: u16,
+
+    /// Open the DB as a RocksDB secondary instance using this scratch directory,
+    /// so the API can follow a running Madara node.
+    #[arg(long, value_name = "PATH")]
+    secondary_path: Option<String>,
+
+    /// Catch up with the primary every N seconds (secondary mode only).
+    #[arg(long, value_name = "SECS")]
+    follow_interval_secs: Option<u64>,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Recompute every node hash of a trie and report mismatches, then exit.
+    Check {
+        #[arg(long, value_parser = parse_trie, default_value = "contract")]
+        trie: TrieKind,
+        /// Contract address, for storage tries.
+        #[arg(long)]
+        identifier: Option<String>,
+        #[arg(long, value_name = "N")]
+        block: Option<u64>,
+        /// Print the full report as JSON.
+        #[arg(long)]
+        json: bool,
+    },
+    /// Cross-check the flat CF against the trie leaves and report orphans on either side, then exit.
+    Consistency {
+        #[arg(long, value_parser = parse_trie, default_value = "contract")]
+        trie: TrieKind,
+        /// Contract address, for storage tries.
+        #[arg(long)]
+        identifier: Option<String>,
+        #[arg(long, value_name = "N")]
+        block: Option<u64>,
+        /// Print the full report as JSON.
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+fn parse_trie(value: &str) -> Result<TrieKind, String> {
+    match value {
+        "contract" => Ok(TrieKind::Contract),
+        "storage" => Ok(TrieKind::Storage),
+        "class" => Ok(TrieKind::Class),
+        other => Err(format!("unknown trie {other}, expected contract, storage or class")),
+    }
 }
 
 #[derive(Clone)]
 struct AppState {
     db: Arc<RwLock<Option<reader::db::RocksDb>>>,
+    follow_interval_secs: Option<u64>,
+    jobs: Jobs,
 }
 
 #[tokio::main]
 async fn main() {
     let args = Args::parse();
+    if let Some(command) = &args.command {
+        std::process::exit(run_command(&args, command));
+    }
     let db = if let Some(path) = args.db_path.as_deref() {
-        reader::open_db(path).ok()
+        open_with_mode(path, args.secondary_path.as_deref()).ok()
     } else {
         None
     };
     let state = AppState {
         db: Arc::new(RwLock::new(db)),
+        follow_interval_secs: args.follow_interval_secs.filter(|secs| *secs > 0),
+        jobs: Jobs::default(),
     };
 
+    if let Some(secs) = state.follow_interval_secs {
+        tokio::spawn(follow_loop(state.clone(), secs));
+    }
+
     let cors = CorsLayer::new().allow_origin(Any).allow_methods(Any);
 
     let app = Router::new()
         .route("/api/health", get(health))
         .route("/api/open", post(open_db))
         .route("/api/cfs", get(cfs))
+        .route("/api/follow", get(follow))
+        .route("/api/follow/catch-up", post(follow_catch_up))
         .route("/api/trie/root", get(trie_root))
         .route("/api/trie/node", get(trie_node))
         .route("/api/trie/leaf", get(trie_leaf))
+        .route("/api/trie/leaves", get(trie_leaves))
         .route("/api/diff", get(diff_block))
+        .route("/api/history", get(history))
+        .route("/api/diff/range", get(diff_range))
+        .route("/api/bisect", get(bisect))
+        .route("/api/activity", get(activity))
+        .route("/api/logs/retention", get(logs_retention))
         .route("/api/proof", get(proof))
+        .route("/api/proof/batch", post(batch_proof))
+        .route("/api/storage/labels", post(storage_labels))
+        .route("/api/proof/verify", post(verify_proof))
+        .route("/api/proof/verify-document", post(verify_proof_document))
+        .route("/api/key/resolve", get(resolve_key))
+        .route("/api/proof/contract-storage", get(contract_storage_proof))
+        .route("/api/state-root", get(state_root))
+        .route("/api/contract", get(contract))
+        .route("/api/class", get(class))
+        .route("/api/classes", get(classes))
+        .route("/rpc", post(json_rpc))
+        .route("/api/jobs", get(jobs_list))
+        .route("/api/jobs/status", get(job_status))
+        .route("/api/jobs/integrity", post(start_integrity_job))
+        .route("/api/jobs/consistency", post(start_consistency_job))
+        .route("/api/jobs/stats", post(start_stats_job))
+        .route("/api/jobs/contracts", post(start_contracts_job))
         .with_state(state)
         .layer(cors);
 
@@ -63,18 +155,109 @@ async fn main() {
         .unwrap();
 }
 
+fn run_command(args: &Args, command: &Command) -> i32 {
+    let Some(path) = args.db_path.as_deref() else {
+        eprintln!("--db-path is required");
+        return 2;
+    };
+    let db = match open_with_mode(path, args.secondary_path.as_deref()) {
+        Ok(db) => db,
+        Err(err) => {
+            eprintln!("failed to open db: {err}");
+            return 2;
+        }
+    };
+    match command {
+        Command::Check { trie, identifier, block, json } => {
+            let progress = std::sync::atomic::AtomicU64::new(0);
+            let report = reader::check_integrity(&db, *trie, identifier.clone(), *block, &progress);
+            if *json {
+                println!("{}", serde_json::to_string_pretty(&report).unwrap_or_default());
+            } else {
+                println!(
+                    "checked {} nodes, {} leaves; stored root {}, computed root {}",
+                    report.nodes_checked,
+                    report.leaves_checked,
+                    report.stored_root.as_deref().unwrap_or("-"),
+                    report.computed_root.as_deref().unwrap_or("-"),
+                );
+                for m in &report.mismatches {
+                    println!(
+                        "{} at {} (len {}): expected {}, found {}",
+                        m.kind,
+                        m.path_hex,
+                        m.path_len,
+                        m.expected.as_deref().unwrap_or("-"),
+                        m.found.as_deref().unwrap_or("-"),
+                    );
+                }
+            }
+            if let Some(err) = &report.error {
+                eprintln!("{err}");
+                return 2;
+            }
+            i32::from(!report.mismatches.is_empty())
+        }
+        Command::Consistency { trie, identifier, block, json } => {
+            let progress = std::sync::atomic::AtomicU64::new(0);
+            let report = reader::check_consistency(&db, *trie, identifier.clone(), *block, &progress);
+            if *json {
+                println!("{}", serde_json::to_string_pretty(&report).unwrap_or_default());
+            } else {
+                println!(
+                    "{} flat entries, {} trie leaves, {} matched, {} missing trie nodes",
+                    report.flat_entries, report.trie_leaves, report.matched, report.missing_nodes,
+                );
+                for issue in &report.issues {
+                    println!(
+                        "{} {}: flat {}, trie {}",
+                        issue.kind,
+                        issue.key,
+                        issue.flat.as_deref().unwrap_or("-"),
+                        issue.trie.as_deref().unwrap_or("-"),
+                    );
+                }
+            }
+            if let Some(err) = &report.error {
+                eprintln!("{err}");
+                return 2;
+            }
+            i32::from(!report.issues.is_empty() || report.missing_nodes > 0)
+        }
+    }
+}
+
 async fn health() -> impl IntoResponse {
     Json(serde_json::json!({"ok": true}))
 }
 
+fn open_with_mode(path: &str, secondary_path: Option<&str>) -> Result<reader::db::RocksDb, String> {
+    match secondary_path {
+        Some(secondary) => reader::open_db_secondary(path, secondary),
+        None => reader::open_db(path),
+    }
+}
+
+async fn follow_loop(state: AppState, secs: u64) {
+    let mut ticker = tokio::time::interval(Duration::from_secs(secs));
+    loop {
+        ticker.tick().await;
+        let Some(db) = state.db.read().await.clone() else {
+            continue;
+        };
+        let _ = tokio::task::spawn_blocking(move || reader::catch_up(&db)).await;
+    }
+}
+
 #[derive(Debug, serde::Deserialize)]
 struct OpenQuery {
     db_path: String,
+    secondary_path: Option<String>,
 }
 
 async fn open_db(State(state): State<AppState>, Query(params): Query<OpenQuery>) -> impl IntoResponse {
     let mut guard = state.db.write().await;
-    match reader::open_db(&params.db_path) {
+    match open_with_mode(&params.db_path, params.secondary_path.as_deref()) {
         Ok(db) => {
             *guard = Some(db);
             Json(serde_json::json!({"ok": true}))
@@ -91,10 +274,43 @@ async fn cfs(State(state): State<AppState>) -> impl IntoResponse {
     Json(reader::list_cfs(db))
 }
 
+fn closed_follow_response(state: &AppState) -> FollowResponse {
+    FollowResponse {
+        mode: "closed".to_string(),
+        secondary_path: None,
+        interval_secs: state.follow_interval_secs,
+        catch_ups: 0,
+        last_catch_up_ms: None,
+        last_error: None,
+    }
+}
+
+async fn follow(State(state): State<AppState>) -> impl IntoResponse {
+    let guard = state.db.read().await;
+    let Some(db) = guard.as_ref() else {
+        return Json(closed_follow_response(&state));
+    };
+    let mut resp = reader::follow_status(db);
+    resp.interval_secs = state.follow_interval_secs;
+    Json(resp)
+}
+
+async fn follow_catch_up(State(state): State<AppState>) -> impl IntoResponse {
+    let Some(db) = state.db.read().await.clone() else {
+        return Json(closed_follow_response(&state));
+    };
+    let mut resp = tokio::task::spawn_blocking(move || reader::catch_up(&db))
+        .await
+        .unwrap_or_else(|_| closed_follow_response(&state));
+    resp.interval_secs = state.follow_interval_secs;
+    Json(resp)
+}
+
 #[derive(Debug, serde::Deserialize)]
 struct TrieQuery {
     trie: TrieKind,
     identifier: Option<String>,
+    block: Option<u64>,
 }
 
 #[derive(Debug, serde::Deserialize)]
@@ -102,6 +318,7 @@ struct NodeQuery {
     trie: TrieKind,
     identifier: Option<String>,
     path: String,
+    block: Option<u64>,
 }
 
 #[derive(Debug, serde::Deserialize)]
@@ -109,6 +326,17 @@ struct LeafQuery {
     trie: TrieKind,
     identifier: Option<String>,
     key: String,
+    block: Option<u64>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct LeavesQuery {
+    trie: TrieKind,
+    identifier: Option<String>,
+    cursor: Option<String>,
+    start: Option<String>,
+    end: Option<String>,
+    limit: Option<usize>,
 }
 
 #[derive(Debug, serde::Deserialize)]
@@ -117,43 +345,399 @@ struct DiffQuery {
     block: u64,
 }
 
+#[derive(Debug, serde::Deserialize)]
+struct DiffRangeQuery {
+    trie: TrieKind,
+    start: u64,
+    end: u64,
+}
+
 async fn trie_root(State(state): State<AppState>, Query(params): Query<TrieQuery>) -> impl IntoResponse {
-    let guard = state.db.read().await;
-    let Some(db) = guard.as_ref() else {
-        return Json(RootResponse { path_hex: "0x00".to_string(), node: None });
+    let failed = |error: Option<String>| RootResponse { path_hex: "0x00".to_string(), node: None, error };
+    let Some(db) = state.db.read().await.clone() else {
+        return Json(failed(None));
     };
-    Json(reader::root_node(db, params.trie, params.identifier))
+    let resp = tokio::task::spawn_blocking(move || reader::root_node(&db, params.trie, params.identifier, params.block))
+        .await
+        .unwrap_or_else(|err| failed(Some(err.to_string())));
+    Json(resp)
 }
 
 async fn trie_node(State(state): State<AppState>, Query(params): Query<NodeQuery>) -> impl IntoResponse {
-    let guard = state.db.read().await;
-    let Some(db) = guard.as_ref() else {
-        return Json(NodeResponse { path_hex: params.path, node: None });
+    let failed = |path_hex: String, error: Option<String>| NodeResponse { path_hex, node: None, error };
+    let Some(db) = state.db.read().await.clone() else {
+        return Json(failed(params.path, None));
     };
-    Json(reader::load_node(db, params.trie, params.identifier, &params.path))
+    let path = params.path.clone();
+    let resp = tokio::task::spawn_blocking(move || reader::load_node(&db, params.trie, params.identifier, &params.path, params.block))
+        .await
+        .unwrap_or_else(|err| failed(path, Some(err.to_string())));
+    Json(resp)
 }
 
 async fn trie_leaf(State(state): State<AppState>, Query(params): Query<LeafQuery>) -> impl IntoResponse {
+    let failed = |key: String, error: Option<String>| LeafResponse { key, value: None, error };
+    let Some(db) = state.db.read().await.clone() else {
+        return Json(failed(params.key, None));
+    };
+    let key = params.key.clone();
+    let resp = tokio::task::spawn_blocking(move || reader::leaf_value(&db, params.trie, params.identifier, &params.key, params.block))
+        .await
+        .unwrap_or_else(|err| failed(key, Some(err.to_string())));
+    Json(resp)
+}
+
+async fn trie_leaves(State(state): State<AppState>, Query(params): Query<LeavesQuery>) -> impl IntoResponse {
     let guard = state.db.read().await;
     let Some(db) = guard.as_ref() else {
-        return Json(LeafResponse { key: params.key, value: None });
+        return Json(LeavesResponse { leaves: Vec::new(), next_cursor: None, error: None });
     };
-    Json(reader::leaf_value(db, params.trie, params.identifier, &params.key))
+    Json(reader::list_leaves(
+        db,
+        params.trie,
+        params.identifier,
+        params.cursor.as_deref(),
+        params.start.as_deref(),
+        params.end.as_deref(),
+        params.limit.unwrap_or(100),
+    ))
 }
 
 async fn diff_block(State(state): State<AppState>, Query(params): Query<DiffQuery>) -> impl IntoResponse {
-    let guard = state.db.read().await;
-    let Some(db) = guard.as_ref() else {
-        return Json(DiffResponse { entries: Vec::new() });
+    let failed = |error: Option<String>| DiffResponse { entries: Vec::new(), changes: Vec::new(), error };
+    let Some(db) = state.db.read().await.clone() else {
+        return Json(failed(None));
+    };
+    let resp = tokio::task::spawn_blocking(move || reader::diff_for_block(&db, params.trie, params.block))
+        .await
+        .unwrap_or_else(|err| failed(Some(err.to_string())));
+    Json(resp)
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct HistoryQuery {
+    trie: TrieKind,
+    identifier: Option<String>,
+    key: String,
+}
+
+async fn history(State(state): State<AppState>, Query(params): Query<HistoryQuery>) -> impl IntoResponse {
+    let failed = |key: String, error: Option<String>| HistoryResponse { key, entries: Vec::new(), earliest_logged: None, error };
+    let Some(db) = state.db.read().await.clone() else {
+        return Json(failed(params.key, None));
+    };
+    let key = params.key.clone();
+    let resp = tokio::task::spawn_blocking(move || reader::leaf_history(&db, params.trie, params.identifier, &params.key))
+        .await
+        .unwrap_or_else(|err| failed(key, Some(err.to_string())));
+    Json(resp)
+}
+
+async fn bisect(State(state): State<AppState>, Query(params): Query<BisectRequest>) -> impl IntoResponse {
+    let failed = |error: Option<String>| BisectResponse { block: None, condition: String::new(), steps: Vec::new(), error };
+    let Some(db) = state.db.read().await.clone() else {
+        return Json(failed(None));
+    };
+    let resp = tokio::task::spawn_blocking(move || reader::bisect(&db, &params))
+        .await
+        .unwrap_or_else(|err| failed(Some(err.to_string())));
+    Json(resp)
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct ActivityQuery {
+    start: u64,
+    end: u64,
+}
+
+async fn activity(State(state): State<AppState>, Query(params): Query<ActivityQuery>) -> impl IntoResponse {
+    let failed = |error: Option<String>| ActivityResponse {
+        start: params.start,
+        end: params.end,
+        blocks: Vec::new(),
+        contracts: Vec::new(),
+        contract_count: 0,
+        error,
+    };
+    let Some(db) = state.db.read().await.clone() else {
+        return Json(failed(None));
+    };
+    let resp = tokio::task::spawn_blocking(move || reader::block_activity(&db, params.start, params.end))
+        .await
+        .unwrap_or_else(|err| failed(Some(err.to_string())));
+    Json(resp)
+}
+
+async fn diff_range(State(state): State<AppState>, Query(params): Query<DiffRangeQuery>) -> impl IntoResponse {
+    let failed = |error: Option<String>| RangeDiffResponse { start: params.start, end: params.end, entries: Vec::new(), error };
+    let Some(db) = state.db.read().await.clone() else {
+        return Json(failed(None));
+    };
+    let resp = tokio::task::spawn_blocking(move || reader::diff_for_range(&db, params.trie, params.start, params.end))
+        .await
+        .unwrap_or_else(|err| failed(Some(err.to_string())));
+    Json(resp)
+}
+
+async fn logs_retention(State(state): State<AppState>) -> impl IntoResponse {
+    let Some(db) = state.db.read().await.clone() else {
+        return Json(RetentionResponse { logs: Vec::new() });
+    };
+    let resp = tokio::task::spawn_blocking(move || reader::log_retention(&db))
+        .await
+        .unwrap_or(RetentionResponse { logs: Vec::new() });
+    Json(resp)
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct JobQuery {
+    id: u64,
+}
+
+async fn jobs_list(State(state): State<AppState>) -> impl IntoResponse {
+    Json(state.jobs.list())
+}
+
+async fn job_status(State(state): State<AppState>, Query(params): Query<JobQuery>) -> impl IntoResponse {
+    Json(state.jobs.get(params.id).unwrap_or_else(|| jobs::unknown_job(params.id)))
+}
+
+async fn start_integrity_job(State(state): State<AppState>, Query(params): Query<TrieQuery>) -> Json<JobResponse> {
+    let Some(db) = state.db.read().await.clone() else {
+        return Json(jobs::failed_job(0, "integrity", "db not open".to_string()));
+    };
+    Json(state.jobs.spawn("integrity", move |progress| {
+        JobOutput::Integrity(reader::check_integrity(&db, params.trie, params.identifier, params.block, progress))
+    }))
+}
+
+async fn start_consistency_job(State(state): State<AppState>, Query(params): Query<TrieQuery>) -> Json<JobResponse> {
+    let Some(db) = state.db.read().await.clone() else {
+        return Json(jobs::failed_job(0, "consistency", "db not open".to_string()));
     };
-    Json(reader::diff_for_block(db, params.trie, params.block))
+    Json(state.jobs.spawn("consistency", move |progress| {
+        JobOutput::Consistency(reader::check_consistency(&db, params.trie, params.identifier, params.block, progress))
+    }))
+}
+
+async fn start_stats_job(State(state): State<AppState>, Query(params): Query<TrieQuery>) -> Json<JobResponse> {
+    let Some(db) = state.db.read().await.clone() else {
+        return Json(jobs::failed_job(0, "stats", "db not open".to_string()));
+    };
+    Json(state.jobs.spawn("stats", move |progress| {
+        JobOutput::Stats(reader::trie_stats(&db, params.trie, params.identifier, params.block, progress))
+    }))
+}
+
+async fn start_contracts_job(State(state): State<AppState>) -> Json<JobResponse> {
+    let Some(db) = state.db.read().await.clone() else {
+        return Json(jobs::failed_job(0, "contracts", "db not open".to_string()));
+    };
+    Json(state.jobs.spawn("contracts", move |progress| JobOutput::Contracts(reader::storage_contracts(&db, progress))))
 }
 
 async fn proof(State(state): State<AppState>, Query(params): Query<LeafQuery>) -> impl IntoResponse {
-    let guard = state.db.read().await;
-    let Some(db) = guard.as_ref() else {
-        return Json(ProofResponse { verified: false, nodes: Vec::new() });
+    let failed = |error: Option<String>| ProofResponse { verified: false, membership: None, value: None, nodes: Vec::new(), document: None, error };
+    let Some(db) = state.db.read().await.clone() else {
+        return Json(failed(None));
+    };
+    let resp = tokio::task::spawn_blocking(move || reader::proof_for_key(&db, params.trie, params.identifier, &params.key, params.block))
+        .await
+        .unwrap_or_else(|err| failed(Some(err.to_string())));
+    Json(resp)
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct ContractStorageProofQuery {
+    contract: String,
+    key: String,
+    block: Option<u64>,
+}
+
+async fn contract_storage_proof(State(state): State<AppState>, Query(params): Query<ContractStorageProofQuery>) -> impl IntoResponse {
+    let failed = |error: Option<String>| {
+        let empty = ProofResponse { verified: false, membership: None, value: None, nodes: Vec::new(), document: None, error: None };
+        ContractStorageProofResponse { verified: false, contract_proof: empty.clone(), leaf: None, leaf_hash: None, storage_proof: empty, error }
+    };
+    let Some(db) = state.db.read().await.clone() else {
+        return Json(failed(None));
+    };
+    let resp = tokio::task::spawn_blocking(move || reader::contract_storage_proof(&db, &params.contract, &params.key, params.block))
+        .await
+        .unwrap_or_else(|err| failed(Some(err.to_string())));
+    Json(resp)
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct ContractQuery {
+    address: String,
+    block: Option<u64>,
+}
+
+async fn contract(State(state): State<AppState>, Query(params): Query<ContractQuery>) -> impl IntoResponse {
+    let failed = |address: String, error: Option<String>| ContractResponse { address, leaf: None, state: None, computed_leaf: None, matches: false, error };
+    let Some(db) = state.db.read().await.clone() else {
+        return Json(failed(params.address, None));
+    };
+    let address = params.address.clone();
+    let resp = tokio::task::spawn_blocking(move || reader::contract_info(&db, &params.address, params.block))
+        .await
+        .unwrap_or_else(|err| failed(address, Some(err.to_string())));
+    Json(resp)
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct ClassQuery {
+    class_hash: String,
+    block: Option<u64>,
+}
+
+async fn class(State(state): State<AppState>, Query(params): Query<ClassQuery>) -> impl IntoResponse {
+    let failed = |class_hash: String, error: Option<String>| ClassResponse {
+        class_hash,
+        leaf: None,
+        compiled_class_hash: None,
+        computed_leaf: None,
+        matches: false,
+        declared_block: None,
+        error,
+    };
+    let Some(db) = state.db.read().await.clone() else {
+        return Json(failed(params.class_hash, None));
+    };
+    let class_hash = params.class_hash.clone();
+    let resp = tokio::task::spawn_blocking(move || reader::class_info(&db, &params.class_hash, params.block))
+        .await
+        .unwrap_or_else(|err| failed(class_hash, Some(err.to_string())));
+    Json(resp)
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct ClassesQuery {
+    cursor: Option<String>,
+    limit: Option<usize>,
+}
+
+async fn classes(State(state): State<AppState>, Query(params): Query<ClassesQuery>) -> impl IntoResponse {
+    let failed = |error: Option<String>| ClassesResponse { classes: Vec::new(), next_cursor: None, error };
+    let Some(db) = state.db.read().await.clone() else {
+        return Json(failed(None));
+    };
+    let resp = tokio::task::spawn_blocking(move || reader::list_classes(&db, params.cursor.as_deref(), params.limit.unwrap_or(100)))
+        .await
+        .unwrap_or_else(|err| failed(Some(err.to_string())));
+    Json(resp)
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct StateRootQuery {
+    block: Option<u64>,
+}
+
+async fn state_root(State(state): State<AppState>, Query(params): Query<StateRootQuery>) -> impl IntoResponse {
+    let failed = |error: Option<String>| StateRootResponse {
+        block: params.block,
+        contract_root: None,
+        class_root: None,
+        state_root: None,
+        header_state_root: None,
+        matches: None,
+        error,
+    };
+    let Some(db) = state.db.read().await.clone() else {
+        return Json(failed(None));
+    };
+    let resp = tokio::task::spawn_blocking(move || reader::state_root(&db, params.block))
+        .await
+        .unwrap_or_else(|err| failed(Some(err.to_string())));
+    Json(resp)
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct BatchProofBody {
+    trie: TrieKind,
+    identifier: Option<String>,
+    block: Option<u64>,
+    keys: Vec<String>,
+}
+
+async fn batch_proof(State(state): State<AppState>, Json(body): Json<BatchProofBody>) -> impl IntoResponse {
+    let Some(db) = state.db.read().await.clone() else {
+        return Json(BatchProofResponse { root: None, nodes: Vec::new(), keys: Vec::new(), error: None });
+    };
+    let resp = tokio::task::spawn_blocking(move || reader::batch_proof(&db, body.trie, body.identifier, &body.keys, body.block))
+        .await
+        .unwrap_or_else(|err| BatchProofResponse { root: None, nodes: Vec::new(), keys: Vec::new(), error: Some(err.to_string()) });
+    Json(resp)
+}
+
+async fn storage_labels(State(state): State<AppState>, Json(body): Json<StorageLabelsRequest>) -> impl IntoResponse {
+    let failed = |error: Option<String>| StorageLabelsResponse { variables: Vec::new(), labels: Vec::new(), leaves: 0, mapping_keys: 0, error };
+    let Some(db) = state.db.read().await.clone() else {
+        return Json(failed(None));
     };
-    Json(reader::proof_for_key(db, params.trie, params.identifier, &params.key))
+    let resp = tokio::task::spawn_blocking(move || reader::storage_labels(&db, &body.contract, &body.layout))
+        .await
+        .unwrap_or_else(|err| failed(Some(err.to_string())));
+    Json(resp)
+}
+
+/// Checks a proof supplied in the body; needs no open database.
+async fn verify_proof(Json(body): Json<VerifyProofRequest>) -> impl IntoResponse {
+    Json(reader::verify_external_proof(&body))
 }
 
+/// Checks a proof document, including the node hashes it states; needs no open database.
+async fn verify_proof_document(Json(doc): Json<ProofDocument>) -> Json<VerifyProofResponse> {
+    Json(reader::verify_proof_document(&doc))
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct ResolveKeyQuery {
+    expression: String,
+}
+
+/// Resolves a storage key expression; needs no open database.
+async fn resolve_key(Query(params): Query<ResolveKeyQuery>) -> impl IntoResponse {
+    Json(reader::resolve_key(&params.expression))
+}
+
+async fn json_rpc(State(state): State<AppState>, body: axum::body::Bytes) -> impl IntoResponse {
+    let Some(db) = state.db.read().await.clone() else {
+        return Json(rpc::error("db not open"));
+    };
+    let resp = tokio::task::spawn_blocking(move || rpc::handle(&db, &body))
+        .await
+        .unwrap_or_else(|err| rpc::error(err.to_string()));
+    Json(resp)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bonsai_db_reader::testing::{spec, TestDb};
+    use starknet_types_core::felt::Felt;
+
+    /// Posts `body` to the verify-document handler the way the route would.
+    async fn post_document(body: &serde_json::Value) -> VerifyProofResponse {
+        let Json(doc) = Json::<ProofDocument>::from_bytes(body.to_string().as_bytes()).unwrap();
+        verify_proof_document(Json(doc)).await.0
+    }
+
+    #[tokio::test]
+    async fn verify_document_rejects_tampered_hashes() {
+        let test_db = TestDb::new();
+        test_db.commit(5, &spec(TrieKind::Storage, Some("0x11")), TrieKind::Storage, &[(Felt::ONE, Felt::from(10u64)), (Felt::TWO, Felt::from(20u64))]);
+        let db = test_db.open();
+        let doc = reader::proof_for_key(&db, TrieKind::Storage, Some("0x11".to_string()), "0x1", None).document.unwrap();
+        let mut body = serde_json::to_value(&doc).unwrap();
+        assert!(post_document(&body).await.verified);
+
+        body["nodes"][1]["hash"] = "0x1234".into();
+        let response = post_document(&body).await;
+        assert!(!response.verified);
+        assert!(response.error.unwrap().contains("states hash 0x1234"));
+        // Flattened to a plain request the stated hashes are gone, which is why documents get their own route.
+        assert!(reader::verify_external_proof(&VerifyProofRequest::from(&serde_json::from_value::<ProofDocument>(body).unwrap())).verified);
+    }
+}
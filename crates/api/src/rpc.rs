@@ -0,0 +1,196 @@
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+
+use bonsai_db_reader as reader;
+use bonsai_db_reader::db::RocksDb;
+use bonsai_db_reader::util::hex::parse_felt_hex;
+use bonsai_types::ContractStorageKeys;
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const INTERNAL_ERROR: i64 = -32603;
+// Starknet spec error codes.
+const CONTRACT_NOT_FOUND: i64 = 20;
+const BLOCK_NOT_FOUND: i64 = 24;
+const STORAGE_PROOF_NOT_SUPPORTED: i64 = 42;
+
+const SPEC_VERSION: &str = "0.8.1";
+
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        Self { code, message: message.into() }
+    }
+}
+
+/// Answers a JSON-RPC 2.0 request object or a batch of them.
+pub fn handle(db: &RocksDb, body: &[u8]) -> Value {
+    let request: Value = match serde_json::from_slice(body) {
+        Ok(request) => request,
+        Err(err) => return response(Value::Null, Err(RpcError::new(PARSE_ERROR, err.to_string()))),
+    };
+    match request {
+        Value::Array(requests) if requests.is_empty() => {
+            response(Value::Null, Err(RpcError::new(INVALID_REQUEST, "empty batch")))
+        }
+        Value::Array(requests) => Value::Array(requests.iter().map(|request| handle_one(db, request)).collect()),
+        request => handle_one(db, &request),
+    }
+}
+
+pub fn error(message: impl Into<String>) -> Value {
+    response(Value::Null, Err(RpcError::new(INTERNAL_ERROR, message)))
+}
+
+fn handle_one(db: &RocksDb, request: &Value) -> Value {
+    let id = request.get("id").cloned().unwrap_or(Value::Null);
+    let Some(method) = request.get("method").and_then(Value::as_str) else {
+        return response(id, Err(RpcError::new(INVALID_REQUEST, "missing method")));
+    };
+    let params = request.get("params").cloned().unwrap_or(Value::Array(Vec::new()));
+    let result = match method {
+        "starknet_specVersion" => Ok(json!(SPEC_VERSION)),
+        "starknet_getStorageProof" => get_storage_proof(db, &params),
+        "starknet_getStorageAt" => get_storage_at(db, &params),
+        other => Err(RpcError::new(METHOD_NOT_FOUND, format!("method not found: {other}"))),
+    };
+    response(id, result)
+}
+
+fn get_storage_proof(db: &RocksDb, params: &Value) -> Result<Value, RpcError> {
+    let block = block_id(required(params, 0, "block_id")?)?;
+    let class_hashes: Vec<String> = optional(params, 1, "class_hashes")?.unwrap_or_default();
+    let contract_addresses: Vec<String> = optional(params, 2, "contract_addresses")?.unwrap_or_default();
+    let storage_keys: Vec<ContractStorageKeys> = optional(params, 3, "contracts_storage_keys")?.unwrap_or_default();
+    check_felts(class_hashes.iter().chain(&contract_addresses))?;
+    for entry in &storage_keys {
+        check_felts(std::iter::once(&entry.contract_address).chain(&entry.storage_keys))?;
+    }
+
+    // Historical proofs need the trie logs of every later block; past retention they are unavailable.
+    let proof = reader::storage_proof(db, block, &class_hashes, &contract_addresses, &storage_keys)
+        .map_err(|err| RpcError::new(if block.is_some() { STORAGE_PROOF_NOT_SUPPORTED } else { INTERNAL_ERROR }, err))?;
+    serde_json::to_value(proof).map_err(|err| RpcError::new(INTERNAL_ERROR, err.to_string()))
+}
+
+fn get_storage_at(db: &RocksDb, params: &Value) -> Result<Value, RpcError> {
+    let contract: String = required(params, 0, "contract_address")?;
+    let key: String = required(params, 1, "key")?;
+    let block = block_id(required(params, 2, "block_id")?)?;
+    check_felts([&contract, &key])?;
+
+    let value = reader::storage_at(db, &contract, &key, block)
+        .map_err(|err| RpcError::new(if block.is_some() { BLOCK_NOT_FOUND } else { INTERNAL_ERROR }, err))?
+        .ok_or_else(|| RpcError::new(CONTRACT_NOT_FOUND, "Contract not found"))?;
+    Ok(json!(value))
+}
+
+/// `None` for the latest state. Tags all read the latest state; only `block_number` can go back.
+fn block_id(value: Value) -> Result<Option<u64>, RpcError> {
+    match &value {
+        Value::String(tag) if matches!(tag.as_str(), "latest" | "pending" | "pre_confirmed" | "l1_accepted") => Ok(None),
+        Value::Object(map) => {
+            if let Some(number) = map.get("block_number").and_then(Value::as_u64) {
+                Ok(Some(number))
+            } else if map.contains_key("block_hash") {
+                Err(RpcError::new(BLOCK_NOT_FOUND, "block_hash lookups are not supported, use block_number"))
+            } else {
+                Err(RpcError::new(INVALID_PARAMS, format!("invalid block_id: {value}")))
+            }
+        }
+        _ => Err(RpcError::new(INVALID_PARAMS, format!("invalid block_id: {value}"))),
+    }
+}
+
+fn check_felts<'a>(values: impl IntoIterator<Item = &'a String>) -> Result<(), RpcError> {
+    for value in values {
+        parse_felt_hex(value).map_err(|err| RpcError::new(INVALID_PARAMS, format!("{value}: {err}")))?;
+    }
+    Ok(())
+}
+
+/// Params may be positional or named.
+fn param(params: &Value, index: usize, name: &str) -> Option<Value> {
+    let value = match params {
+        Value::Array(items) => items.get(index),
+        Value::Object(map) => map.get(name),
+        _ => None,
+    };
+    value.filter(|value| !value.is_null()).cloned()
+}
+
+fn optional<T: DeserializeOwned>(params: &Value, index: usize, name: &str) -> Result<Option<T>, RpcError> {
+    param(params, index, name)
+        .map(|value| serde_json::from_value(value).map_err(|err| RpcError::new(INVALID_PARAMS, format!("{name}: {err}"))))
+        .transpose()
+}
+
+fn required<T: DeserializeOwned>(params: &Value, index: usize, name: &str) -> Result<T, RpcError> {
+    optional(params, index, name)?.ok_or_else(|| RpcError::new(INVALID_PARAMS, format!("missing param {name}")))
+}
+
+fn response(id: Value, result: Result<Value, RpcError>) -> Value {
+    match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err(err) => json!({ "jsonrpc": "2.0", "id": id, "error": { "code": err.code, "message": err.message } }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bonsai_db_reader::testing::{spec, TestDb};
+    use bonsai_types::TrieKind;
+    use starknet_types_core::felt::Felt;
+
+    /// Contract 0x11 deployed with slot 0x1 set to 7; nothing at 0x99.
+    fn deployed_contract() -> TestDb {
+        let test_db = TestDb::new();
+        let contract = Felt::from(0x11u64);
+        test_db.commit(5, &spec(TrieKind::Contract, None), TrieKind::Contract, &[(contract, Felt::from(0xabcu64))]);
+        test_db.commit(5, &spec(TrieKind::Storage, Some("0x11")), TrieKind::Storage, &[(Felt::ONE, Felt::from(7u64))]);
+        test_db.deploy(&contract, 5, &Felt::from(0xc1a55u64));
+        test_db
+    }
+
+    fn call(db: &RocksDb, method: &str, params: Value) -> Value {
+        let request = json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params });
+        handle(db, request.to_string().as_bytes())
+    }
+
+    #[test]
+    fn get_storage_at_reports_unknown_contracts() {
+        let test_db = deployed_contract();
+        let db = test_db.open();
+
+        let found = call(&db, "starknet_getStorageAt", json!(["0x11", "0x1", "latest"]));
+        assert_eq!(found["result"], "0x7");
+        let unset = call(&db, "starknet_getStorageAt", json!({ "contract_address": "0x11", "key": "0x2", "block_id": "latest" }));
+        assert_eq!(unset["result"], "0x0");
+        let unknown = call(&db, "starknet_getStorageAt", json!(["0x99", "0x1", "latest"]));
+        assert_eq!(unknown["error"]["code"], CONTRACT_NOT_FOUND);
+        let bad_block = call(&db, "starknet_getStorageAt", json!(["0x11", "0x1", { "block_hash": "0x1" }]));
+        assert_eq!(bad_block["error"]["code"], BLOCK_NOT_FOUND);
+    }
+
+    #[test]
+    fn get_storage_proof_accepts_absent_contracts() {
+        let test_db = deployed_contract();
+        let db = test_db.open();
+
+        let resp = call(&db, "starknet_getStorageProof", json!(["latest", [], ["0x11", "0x99"], [{ "contract_address": "0x11", "storage_keys": ["0x1"] }]]));
+        let leaves = &resp["result"]["contracts_proof"]["contract_leaves_data"];
+        assert_eq!(leaves[0]["class_hash"], "0xc1a55");
+        assert_eq!(leaves[1]["class_hash"], "0x0");
+        assert_eq!(resp["result"]["contracts_storage_proofs"].as_array().map(Vec::len), Some(1));
+
+        let bad = call(&db, "starknet_getStorageProof", json!(["latest", [], ["0xzz"]]));
+        assert_eq!(bad["error"]["code"], INVALID_PARAMS);
+    }
+}
@@ -1,6 +1,38 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
+use parity_scale_codec::Decode;
+use starknet_types_core::felt::Felt;
+
+use crate::bonsai::node::Node;
 use crate::bonsai::path::PathBits;
 use crate::db::RocksDb;
-use bonsai_types::TrieKind;
+use bonsai_types::{ChangeKind, TrieKind};
+
+pub const KEY_TYPE_TRIE: u8 = 0;
+pub const KEY_TYPE_FLAT: u8 = 1;
+pub const CHANGE_NEW: u8 = 0;
+pub const CHANGE_OLD: u8 = 1;
+
+#[derive(Debug, thiserror::Error)]
+pub enum LogsUnavailable {
+    #[error("logs unavailable for block {block}: no trie logs in {log_cf}")]
+    NoLogs { block: u64, log_cf: String },
+    #[error("logs unavailable for block {block}: pruned, earliest logged block is {earliest}")]
+    Pruned { block: u64, earliest: u64 },
+    #[error("logs unavailable for block {block}: latest logged block is {latest}")]
+    NotLogged { block: u64, latest: u64 },
+}
+
+/// Blocks a log CF still covers. `gaps` are inclusive runs of blocks between
+/// `earliest` and `latest` with no log entries.
+#[derive(Debug, Clone, Default)]
+pub struct LogRetention {
+    pub earliest: Option<u64>,
+    pub latest: Option<u64>,
+    pub logged_blocks: u64,
+    pub gaps: Vec<(u64, u64)>,
+}
 
 #[derive(Debug, Clone)]
 pub struct TrieLogEntry {
@@ -8,51 +40,297 @@ pub struct TrieLogEntry {
     pub trie_kind: TrieKind,
     pub identifier: Vec<u8>,
     pub key_bits: Option<PathBits>,
+    /// Raw key as stored in the trie/flat CF: identifier followed by the encoded path.
+    pub trie_key: Vec<u8>,
     pub key_type: u8,
     pub change_type: u8,
     pub value: Vec<u8>,
 }
 
+/// Net effect of a block range on one key: the value before the range and after it.
+#[derive(Debug, Clone)]
+pub struct RangeChange {
+    pub trie_kind: TrieKind,
+    pub identifier: Vec<u8>,
+    pub key_bits: Option<PathBits>,
+    pub trie_key: Vec<u8>,
+    pub key_type: u8,
+    pub first_block: u64,
+    pub last_block: u64,
+    pub old: Option<Vec<u8>>,
+    pub new: Option<Vec<u8>>,
+}
+
+/// A trie-log value decoded according to its key type.
+#[derive(Debug, Clone)]
+pub enum LogValue {
+    Felt(Felt),
+    Node(Node),
+    Raw(Vec<u8>),
+}
+
+impl LogValue {
+    pub fn decode(key_type: u8, bytes: &[u8]) -> Self {
+        let decoded = match key_type {
+            KEY_TYPE_FLAT => Felt::decode(&mut &bytes[..]).ok().map(LogValue::Felt),
+            KEY_TYPE_TRIE => Node::decode(&mut &bytes[..]).ok().map(LogValue::Node),
+            _ => None,
+        };
+        decoded.unwrap_or_else(|| LogValue::Raw(bytes.to_vec()))
+    }
+}
+
+/// The `old` and `new` records a block logged for one key, joined into a single change.
+#[derive(Debug, Clone)]
+pub struct TrieChange {
+    pub block: u64,
+    pub trie_kind: TrieKind,
+    pub identifier: Vec<u8>,
+    pub key_bits: Option<PathBits>,
+    pub trie_key: Vec<u8>,
+    pub key_type: u8,
+    pub kind: ChangeKind,
+    pub before: Option<LogValue>,
+    pub after: Option<LogValue>,
+}
+
 pub fn read_block_log(
     db: &RocksDb,
     log_cf: &str,
     block: u64,
 ) -> Vec<TrieLogEntry> {
-    let mut prefix = block.to_be_bytes().to_vec();
-    prefix.push(0x00);
+    read_log_range(db, log_cf, block, Some(block))
+}
 
+/// Reads every log entry from block `from` up to `to` (inclusive, or the end of the CF).
+pub fn read_log_range(
+    db: &RocksDb,
+    log_cf: &str,
+    from: u64,
+    to: Option<u64>,
+) -> Vec<TrieLogEntry> {
     let mut entries = Vec::new();
-    let iter = match db.iter_cf_from(log_cf, &prefix) {
+    let iter = match db.iter_cf_from(log_cf, &from.to_be_bytes()) {
         Ok(iter) => iter,
         Err(_) => return entries,
     };
 
     for (key, value) in iter {
-        if !key.starts_with(&prefix) {
+        let Some(block) = log_key_block(&key) else {
+            continue;
+        };
+        if to.is_some_and(|to| block > to) {
             break;
         }
-        if key.len() < 8 + 1 + 2 {
-            continue;
+        if let Some(entry) = parse_log_entry(block, &key, value) {
+            entries.push(entry);
         }
-        let key_type = key[key.len() - 2];
-        let change_type = key[key.len() - 1];
-        let trie_key_bytes = &key[(8 + 1)..(key.len() - 2)];
-
-        let (trie_kind, identifier, key_bits) = parse_trie_key(trie_key_bytes);
-        entries.push(TrieLogEntry {
-            block,
-            trie_kind,
-            identifier,
-            key_bits,
-            key_type,
-            change_type,
-            value,
-        });
     }
 
     entries
 }
 
+/// Joins log entries that share a block and key. A key with only a `new` record was
+/// inserted, one with only an `old` record was deleted.
+pub fn pair_changes(entries: Vec<TrieLogEntry>) -> Vec<TrieChange> {
+    struct Pending {
+        entry: TrieLogEntry,
+        old: Option<Vec<u8>>,
+        new: Option<Vec<u8>>,
+    }
+
+    let mut pending: BTreeMap<(u64, u8, Vec<u8>), Pending> = BTreeMap::new();
+    for entry in entries {
+        let slot = pending
+            .entry((entry.block, entry.key_type, entry.trie_key.clone()))
+            .or_insert_with(|| Pending { entry: entry.clone(), old: None, new: None });
+        match entry.change_type {
+            CHANGE_OLD => slot.old = Some(entry.value),
+            CHANGE_NEW => slot.new = Some(entry.value),
+            _ => {}
+        }
+    }
+
+    pending
+        .into_values()
+        .filter_map(|Pending { entry, old, new }| {
+            let kind = match (&old, &new) {
+                (None, Some(_)) => ChangeKind::Insert,
+                (Some(_), Some(_)) => ChangeKind::Update,
+                (Some(_), None) => ChangeKind::Delete,
+                (None, None) => return None,
+            };
+            Some(TrieChange {
+                block: entry.block,
+                trie_kind: entry.trie_kind,
+                identifier: entry.identifier,
+                key_bits: entry.key_bits,
+                trie_key: entry.trie_key,
+                key_type: entry.key_type,
+                kind,
+                before: old.map(|v| LogValue::decode(entry.key_type, &v)),
+                after: new.map(|v| LogValue::decode(entry.key_type, &v)),
+            })
+        })
+        .collect()
+}
+
+/// Every entry of `log_cf` in block order, read lazily; empty if the CF is missing.
+pub fn log_entries<'a>(db: &'a RocksDb, log_cf: &str) -> impl Iterator<Item = TrieLogEntry> + 'a {
+    log_entries_from(db, log_cf, 0)
+}
+
+/// Like [`log_entries`], starting at block `from`.
+pub fn log_entries_from<'a>(db: &'a RocksDb, log_cf: &str, from: u64) -> impl Iterator<Item = TrieLogEntry> + 'a {
+    db.iter_cf_from(log_cf, &from.to_be_bytes())
+        .into_iter()
+        .flatten()
+        .filter_map(|(key, value)| parse_log_entry(log_key_block(&key)?, &key, value))
+}
+
+/// Every change `log_cf` records for one leaf, oldest first. The log is keyed by block,
+/// so this scans all of it.
+pub fn key_history(db: &RocksDb, log_cf: &str, trie_key: &[u8]) -> Vec<TrieChange> {
+    let entries = log_entries(db, log_cf)
+        .filter(|entry| entry.key_type == KEY_TYPE_FLAT && entry.trie_key == trie_key)
+        .collect();
+    pair_changes(entries)
+}
+
+/// Per log CF, the block that first inserted each flat key.
+pub type FirstInsertCache = HashMap<String, Arc<BTreeMap<Vec<u8>, u64>>>;
+
+/// First block in `log_cf` that inserted each flat key, i.e. logged a `new` leaf value
+/// with no `old` one. Keys inserted before the earliest logged block are left out. This
+/// scans the whole log, so the result is kept on `db` until it catches up.
+pub fn first_inserted_blocks(db: &RocksDb, log_cf: &str) -> Arc<BTreeMap<Vec<u8>, u64>> {
+    if let Some(inserted) = db.first_insert_cache().get(log_cf) {
+        return Arc::clone(inserted);
+    }
+    let entries = log_entries(db, log_cf).filter(|entry| entry.key_type == KEY_TYPE_FLAT).collect();
+    let mut inserted = BTreeMap::new();
+    for change in pair_changes(entries) {
+        if change.kind == ChangeKind::Insert {
+            // Changes come in block order, so the first insert of a key wins.
+            inserted.entry(change.trie_key).or_insert(change.block);
+        }
+    }
+    let inserted = Arc::new(inserted);
+    db.first_insert_cache().insert(log_cf.to_string(), Arc::clone(&inserted));
+    inserted
+}
+
+/// Merges every log entry in `[start, end]` into one change per key, keeping the
+/// `old` value of the first block and the `new` value of the last block that touched it.
+/// Keys that end up where they started are left out.
+pub fn aggregate_range(db: &RocksDb, log_cf: &str, start: u64, end: u64) -> Vec<RangeChange> {
+    let mut changes: BTreeMap<(u8, Vec<u8>), RangeChange> = BTreeMap::new();
+    for entry in read_log_range(db, log_cf, start, Some(end)) {
+        let change = changes
+            .entry((entry.key_type, entry.trie_key.clone()))
+            .or_insert_with(|| RangeChange {
+                trie_kind: entry.trie_kind,
+                identifier: entry.identifier.clone(),
+                key_bits: entry.key_bits.clone(),
+                trie_key: entry.trie_key.clone(),
+                key_type: entry.key_type,
+                first_block: entry.block,
+                last_block: entry.block,
+                old: None,
+                new: None,
+            });
+        if entry.block > change.last_block {
+            // A later block supersedes the new value; a missing `new` record means a delete.
+            change.last_block = entry.block;
+            change.new = None;
+        }
+        match entry.change_type {
+            CHANGE_OLD if entry.block == change.first_block => change.old = Some(entry.value),
+            CHANGE_NEW => change.new = Some(entry.value),
+            _ => {}
+        }
+    }
+    changes.into_values().filter(|c| c.old != c.new).collect()
+}
+
+/// First block that still has log entries in `log_cf`.
+pub fn earliest_logged_block(db: &RocksDb, log_cf: &str) -> Option<u64> {
+    let mut iter = db.iter_cf_from(log_cf, &[]).ok()?;
+    iter.find_map(|(key, _)| log_key_block(&key))
+}
+
+/// Last block that has log entries in `log_cf`.
+pub fn latest_logged_block(db: &RocksDb, log_cf: &str) -> Option<u64> {
+    db.last_key_cf(log_cf).ok()?.and_then(|key| log_key_block(&key))
+}
+
+/// Fails unless `block` lies between the earliest and latest logged blocks.
+pub fn check_block_logged(db: &RocksDb, log_cf: &str, block: u64) -> Result<(), LogsUnavailable> {
+    let (Some(earliest), Some(latest)) = (earliest_logged_block(db, log_cf), latest_logged_block(db, log_cf)) else {
+        return Err(LogsUnavailable::NoLogs { block, log_cf: log_cf.to_string() });
+    };
+    if block < earliest {
+        return Err(LogsUnavailable::Pruned { block, earliest });
+    }
+    if block > latest {
+        return Err(LogsUnavailable::NotLogged { block, latest });
+    }
+    Ok(())
+}
+
+/// Walks the distinct blocks of `log_cf`, seeking past each block's entries
+/// instead of reading them.
+pub fn scan_retention(db: &RocksDb, log_cf: &str) -> LogRetention {
+    let mut retention = LogRetention::default();
+    let mut cursor = 0u64;
+    loop {
+        let Ok(mut iter) = db.iter_cf_from(log_cf, &cursor.to_be_bytes()) else {
+            break;
+        };
+        let Some(block) = iter.find_map(|(key, _)| log_key_block(&key)) else {
+            break;
+        };
+        match retention.latest {
+            Some(prev) if block > prev + 1 => retention.gaps.push((prev + 1, block - 1)),
+            None => retention.earliest = Some(block),
+            _ => {}
+        }
+        retention.latest = Some(block);
+        retention.logged_blocks += 1;
+        let Some(next) = block.checked_add(1) else {
+            break;
+        };
+        cursor = next;
+    }
+    retention
+}
+
+fn log_key_block(key: &[u8]) -> Option<u64> {
+    let block_bytes: [u8; 8] = key.get(..8)?.try_into().ok()?;
+    Some(u64::from_be_bytes(block_bytes))
+}
+
+fn parse_log_entry(block: u64, key: &[u8], value: Vec<u8>) -> Option<TrieLogEntry> {
+    if key.len() < 8 + 1 + 2 || key[8] != 0x00 {
+        return None;
+    }
+    let key_type = key[key.len() - 2];
+    let change_type = key[key.len() - 1];
+    let trie_key_bytes = &key[(8 + 1)..(key.len() - 2)];
+
+    let (trie_kind, identifier, key_bits) = parse_trie_key(trie_key_bytes);
+    Some(TrieLogEntry {
+        block,
+        trie_kind,
+        identifier,
+        key_bits,
+        trie_key: trie_key_bytes.to_vec(),
+        key_type,
+        change_type,
+        value,
+    })
+}
+
 fn parse_trie_key(bytes: &[u8]) -> (TrieKind, Vec<u8>, Option<PathBits>) {
     let contract_prefix = TrieKind::Contract.identifier();
     if bytes.starts_with(contract_prefix) {
@@ -86,3 +364,99 @@ fn parse_trie_key(bytes: &[u8]) -> (TrieKind, Vec<u8>, Option<PathBits>) {
 
     (TrieKind::Contract, bytes.to_vec(), None)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bonsai::path::path_to_felt;
+    use crate::bonsai::trie_reader::TrieSpec;
+    use crate::build_spec;
+    use crate::testing::TestDb;
+
+    fn felt(value: u64) -> Felt {
+        Felt::from(value)
+    }
+
+    fn storage_spec() -> TrieSpec {
+        build_spec(TrieKind::Storage, Some("0x11".to_string())).unwrap()
+    }
+
+    fn leaf_key(bits: &Option<PathBits>) -> Felt {
+        path_to_felt(bits.as_ref().unwrap())
+    }
+
+    fn log_felt(value: &Option<LogValue>) -> Option<Felt> {
+        match value {
+            Some(LogValue::Felt(felt)) => Some(*felt),
+            None => None,
+            Some(other) => panic!("not a felt: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn pair_changes_joins_old_and_new_records() {
+        let test_db = TestDb::new();
+        let spec = storage_spec();
+        test_db.commit(5, &spec, TrieKind::Storage, &[(felt(1), felt(10))]);
+        test_db.commit(6, &spec, TrieKind::Storage, &[(felt(1), felt(11)), (felt(2), felt(20))]);
+        test_db.commit(7, &spec, TrieKind::Storage, &[(felt(2), felt(20))]);
+        let db = test_db.open();
+
+        let flat = |block| -> Vec<(Felt, ChangeKind, Option<Felt>, Option<Felt>)> {
+            pair_changes(read_block_log(&db, &spec.log_cf, block))
+                .into_iter()
+                .filter(|change| change.key_type == KEY_TYPE_FLAT)
+                .map(|change| (leaf_key(&change.key_bits), change.kind, log_felt(&change.before), log_felt(&change.after)))
+                .collect()
+        };
+        assert_eq!(flat(5), vec![(felt(1), ChangeKind::Insert, None, Some(felt(10)))]);
+        assert_eq!(
+            flat(6),
+            vec![(felt(1), ChangeKind::Update, Some(felt(10)), Some(felt(11))), (felt(2), ChangeKind::Insert, None, Some(felt(20)))]
+        );
+        assert_eq!(flat(7), vec![(felt(1), ChangeKind::Delete, Some(felt(11)), None)]);
+
+        // Node records pair up the same way and decode as nodes.
+        let nodes = pair_changes(read_block_log(&db, &spec.log_cf, 6));
+        let root = nodes.iter().find(|change| change.key_type == KEY_TYPE_TRIE && change.key_bits.as_ref().is_some_and(PathBits::is_empty)).unwrap();
+        assert_eq!(root.kind, ChangeKind::Update);
+        assert!(matches!(root.after, Some(LogValue::Node(_))));
+    }
+
+    #[test]
+    fn aggregate_range_keeps_net_changes() {
+        let test_db = TestDb::new();
+        let spec = storage_spec();
+        test_db.commit(5, &spec, TrieKind::Storage, &[(felt(1), felt(10)), (felt(2), felt(20))]);
+        test_db.commit(6, &spec, TrieKind::Storage, &[(felt(1), felt(11)), (felt(2), felt(20)), (felt(3), felt(30))]);
+        test_db.commit(7, &spec, TrieKind::Storage, &[(felt(1), felt(10)), (felt(3), felt(31))]);
+        let db = test_db.open();
+
+        let decode = |bytes: &Option<Vec<u8>>| bytes.as_ref().map(|bytes| Felt::decode(&mut bytes.as_slice()).unwrap());
+        let flat: Vec<_> = aggregate_range(&db, &spec.log_cf, 6, 7)
+            .into_iter()
+            .filter(|change| change.key_type == KEY_TYPE_FLAT)
+            .map(|change| (leaf_key(&change.key_bits), change.first_block, change.last_block, decode(&change.old), decode(&change.new)))
+            .collect();
+        // Key 1 went 10 -> 11 -> 10 and is left out.
+        assert_eq!(flat, vec![(felt(2), 7, 7, Some(felt(20)), None), (felt(3), 6, 7, None, Some(felt(31)))]);
+    }
+
+    #[test]
+    fn first_inserts_are_scanned_once() {
+        let test_db = TestDb::new();
+        let spec = storage_spec();
+        test_db.commit(5, &spec, TrieKind::Storage, &[(felt(1), felt(10))]);
+        test_db.commit(6, &spec, TrieKind::Storage, &[(felt(1), felt(11)), (felt(2), felt(20))]);
+        test_db.commit(7, &spec, TrieKind::Storage, &[(felt(2), felt(20))]);
+        test_db.commit(8, &spec, TrieKind::Storage, &[(felt(1), felt(12)), (felt(2), felt(20))]);
+        let db = test_db.open();
+
+        let inserted = first_inserted_blocks(&db, &spec.log_cf);
+        let blocks: Vec<(Felt, u64)> =
+            inserted.iter().map(|(key, block)| (path_to_felt(&PathBits::from_encoded(&key[spec.identifier.len()..])), *block)).collect();
+        // Key 1 was deleted at block 7 and inserted again at 8; its first insert stays.
+        assert_eq!(blocks, vec![(felt(1), 5), (felt(2), 6)]);
+        assert!(Arc::ptr_eq(&inserted, &first_inserted_blocks(&db, &spec.log_cf)));
+    }
+}
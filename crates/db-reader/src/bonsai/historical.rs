@@ -0,0 +1,239 @@
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::Arc;
+
+use crate::bonsai::diff_reader::{
+    earliest_logged_block, latest_logged_block, read_log_range, LogsUnavailable, CHANGE_OLD, KEY_TYPE_FLAT, KEY_TYPE_TRIE,
+};
+use crate::bonsai::trie_reader::{TrieReader, TrieSpec};
+use crate::db::{cf_map, RocksDb};
+
+/// Values every key had at `block`, for keys that changed after it.
+/// `None` means the key did not exist yet at that block.
+#[derive(Debug, Clone, Default)]
+pub struct LogOverlay {
+    block: u64,
+    trie: HashMap<Vec<u8>, Option<Vec<u8>>>,
+    flat: HashMap<Vec<u8>, Option<Vec<u8>>>,
+}
+
+impl LogOverlay {
+    pub fn block(&self) -> u64 {
+        self.block
+    }
+
+    pub fn trie_value(&self, key: &[u8]) -> Option<Option<&[u8]>> {
+        self.trie.get(key).map(|v| v.as_deref())
+    }
+
+    pub fn flat_value(&self, key: &[u8]) -> Option<Option<&[u8]>> {
+        self.flat.get(key).map(|v| v.as_deref())
+    }
+
+    /// Pinned flat keys that start with `prefix` and sort at or after `from`, in key order.
+    pub fn flat_entries_from(&self, prefix: &[u8], from: &[u8]) -> BTreeMap<Vec<u8>, Option<Vec<u8>>> {
+        self.flat
+            .iter()
+            .filter(|(key, _)| key.starts_with(prefix) && key.as_slice() >= from)
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect()
+    }
+}
+
+/// Rewinds the trie described by `spec` to the state right after `block` was committed.
+///
+/// Every key changed in a later block is pinned to the `old` record of the first
+/// such change; keys first written after `block` are pinned to "absent".
+pub fn build_overlay(db: &RocksDb, spec: &TrieSpec, block: u64) -> Result<LogOverlay, LogsUnavailable> {
+    let Some(earliest) = earliest_logged_block(db, &spec.log_cf) else {
+        return Err(LogsUnavailable::NoLogs { block, log_cf: spec.log_cf.clone() });
+    };
+    // Reverting to `block` needs the logs of every block after it.
+    if block.saturating_add(1) < earliest {
+        return Err(LogsUnavailable::Pruned { block, earliest });
+    }
+    // Past the chain tip there is nothing to revert, so the latest state would be passed
+    // off as the state at `block`. Blocks that only touched other tries still count, so
+    // the tip is the last block any trie logged.
+    let latest = [cf_map::BONSAI_CONTRACT_LOG, cf_map::BONSAI_CONTRACT_STORAGE_LOG, cf_map::BONSAI_CLASS_LOG]
+        .into_iter()
+        .filter_map(|log_cf| latest_logged_block(db, log_cf))
+        .max()
+        .unwrap_or(earliest);
+    if block > latest {
+        return Err(LogsUnavailable::NotLogged { block, latest });
+    }
+
+    let mut overlay = LogOverlay { block, ..Default::default() };
+    let mut first_seen: HashMap<(u8, Vec<u8>), u64> = HashMap::new();
+    for entry in read_log_range(db, &spec.log_cf, block.saturating_add(1), None) {
+        if !entry.trie_key.starts_with(&spec.identifier) {
+            continue;
+        }
+        let target = match entry.key_type {
+            KEY_TYPE_TRIE => &mut overlay.trie,
+            KEY_TYPE_FLAT => &mut overlay.flat,
+            _ => continue,
+        };
+        let seen = first_seen.entry((entry.key_type, entry.trie_key.clone())).or_insert(entry.block);
+        if *seen != entry.block {
+            continue;
+        }
+        let slot = target.entry(entry.trie_key).or_insert(None);
+        if entry.change_type == CHANGE_OLD {
+            *slot = Some(entry.value);
+        }
+    }
+    Ok(overlay)
+}
+
+/// Overlays a `RocksDb` keeps around; building one scans every log entry after its block.
+const MAX_CACHED_OVERLAYS: usize = 64;
+
+type OverlayKey = (String, Vec<u8>, u64);
+
+/// Overlays already built, keyed by log CF, trie identifier and block. Shared by every
+/// clone of a `RocksDb` and cleared when a secondary instance catches up, since new
+/// blocks change what has to be reverted.
+#[derive(Debug, Default)]
+pub struct OverlayCache {
+    overlays: HashMap<OverlayKey, Arc<LogOverlay>>,
+    /// Keys oldest first, for eviction.
+    order: VecDeque<OverlayKey>,
+    /// Bumped on every clear, so an overlay built across a catch-up is not stored.
+    generation: u64,
+}
+
+impl OverlayCache {
+    pub fn clear(&mut self) {
+        self.overlays.clear();
+        self.order.clear();
+        self.generation += 1;
+    }
+
+    fn insert(&mut self, key: OverlayKey, overlay: Arc<LogOverlay>) {
+        if self.overlays.insert(key.clone(), overlay).is_none() {
+            self.order.push_back(key);
+        }
+        while self.order.len() > MAX_CACHED_OVERLAYS {
+            if let Some(oldest) = self.order.pop_front() {
+                self.overlays.remove(&oldest);
+            }
+        }
+    }
+}
+
+/// The overlay for `spec` at `block`, built once per DB and block.
+pub fn cached_overlay(db: &RocksDb, spec: &TrieSpec, block: u64) -> Result<Arc<LogOverlay>, LogsUnavailable> {
+    let key = (spec.log_cf.clone(), spec.identifier.clone(), block);
+    let generation = {
+        let cache = db.overlay_cache();
+        if let Some(overlay) = cache.overlays.get(&key) {
+            return Ok(Arc::clone(overlay));
+        }
+        cache.generation
+    };
+    let overlay = Arc::new(build_overlay(db, spec, block)?);
+    let mut cache = db.overlay_cache();
+    if cache.generation == generation {
+        cache.insert(key, Arc::clone(&overlay));
+    }
+    Ok(overlay)
+}
+
+pub fn historical_reader(db: RocksDb, spec: TrieSpec, block: u64) -> Result<TrieReader, LogsUnavailable> {
+    let overlay = cached_overlay(&db, &spec, block)?;
+    Ok(TrieReader::new(db, spec).with_overlay(overlay))
+}
+
+#[cfg(test)]
+mod tests {
+    use starknet_types_core::felt::Felt;
+
+    use super::*;
+    use crate::bonsai::path::felt_to_path;
+    use crate::testing::TestDb;
+    use crate::{build_spec, root_hash};
+    use bonsai_types::TrieKind;
+
+    fn felt(value: u64) -> Felt {
+        Felt::from(value)
+    }
+
+    #[test]
+    fn overlay_rewinds_nodes_and_leaves() {
+        let test_db = TestDb::new();
+        let spec = build_spec(TrieKind::Storage, Some("0x11".to_string())).unwrap();
+        let roots = [
+            test_db.commit(5, &spec, TrieKind::Storage, &[(felt(1), felt(10))]),
+            test_db.commit(6, &spec, TrieKind::Storage, &[(felt(1), felt(11)), (felt(2), felt(20))]),
+            test_db.commit(7, &spec, TrieKind::Storage, &[(felt(2), felt(20))]),
+        ];
+        let db = test_db.open();
+
+        let expected = [(4, Felt::ZERO, None, None), (5, roots[0], Some(10), None), (6, roots[1], Some(11), Some(20)), (7, roots[2], None, Some(20))];
+        for (block, root, key1, key2) in expected {
+            let mut reader = historical_reader(db.clone(), spec.clone(), block).unwrap();
+            assert_eq!(root_hash(&mut reader), Some(root), "root at block {block}");
+            assert_eq!(reader.load_flat_value(&felt_to_path(&felt(1))), key1.map(felt), "key 1 at block {block}");
+            assert_eq!(reader.load_flat_value(&felt_to_path(&felt(2))), key2.map(felt), "key 2 at block {block}");
+        }
+    }
+
+    #[test]
+    fn overlay_rejects_blocks_outside_the_logs() {
+        let test_db = TestDb::new();
+        let spec = build_spec(TrieKind::Storage, Some("0x11".to_string())).unwrap();
+        test_db.commit(5, &spec, TrieKind::Storage, &[(felt(1), felt(10))]);
+        test_db.commit(6, &spec, TrieKind::Storage, &[(felt(1), felt(11))]);
+        let db = test_db.open();
+
+        assert!(matches!(build_overlay(&db, &spec, 3), Err(LogsUnavailable::Pruned { block: 3, earliest: 5 })));
+        assert!(matches!(build_overlay(&db, &spec, 7), Err(LogsUnavailable::NotLogged { block: 7, latest: 6 })));
+
+        // A later block logged by another trie moves the tip.
+        let contract = build_spec(TrieKind::Contract, None).unwrap();
+        test_db.commit(8, &contract, TrieKind::Contract, &[(felt(0x11), felt(1))]);
+        let db = test_db.open();
+        assert_eq!(build_overlay(&db, &spec, 8).unwrap().block(), 8);
+    }
+
+    #[test]
+    fn overlays_are_built_once_per_block() {
+        let test_db = TestDb::new();
+        let spec = build_spec(TrieKind::Storage, Some("0x11".to_string())).unwrap();
+        test_db.commit(5, &spec, TrieKind::Storage, &[(felt(1), felt(10))]);
+        test_db.commit(6, &spec, TrieKind::Storage, &[(felt(1), felt(11))]);
+        let db = test_db.open();
+
+        let first = cached_overlay(&db, &spec, 5).unwrap();
+        assert!(Arc::ptr_eq(&first, &cached_overlay(&db.clone(), &spec, 5).unwrap()));
+        assert!(!Arc::ptr_eq(&first, &cached_overlay(&db, &spec, 6).unwrap()));
+
+        let other = build_spec(TrieKind::Storage, Some("0x12".to_string())).unwrap();
+        assert!(!Arc::ptr_eq(&first, &cached_overlay(&db, &other, 5).unwrap()));
+
+        db.overlay_cache().clear();
+        assert!(!Arc::ptr_eq(&first, &cached_overlay(&db, &spec, 5).unwrap()));
+    }
+
+    #[test]
+    fn flat_leaves_merge_the_overlay() {
+        let test_db = TestDb::new();
+        let spec = build_spec(TrieKind::Storage, Some("0x11".to_string())).unwrap();
+        test_db.commit(5, &spec, TrieKind::Storage, &[(felt(1), felt(10)), (felt(2), felt(20)), (felt(3), felt(30))]);
+        // Block 6 deletes key 2, updates key 3 and inserts key 4.
+        test_db.commit(6, &spec, TrieKind::Storage, &[(felt(1), felt(10)), (felt(3), felt(31)), (felt(4), felt(40))]);
+        let db = test_db.open();
+        let leaves = |reader: &TrieReader, from: u64| reader.flat_leaves_from(&felt(from)).unwrap().collect::<Vec<_>>();
+        let expect = |leaves: &[(u64, u64)]| leaves.iter().map(|(key, value)| (felt(*key), Some(felt(*value)))).collect::<Vec<_>>();
+
+        let latest = TrieReader::new(db.clone(), spec.clone());
+        assert_eq!(leaves(&latest, 0), expect(&[(1, 10), (3, 31), (4, 40)]));
+
+        // Key 2 comes back from the overlay between the stored keys 1 and 3.
+        let at_5 = historical_reader(db, spec, 5).unwrap();
+        assert_eq!(leaves(&at_5, 0), expect(&[(1, 10), (2, 20), (3, 30)]));
+        assert_eq!(leaves(&at_5, 2), expect(&[(2, 20), (3, 30)]));
+        assert_eq!(leaves(&at_5, 4), expect(&[]));
+    }
+}
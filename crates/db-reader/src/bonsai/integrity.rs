@@ -0,0 +1,160 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use starknet_types_core::felt::Felt;
+
+use crate::bonsai::node::{Node, NodeHandle};
+use crate::bonsai::path::{PathBits, LEAF_PATH_LEN};
+use crate::bonsai::proof::ProofNode;
+use crate::bonsai::trie_reader::TrieReader;
+use bonsai_types::TrieKind;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MismatchKind {
+    /// The node's stored hash differs from the hash recomputed from its children.
+    NodeHash,
+    /// The parent's handle for this child differs from the child's recomputed hash.
+    ChildHash,
+    MissingNode,
+    MissingLeaf,
+}
+
+/// `expected` is the recomputed hash (or the parent's handle for missing entries),
+/// `found` is what storage holds.
+#[derive(Debug, Clone)]
+pub struct Mismatch {
+    pub path: PathBits,
+    pub kind: MismatchKind,
+    pub expected: Option<Felt>,
+    pub found: Option<Felt>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct IntegrityReport {
+    pub nodes_checked: u64,
+    pub leaves_checked: u64,
+    pub stored_root: Option<Felt>,
+    pub computed_root: Option<Felt>,
+    pub mismatches: Vec<Mismatch>,
+}
+
+/// Recomputes every node hash bottom-up and compares it with what the trie stores.
+/// `progress` is bumped once per visited node so callers can poll a running check.
+pub fn check_integrity(reader: &TrieReader, kind: TrieKind, progress: &AtomicU64) -> IntegrityReport {
+    let mut checker = Checker { reader, kind, progress, report: IntegrityReport::default() };
+    let root_path = TrieReader::root_path();
+    if let Some(root) = reader.load_node_uncached(&root_path) {
+        checker.report.stored_root = match &root {
+            Node::Binary(binary) => binary.hash,
+            Node::Edge(edge) => edge.hash,
+        };
+        checker.report.computed_root = Some(checker.node_hash(&root_path, &root));
+    }
+    checker.report
+}
+
+struct Checker<'a> {
+    reader: &'a TrieReader,
+    kind: TrieKind,
+    progress: &'a AtomicU64,
+    report: IntegrityReport,
+}
+
+impl Checker<'_> {
+    fn node_hash(&mut self, path: &PathBits, node: &Node) -> Felt {
+        self.report.nodes_checked += 1;
+        self.progress.fetch_add(1, Ordering::Relaxed);
+
+        let (computed, stored) = match node {
+            Node::Binary(binary) => {
+                let left = self.child_hash(&path.with_bit(false), binary.left);
+                let right = self.child_hash(&path.with_bit(true), binary.right);
+                (ProofNode::Binary { left, right }.hash(self.kind), binary.hash)
+            }
+            Node::Edge(edge) => {
+                let mut child_path = path.clone();
+                child_path.extend_from_bitslice(&edge.path.0);
+                let child = self.child_hash(&child_path, edge.child);
+                let proof_node = ProofNode::Edge { child, path: PathBits(edge.path.0.clone()) };
+                (proof_node.hash(self.kind), edge.hash)
+            }
+        };
+        if let Some(stored) = stored.filter(|stored| *stored != computed) {
+            self.mismatch(path, MismatchKind::NodeHash, Some(computed), Some(stored));
+        }
+        computed
+    }
+
+    /// Hash of the child at `path`; leaves hash to their flat value. Falls back to the
+    /// parent's handle when the child is missing so the walk can go on.
+    fn child_hash(&mut self, path: &PathBits, handle: NodeHandle) -> Felt {
+        let stored = handle.as_hash();
+        let computed = if path.len() >= LEAF_PATH_LEN {
+            self.report.leaves_checked += 1;
+            let value = self.reader.load_flat_value(path);
+            if value.is_none() {
+                self.mismatch(path, MismatchKind::MissingLeaf, stored, None);
+            }
+            value
+        } else {
+            match self.reader.load_node_uncached(path) {
+                Some(node) => Some(self.node_hash(path, &node)),
+                None => {
+                    self.mismatch(path, MismatchKind::MissingNode, stored, None);
+                    None
+                }
+            }
+        };
+        if let (Some(computed), Some(stored)) = (computed, stored) {
+            if computed != stored {
+                self.mismatch(path, MismatchKind::ChildHash, Some(computed), Some(stored));
+            }
+        }
+        computed.or(stored).unwrap_or(Felt::ZERO)
+    }
+
+    fn mismatch(&mut self, path: &PathBits, kind: MismatchKind, expected: Option<Felt>, found: Option<Felt>) {
+        self.report.mismatches.push(Mismatch { path: path.clone(), kind, expected, found });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parity_scale_codec::Encode;
+
+    use crate::testing::{spec, TestDb};
+
+    fn felt(value: u64) -> Felt {
+        Felt::from(value)
+    }
+
+    #[test]
+    fn reports_corrupted_node_hashes() {
+        let test_db = TestDb::new();
+        let spec = spec(TrieKind::Storage, Some("0x11"));
+        let root = test_db.commit(5, &spec, TrieKind::Storage, &[(felt(1), felt(10)), (felt(2), felt(20)), (felt(4), felt(40))]);
+
+        let reader = TrieReader::new(test_db.open(), spec.clone());
+        let report = check_integrity(&reader, TrieKind::Storage, &AtomicU64::new(0));
+        assert!(report.mismatches.is_empty(), "{:?}", report.mismatches);
+        assert_eq!((report.stored_root, report.computed_root), (Some(root), Some(root)));
+
+        // Overwrite the stored hash of the binary node under the root edge.
+        let Some(Node::Edge(edge)) = reader.load_node_uncached(&TrieReader::root_path()) else { panic!("root is not an edge") };
+        let path = PathBits(edge.path.0.clone());
+        let Some(Node::Binary(mut binary)) = reader.load_node_uncached(&path) else { panic!("no binary node under the root") };
+        let real = binary.hash.unwrap();
+        binary.hash = Some(felt(0x1234));
+        let mut key = spec.identifier.clone();
+        key.extend_from_slice(&path.to_bytes());
+        test_db.put(&spec.trie_cf, &key, &Node::Binary(binary).encode());
+
+        let reader = TrieReader::new(test_db.open(), spec);
+        let report = check_integrity(&reader, TrieKind::Storage, &AtomicU64::new(0));
+        assert_eq!(report.computed_root, Some(root));
+        assert_eq!(report.mismatches.len(), 1, "{:?}", report.mismatches);
+        let mismatch = &report.mismatches[0];
+        assert_eq!((mismatch.kind, &mismatch.path), (MismatchKind::NodeHash, &path));
+        assert_eq!((mismatch.expected, mismatch.found), (Some(real), Some(felt(0x1234))));
+    }
+}
@@ -1,10 +1,12 @@
+use std::collections::HashMap;
+
 use starknet_types_core::felt::Felt;
 use starknet_types_core::hash::{Pedersen, Poseidon, StarkHash};
 
 use crate::bonsai::node::Node;
 use crate::bonsai::path::{path_to_felt, PathBits};
 use crate::bonsai::trie_reader::TrieReader;
-use bonsai_types::TrieKind;
+use bonsai_types::{ProofDocument, ProofMembership, ProofNodeJson, TrieKind, PROOF_DOCUMENT_VERSION};
 
 #[derive(Debug, Clone)]
 pub enum ProofNode {
@@ -19,20 +21,162 @@ impl ProofNode {
             TrieKind::Class => hash_node::<Poseidon>(self),
         }
     }
+
+    /// The node as exported in proof documents and API responses.
+    pub fn to_json(&self, kind: TrieKind) -> ProofNodeJson {
+        let hash = Some(format!("{:#x}", self.hash(kind)));
+        match self {
+            ProofNode::Binary { left, right } => ProofNodeJson {
+                kind: "binary".to_string(),
+                hash,
+                left: Some(format!("{left:#x}")),
+                right: Some(format!("{right:#x}")),
+                child: None,
+                path_len: None,
+                path: None,
+            },
+            ProofNode::Edge { child, path } => ProofNodeJson {
+                kind: "edge".to_string(),
+                hash,
+                left: None,
+                right: None,
+                child: Some(format!("{child:#x}")),
+                path_len: Some(path.len()),
+                path: Some(format!("{:#x}", path_to_felt(path))),
+            },
+        }
+    }
+}
+
+/// What a valid proof shows about its key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Membership {
+    /// The path reaches the full key; carries the leaf value the last node commits to.
+    Member(Felt),
+    /// The last edge leaves the key's path (or the trie is empty), so no leaf exists under the key.
+    NonMember,
+}
+
+/// The standalone document for a verified proof of `key` against `root`.
+pub fn proof_document(
+    kind: TrieKind,
+    identifier: Option<String>,
+    block: Option<u64>,
+    root: Felt,
+    key: Felt,
+    membership: Membership,
+    proof: &[ProofNode],
+) -> ProofDocument {
+    let (membership, value) = match membership {
+        Membership::Member(value) => (ProofMembership::Member, Some(format!("{value:#x}"))),
+        Membership::NonMember => (ProofMembership::NonMember, None),
+    };
+    ProofDocument {
+        version: PROOF_DOCUMENT_VERSION,
+        trie: kind,
+        hash_function: kind.hash_function(),
+        identifier,
+        block,
+        root: format!("{root:#x}"),
+        key: format!("{key:#x}"),
+        value,
+        membership,
+        nodes: proof.iter().map(|node| node.to_json(kind)).collect(),
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ProofError {
+    #[error("proof node {index} does not hash to the value its parent commits to")]
+    HashMismatch { index: usize },
+    #[error("proof ends before it reaches the key or diverges from it")]
+    Incomplete,
+    #[error("proof continues after node {index}, where it already ends")]
+    TrailingNodes { index: usize },
+    #[error("proof shows the key with value {proven:#x}, but the leaf value is {}", display_value(.value))]
+    ValueMismatch { proven: Felt, value: Option<Felt> },
+    #[error("proof shows the key is absent, but it has leaf value {0:#x}")]
+    UnexpectedValue(Felt),
+}
+
+fn display_value(value: &Option<Felt>) -> String {
+    value.map(|v| format!("{v:#x}")).unwrap_or_else(|| "missing".to_string())
 }
 
+/// Collects the nodes from the root towards `key`. The proof stops at the leaf, or at
+/// the first edge that diverges from `key`, which proves the key absent.
+/// An empty trie yields an empty proof.
 pub fn build_proof(reader: &mut TrieReader, key: &PathBits) -> Option<Vec<ProofNode>> {
+    Some(proof_steps(reader, key)?.into_iter().map(|(_, node)| node).collect())
+}
+
+/// Proofs for several keys sharing one node list. Nodes on the common upper paths
+/// appear once; `indices[i]` lists, root first, the nodes of the proof for key `i`.
+#[derive(Debug, Clone, Default)]
+pub struct MultiProof {
+    pub nodes: Vec<ProofNode>,
+    pub indices: Vec<Vec<usize>>,
+}
+
+impl MultiProof {
+    /// The standalone proof for key `i`, or `None` if an index is out of range.
+    pub fn proof_for(&self, i: usize) -> Option<Vec<ProofNode>> {
+        self.indices.get(i)?.iter().map(|&idx| self.nodes.get(idx).cloned()).collect()
+    }
+}
+
+/// Builds the proofs for `keys` in one pass, deduplicating nodes by their trie path.
+pub fn build_multi_proof(reader: &mut TrieReader, keys: &[PathBits]) -> Option<MultiProof> {
+    let mut proof = MultiProof::default();
+    let mut seen: HashMap<Vec<u8>, usize> = HashMap::new();
+    for key in keys {
+        let mut indices = Vec::new();
+        for (path, node) in proof_steps(reader, key)? {
+            let index = *seen.entry(path.to_bytes()).or_insert_with(|| {
+                proof.nodes.push(node);
+                proof.nodes.len() - 1
+            });
+            indices.push(index);
+        }
+        proof.indices.push(indices);
+    }
+    Some(proof)
+}
+
+/// Verifies every key of `proof` against `root`; `values[i]` is key `i`'s stored leaf value.
+pub fn verify_multi_proof(
+    root: Felt,
+    keys: &[PathBits],
+    proof: &MultiProof,
+    kind: TrieKind,
+    values: &[Option<Felt>],
+) -> Vec<Result<Membership, ProofError>> {
+    keys.iter()
+        .zip(values)
+        .enumerate()
+        .map(|(i, (key, value))| {
+            let nodes = proof.proof_for(i).ok_or(ProofError::Incomplete)?;
+            verify_proof(root, key, &nodes, kind, *value)
+        })
+        .collect()
+}
+
+/// The proof nodes towards `key`, each with the trie path it is stored under.
+fn proof_steps(reader: &mut TrieReader, key: &PathBits) -> Option<Vec<(PathBits, ProofNode)>> {
     let mut path = PathBits::default();
     let mut proof = Vec::new();
 
-    let mut current_node = reader.load_root_node()?;
+    let Some(mut current_node) = reader.load_root_node() else {
+        return Some(proof);
+    };
 
     loop {
+        let node_path = path.clone();
         match &current_node {
             Node::Binary(node) => {
                 let left = node.left.as_hash()?;
                 let right = node.right.as_hash()?;
-                proof.push(ProofNode::Binary { left, right });
+                proof.push((node_path, ProofNode::Binary { left, right }));
 
                 let bit_index = path.len();
                 if bit_index >= key.len() {
@@ -44,48 +188,154 @@ pub fn build_proof(reader: &mut TrieReader, key: &PathBits) -> Option<Vec<ProofN
             Node::Edge(node) => {
                 let child = node.child.as_hash()?;
                 let edge_bits = PathBits(node.path.0.clone());
-                proof.push(ProofNode::Edge {
-                    child,
-                    path: edge_bits.clone(),
-                });
-                path.extend_from_bitslice(&edge_bits.0);
-                if path.len() >= key.len() {
+                proof.push((
+                    node_path,
+                    ProofNode::Edge {
+                        child,
+                        path: edge_bits.clone(),
+                    },
+                ));
+                if key.0.get(path.len()..path.len() + edge_bits.len()) != Some(&edge_bits.0) {
                     return Some(proof);
                 }
+                path.extend_from_bitslice(&edge_bits.0);
             }
         }
+        if path.len() >= key.len() {
+            return Some(proof);
+        }
 
         current_node = reader.load_node_by_path(&path)?;
     }
 }
 
-pub fn verify_proof(root: Felt, key: &PathBits, proof: &[ProofNode], kind: TrieKind) -> bool {
+/// Checks the hash chain from `root` along `key` and works out whether the proof shows
+/// membership or non-membership, then checks that against `value`, the key's leaf
+/// value in storage (`None` when it has none).
+pub fn verify_proof(
+    root: Felt,
+    key: &PathBits,
+    proof: &[ProofNode],
+    kind: TrieKind,
+    value: Option<Felt>,
+) -> Result<Membership, ProofError> {
+    verify_proof_steps(root, key, proof, kind, value).1
+}
+
+/// One proof node as checked by [`verify_proof_steps`].
+#[derive(Debug, Clone)]
+pub struct VerifyStep {
+    pub index: usize,
+    /// Key bits consumed above this node.
+    pub depth: usize,
+    /// The hash the parent (or the root) commits to.
+    pub expected: Felt,
+    /// The node's own hash.
+    pub computed: Felt,
+}
+
+impl VerifyStep {
+    pub fn ok(&self) -> bool {
+        self.expected == self.computed
+    }
+}
+
+/// Like [`verify_proof`], but also reports every node it hashed, root first. When the
+/// chain breaks, the failing node is the last step.
+pub fn verify_proof_steps(
+    root: Felt,
+    key: &PathBits,
+    proof: &[ProofNode],
+    kind: TrieKind,
+    value: Option<Felt>,
+) -> (Vec<VerifyStep>, Result<Membership, ProofError>) {
+    let mut steps = Vec::new();
+    let result = walk_proof(root, key, proof, kind, &mut steps).and_then(|membership| match (membership, value) {
+        (Membership::Member(proven), Some(value)) if proven == value => Ok(membership),
+        (Membership::Member(proven), value) => Err(ProofError::ValueMismatch { proven, value }),
+        (Membership::NonMember, None) => Ok(membership),
+        (Membership::NonMember, Some(value)) => Err(ProofError::UnexpectedValue(value)),
+    });
+    (steps, result)
+}
+
+/// Puts the nodes of an RPC-style node map (node hash to node) in proof order by
+/// following `key` down from `root`. Stops where the map has no node for the next hash,
+/// so a missing node shows up as an incomplete proof.
+pub fn order_node_map(root: Felt, key: &PathBits, nodes: &HashMap<Felt, ProofNode>) -> Vec<ProofNode> {
+    let mut proof = Vec::new();
+    let mut current_hash = root;
+    let mut depth = 0;
+    while depth < key.len() {
+        let Some(node) = nodes.get(&current_hash) else {
+            break;
+        };
+        proof.push(node.clone());
+        match node {
+            ProofNode::Binary { left, right } => {
+                current_hash = if key.0[depth] { *right } else { *left };
+                depth += 1;
+            }
+            ProofNode::Edge { child, path } => {
+                if key.0.get(depth..depth + path.len()) != Some(&path.0) {
+                    break;
+                }
+                current_hash = *child;
+                depth += path.len();
+            }
+        }
+    }
+    proof
+}
+
+fn walk_proof(
+    root: Felt,
+    key: &PathBits,
+    proof: &[ProofNode],
+    kind: TrieKind,
+    steps: &mut Vec<VerifyStep>,
+) -> Result<Membership, ProofError> {
+    if proof.is_empty() {
+        // Only an empty trie, whose root is zero, has nothing to show.
+        return if root == Felt::ZERO { Ok(Membership::NonMember) } else { Err(ProofError::Incomplete) };
+    }
     let mut current_hash = root;
     let mut current_path = PathBits::default();
 
-    for node in proof {
-        if node.hash(kind) != current_hash {
-            return false;
+    for (index, node) in proof.iter().enumerate() {
+        if current_path.len() >= key.len() {
+            return Err(ProofError::TrailingNodes { index: index.saturating_sub(1) });
+        }
+        let step = VerifyStep { index, depth: current_path.len(), expected: current_hash, computed: node.hash(kind) };
+        let ok = step.ok();
+        steps.push(step);
+        if !ok {
+            return Err(ProofError::HashMismatch { index });
         }
         match node {
             ProofNode::Binary { left, right } => {
-                if current_path.len() >= key.len() {
-                    return false;
-                }
                 let direction = key.0[current_path.len()];
                 current_path.push(direction);
                 current_hash = if direction { *right } else { *left };
             }
             ProofNode::Edge { child, path } => {
                 if key.0.get(current_path.len()..(current_path.len() + path.len())) != Some(&path.0) {
-                    return false;
+                    return if index + 1 == proof.len() {
+                        Ok(Membership::NonMember)
+                    } else {
+                        Err(ProofError::TrailingNodes { index })
+                    };
                 }
                 current_path.extend_from_bitslice(&path.0);
                 current_hash = *child;
             }
         }
     }
-    true
+    if current_path.len() == key.len() {
+        Ok(Membership::Member(current_hash))
+    } else {
+        Err(ProofError::Incomplete)
+    }
 }
 
 fn hash_node<H: StarkHash>(node: &ProofNode) -> Felt {
@@ -100,3 +350,108 @@ fn hash_edge<H: StarkHash>(child_hash: &Felt, path: &PathBits) -> Felt {
     let length = Felt::from(path.len() as u64);
     H::hash(child_hash, &felt_path) + length
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bonsai::path::felt_to_path;
+    use crate::build_spec;
+    use crate::testing::TestDb;
+
+    fn felt(value: u64) -> Felt {
+        Felt::from(value)
+    }
+
+    /// A storage trie holding keys 1 and 2, which split at the last bit, and its root.
+    fn two_leaf_trie(test_db: &TestDb) -> (TrieReader, Felt) {
+        let spec = build_spec(TrieKind::Storage, Some("0x11".to_string())).unwrap();
+        let root = test_db.commit(5, &spec, TrieKind::Storage, &[(felt(1), felt(10)), (felt(2), felt(20))]);
+        (TrieReader::new(test_db.open(), spec), root)
+    }
+
+    #[test]
+    fn verify_proof_tells_members_from_absent_keys() {
+        let test_db = TestDb::new();
+        let (mut reader, root) = two_leaf_trie(&test_db);
+        let verify = |reader: &mut TrieReader, key: u64, value: Option<u64>| {
+            let key = felt_to_path(&felt(key));
+            let proof = build_proof(reader, &key).unwrap();
+            verify_proof(root, &key, &proof, TrieKind::Storage, value.map(felt))
+        };
+
+        assert!(matches!(verify(&mut reader, 1, Some(10)), Ok(Membership::Member(value)) if value == felt(10)));
+        assert!(matches!(verify(&mut reader, 2, Some(20)), Ok(Membership::Member(value)) if value == felt(20)));
+        assert!(matches!(verify(&mut reader, 1, Some(11)), Err(ProofError::ValueMismatch { value: Some(_), .. })));
+        assert!(matches!(verify(&mut reader, 1, None), Err(ProofError::ValueMismatch { value: None, .. })));
+        // Key 3 turns off at the edge to key 2.
+        assert!(matches!(verify(&mut reader, 3, None), Ok(Membership::NonMember)));
+        assert!(matches!(verify(&mut reader, 3, Some(30)), Err(ProofError::UnexpectedValue(_))));
+    }
+
+    #[test]
+    fn verify_proof_rejects_broken_chains() {
+        let test_db = TestDb::new();
+        let (mut reader, root) = two_leaf_trie(&test_db);
+        let key = felt_to_path(&felt(1));
+        let proof = build_proof(&mut reader, &key).unwrap();
+        let verify = |proof: &[ProofNode], root: Felt| verify_proof(root, &key, proof, TrieKind::Storage, Some(felt(10)));
+
+        let mut tampered = proof.clone();
+        let last = tampered.len() - 1;
+        tampered[last] = ProofNode::Edge { child: felt(11), path: PathBits(key.0[key.len() - 1..].to_bitvec()) };
+        assert!(matches!(verify(&tampered, root), Err(ProofError::HashMismatch { index }) if index == last));
+        assert!(matches!(verify(&proof[..last], root), Err(ProofError::Incomplete)));
+        let mut trailing = proof.clone();
+        trailing.push(proof[0].clone());
+        assert!(matches!(verify(&trailing, root), Err(ProofError::TrailingNodes { .. })));
+
+        // An empty proof only stands for the empty trie.
+        assert!(matches!(verify_proof(Felt::ZERO, &key, &[], TrieKind::Storage, None), Ok(Membership::NonMember)));
+        assert!(matches!(verify(&[], root), Err(ProofError::Incomplete)));
+    }
+
+    #[test]
+    fn multi_proofs_share_common_nodes() {
+        let test_db = TestDb::new();
+        let (mut reader, root) = two_leaf_trie(&test_db);
+        let keys: Vec<PathBits> = [1, 2, 3, 1].into_iter().map(|key| felt_to_path(&felt(key))).collect();
+        let proof = build_multi_proof(&mut reader, &keys).unwrap();
+
+        // Root edge and binary node are shared; keys 2 and 3 share the edge to leaf 2.
+        assert_eq!(proof.nodes.len(), 4);
+        assert_eq!(proof.indices, vec![vec![0, 1, 2], vec![0, 1, 3], vec![0, 1, 3], vec![0, 1, 2]]);
+        for (i, key) in keys.iter().enumerate() {
+            assert_eq!(proof.proof_for(i).unwrap().len(), build_proof(&mut reader, key).unwrap().len());
+        }
+        assert!(proof.proof_for(keys.len()).is_none());
+
+        let values = [Some(felt(10)), Some(felt(20)), None, Some(felt(10))];
+        let results = verify_multi_proof(root, &keys, &proof, TrieKind::Storage, &values);
+        assert!(matches!(results[..], [Ok(Membership::Member(_)), Ok(Membership::Member(_)), Ok(Membership::NonMember), Ok(Membership::Member(_))]));
+    }
+
+    #[test]
+    fn node_maps_are_ordered_along_the_key() {
+        let test_db = TestDb::new();
+        let (mut reader, root) = two_leaf_trie(&test_db);
+        let hashes = |proof: &[ProofNode]| -> Vec<Felt> { proof.iter().map(|node| node.hash(TrieKind::Storage)).collect() };
+        let (key1, key3) = (felt_to_path(&felt(1)), felt_to_path(&felt(3)));
+        let proof1 = build_proof(&mut reader, &key1).unwrap();
+        let proof3 = build_proof(&mut reader, &key3).unwrap();
+        // One map holding both proofs, as an RPC response for several keys would.
+        let mut nodes: HashMap<Felt, ProofNode> = proof1.iter().chain(&proof3).map(|node| (node.hash(TrieKind::Storage), node.clone())).collect();
+        assert_eq!(nodes.len(), 4);
+
+        assert_eq!(hashes(&order_node_map(root, &key1, &nodes)), hashes(&proof1));
+        // Key 3 stops at the edge that turns away from it.
+        let ordered3 = order_node_map(root, &key3, &nodes);
+        assert_eq!(hashes(&ordered3), hashes(&proof3));
+        assert!(matches!(verify_proof(root, &key3, &ordered3, TrieKind::Storage, None), Ok(Membership::NonMember)));
+
+        nodes.remove(&proof1[1].hash(TrieKind::Storage));
+        let ordered = order_node_map(root, &key1, &nodes);
+        assert_eq!(hashes(&ordered), hashes(&proof1[..1]));
+        assert!(matches!(verify_proof(root, &key1, &ordered, TrieKind::Storage, Some(felt(10))), Err(ProofError::Incomplete)));
+        assert!(order_node_map(felt(0xdead), &key1, &nodes).is_empty());
+    }
+}
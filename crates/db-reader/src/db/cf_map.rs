@@ -19,3 +19,9 @@ pub const BONSAI_COLUMNS: &[&str] = &[
     BONSAI_CLASS_TRIE,
     BONSAI_CLASS_LOG,
 ];
+
+// Madara state columns outside bonsai. They are read when present but not required to open a DB.
+pub const CONTRACT_TO_CLASS_HASHES: &str = "contract_to_class_hashes";
+pub const CONTRACT_TO_NONCES: &str = "contract_to_nonces";
+pub const BLOCK_N_TO_BLOCK_INFO: &str = "block_n_to_block_info";
+pub const CLASS_INFO: &str = "class_info";
@@ -0,0 +1,277 @@
+use starknet_types_core::felt::Felt;
+use starknet_types_core::hash::{Pedersen, Poseidon, StarkHash};
+
+use crate::db::cf_map;
+use crate::db::RocksDb;
+
+/// Class hash of `address` as of `block` (latest when `None`). Fails if the contract was
+/// not deployed by then.
+pub fn contract_class_hash(db: &RocksDb, address: &Felt, block: Option<u64>) -> Result<Felt, String> {
+    value_at(db, cf_map::CONTRACT_TO_CLASS_HASHES, address, block)?.ok_or_else(|| format!("no class hash for contract {address:#x}"))
+}
+
+/// Nonce of `address` as of `block` (latest when `None`). Madara only stores nonce
+/// updates, so a contract that never had one set is at the initial nonce, zero.
+pub fn contract_nonce(db: &RocksDb, address: &Felt, block: Option<u64>) -> Result<Felt, String> {
+    Ok(value_at(db, cf_map::CONTRACT_TO_NONCES, address, block)?.unwrap_or(Felt::ZERO))
+}
+
+/// The contract trie leaf committing to a contract's state:
+/// `Pedersen(Pedersen(Pedersen(class_hash, storage_root), nonce), 0)`.
+pub fn contract_state_hash(class_hash: &Felt, storage_root: &Felt, nonce: &Felt) -> Felt {
+    let hash = Pedersen::hash(class_hash, storage_root);
+    let hash = Pedersen::hash(&hash, nonce);
+    Pedersen::hash(&hash, &Felt::ZERO)
+}
+
+/// The class trie leaf committing to a declared class:
+/// `Poseidon("CONTRACT_CLASS_LEAF_V0", compiled_class_hash)`.
+pub fn class_leaf_hash(compiled_class_hash: &Felt) -> Felt {
+    Poseidon::hash(&Felt::from_bytes_be_slice(b"CONTRACT_CLASS_LEAF_V0"), compiled_class_hash)
+}
+
+/// Starknet's global state root, `Poseidon("STARKNET_STATE_V0", contract_root, class_root)`.
+/// Before any class is declared the class root is zero and the state root is the contract root.
+pub fn global_state_root(contract_root: &Felt, class_root: &Felt) -> Felt {
+    if *class_root == Felt::ZERO {
+        return *contract_root;
+    }
+    Poseidon::hash_array(&[Felt::from_bytes_be_slice(b"STARKNET_STATE_V0"), *contract_root, *class_root])
+}
+
+/// Global state root in the header of `block`, or `None` if the DB has no header for it.
+///
+/// Best effort against Madara's bincode layout: the stored block info starts with the
+/// header's `parent_block_hash`, `block_number` and `global_state_root`, each felt being a
+/// `u64` length followed by its big-endian bytes. Keys are tried as bincode `u64`
+/// (little-endian), then big-endian `u64` and `u32`.
+pub fn header_state_root(db: &RocksDb, block: u64) -> Result<Option<Felt>, String> {
+    let cf = cf_map::BLOCK_N_TO_BLOCK_INFO;
+    if !db.cf_names().iter().any(|name| name == cf) {
+        return Err(format!("no {cf} column in this DB"));
+    }
+    let keys = [block.to_le_bytes().to_vec(), block.to_be_bytes().to_vec(), (block as u32).to_be_bytes().to_vec()];
+    for key in keys {
+        let Some(value) = db.get_cf(cf, &key).map_err(|e| e.to_string())? else { continue };
+        match decode_header(&value) {
+            Some((number, state_root)) if number == block => return Ok(Some(state_root)),
+            // Another key encoding landed on a different block.
+            Some(_) => continue,
+            None => return Err(format!("undecodable block header for block {block}")),
+        }
+    }
+    Ok(None)
+}
+
+/// Compiled class hash of a declared class, or `None` if the DB has no class info for it.
+///
+/// `class_info` is keyed by the class hash's big-endian bytes and holds a bincode
+/// `ClassInfoWithBlockNumber`, decoded by [`decode_class_info`]. Legacy classes have no
+/// compiled class hash and are an error here, since the class trie only holds Sierra classes.
+pub fn compiled_class_hash(db: &RocksDb, class_hash: &Felt) -> Result<Option<Felt>, String> {
+    let cf = cf_map::CLASS_INFO;
+    if !db.cf_names().iter().any(|name| name == cf) {
+        return Err(format!("no {cf} column in this DB"));
+    }
+    let Some(value) = db.get_cf(cf, &class_hash.to_bytes_be()).map_err(|e| e.to_string())? else {
+        return Ok(None);
+    };
+    match decode_class_info(&value) {
+        Some(ClassInfo::Sierra { compiled_class_hash }) => Ok(Some(compiled_class_hash)),
+        Some(ClassInfo::Legacy) => Err(format!("{class_hash:#x} is a legacy class, which has no compiled class hash")),
+        None => Err(format!("undecodable class info for {class_hash:#x}")),
+    }
+}
+
+/// The part of a stored class info this crate reads.
+#[derive(Debug, PartialEq)]
+enum ClassInfo {
+    Sierra { compiled_class_hash: Felt },
+    Legacy,
+}
+
+/// Decodes Madara's bincode `ClassInfoWithBlockNumber { class_info, block_id }`:
+///
+/// - `class_info` is the enum `ClassInfo`, a `u32` variant index followed by the variant.
+///   `Sierra` (0) holds the flattened class, then `compiled_class_hash`. The flattened class is
+///   `sierra_program: Vec<Felt>`, `contract_class_version: String`, the `constructor`,
+///   `external` and `l1_handler` entry points as `Vec<(selector: Felt, function_idx: u64)>`,
+///   and `abi: String`. `Legacy` (1) is not decoded further.
+/// - `block_id` is `Pending` (0) or `Number(u64)` (1), and must end the value.
+///
+/// Vecs and strings are a `u64` length followed by their elements.
+fn decode_class_info(value: &[u8]) -> Option<ClassInfo> {
+    let mut cursor = value;
+    match read_u32_le(&mut cursor)? {
+        0 => {}
+        1 => return Some(ClassInfo::Legacy),
+        _ => return None,
+    }
+    let program_len = read_u64_le(&mut cursor)?;
+    for _ in 0..program_len {
+        read_bincode_felt(&mut cursor)?;
+    }
+    let version_len = read_u64_le(&mut cursor)? as usize;
+    std::str::from_utf8(take(&mut cursor, version_len)?).ok()?;
+    for _ in 0..3 {
+        let entry_points = read_u64_le(&mut cursor)?;
+        for _ in 0..entry_points {
+            read_bincode_felt(&mut cursor)?;
+            read_u64_le(&mut cursor)?;
+        }
+    }
+    let abi_len = read_u64_le(&mut cursor)? as usize;
+    take(&mut cursor, abi_len)?;
+    let compiled_class_hash = read_bincode_felt(&mut cursor)?;
+    match read_u32_le(&mut cursor)? {
+        0 => {}
+        1 => {
+            read_u64_le(&mut cursor)?;
+        }
+        _ => return None,
+    }
+    cursor.is_empty().then_some(ClassInfo::Sierra { compiled_class_hash })
+}
+
+/// `(block_number, global_state_root)` from the start of a bincode block info.
+fn decode_header(mut cursor: &[u8]) -> Option<(u64, Felt)> {
+    read_bincode_felt(&mut cursor)?;
+    let number = read_u64_le(&mut cursor)?;
+    Some((number, read_bincode_felt(&mut cursor)?))
+}
+
+fn take<'a>(cursor: &mut &'a [u8], len: usize) -> Option<&'a [u8]> {
+    if cursor.len() < len {
+        return None;
+    }
+    let (head, rest) = cursor.split_at(len);
+    *cursor = rest;
+    Some(head)
+}
+
+fn read_u32_le(cursor: &mut &[u8]) -> Option<u32> {
+    Some(u32::from_le_bytes(take(cursor, 4)?.try_into().ok()?))
+}
+
+fn read_u64_le(cursor: &mut &[u8]) -> Option<u64> {
+    Some(u64::from_le_bytes(take(cursor, 8)?.try_into().ok()?))
+}
+
+fn read_bincode_felt(cursor: &mut &[u8]) -> Option<Felt> {
+    let len = read_u64_le(cursor)? as usize;
+    if len > 32 {
+        return None;
+    }
+    Some(Felt::from_bytes_be_slice(take(cursor, len)?))
+}
+
+/// Last value written at or before `block` in one of Madara's contract history columns.
+/// Keys are `contract_address (32 bytes BE) || block_n (4 or 8 bytes BE)`, so one
+/// contract's entries sort by block and the scan can stop once it passes `block`.
+fn value_at(db: &RocksDb, cf: &str, address: &Felt, block: Option<u64>) -> Result<Option<Felt>, String> {
+    if !db.cf_names().iter().any(|name| name == cf) {
+        return Err(format!("no {cf} column in this DB"));
+    }
+    let prefix = address.to_bytes_be();
+    let iter = db.iter_cf_prefix(cf, &prefix).map_err(|e| e.to_string())?;
+    let mut found = None;
+    for (key, value) in iter {
+        let Some(entry_block) = history_block(&key[prefix.len()..]) else { continue };
+        if block.is_some_and(|block| entry_block > block) {
+            break;
+        }
+        found = Some((entry_block, value));
+    }
+    let Some((entry_block, value)) = found else {
+        return Ok(None);
+    };
+    decode_value(&value)
+        .map(Some)
+        .ok_or_else(|| format!("undecodable {cf} value for {address:#x} at block {entry_block}: {} bytes", value.len()))
+}
+
+fn history_block(suffix: &[u8]) -> Option<u64> {
+    match suffix.len() {
+        0 => Some(0),
+        4 => Some(u32::from_be_bytes(suffix.try_into().ok()?) as u64),
+        8 => Some(u64::from_be_bytes(suffix.try_into().ok()?)),
+        _ => None,
+    }
+}
+
+/// Values are bincode felts, like the header fields; a bare 32-byte big-endian felt is
+/// accepted too. The two cannot be confused: a felt's first byte is at most 0x08.
+fn decode_value(value: &[u8]) -> Option<Felt> {
+    let mut cursor = value;
+    if let Some(felt) = read_bincode_felt(&mut cursor).filter(|_| cursor.is_empty()) {
+        return Some(felt);
+    }
+    (value.len() == 32).then(|| Felt::from_bytes_be_slice(value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{bincode_felt, sierra_class_info, TestDb};
+
+    fn history_key(address: &Felt, block: u32) -> Vec<u8> {
+        [address.to_bytes_be().as_slice(), &block.to_be_bytes()].concat()
+    }
+
+    #[test]
+    fn decodes_bincode_and_bare_felts() {
+        let class_hash = Felt::from_hex_unchecked("0x7b3e05f48f0c69e4a65ce5e076a66271a527aff2c34ce1083ec6e1526997a69");
+        assert_eq!(bincode_felt(&Felt::ONE).len(), 9);
+        assert_eq!(bincode_felt(&class_hash).len(), 40);
+        assert_eq!(decode_value(&bincode_felt(&Felt::ONE)), Some(Felt::ONE));
+        assert_eq!(decode_value(&bincode_felt(&class_hash)), Some(class_hash));
+        assert_eq!(decode_value(&class_hash.to_bytes_be()), Some(class_hash));
+        assert_eq!(decode_value(&[1, 0, 0, 0, 0, 0, 0, 0]), None);
+        assert_eq!(decode_value(&[0xff; 40]), None);
+    }
+
+    #[test]
+    fn decodes_sierra_class_info() {
+        let compiled = Felt::from_hex_unchecked("0x4f2ad7a2a9cbe5b5fe6c3f4d4c9fd0e1e2f5a9d8fa4a6b3a0cbb0d8c1d2e3f4");
+        // The declaring block trails the compiled class hash.
+        let declared = sierra_class_info(&compiled, Some(0x0102));
+        assert_eq!(decode_class_info(&declared), Some(ClassInfo::Sierra { compiled_class_hash: compiled }));
+        assert_eq!(decode_class_info(&sierra_class_info(&compiled, None)), Some(ClassInfo::Sierra { compiled_class_hash: compiled }));
+        assert_eq!(decode_class_info(&1u32.to_le_bytes()), Some(ClassInfo::Legacy));
+
+        assert_eq!(decode_class_info(&declared[..declared.len() - 1]), None);
+        assert_eq!(decode_class_info(&[declared.as_slice(), &[0]].concat()), None);
+        assert_eq!(decode_class_info(&[4u64.to_le_bytes().to_vec(), vec![7; 40], bincode_felt(&compiled)].concat()), None);
+    }
+
+    #[test]
+    fn reads_compiled_class_hashes() {
+        let test_db = TestDb::new();
+        let compiled = Felt::from(0xcc77u64);
+        test_db.put(cf_map::CLASS_INFO, &Felt::from(0x77u64).to_bytes_be(), &sierra_class_info(&compiled, Some(4)));
+        test_db.put(cf_map::CLASS_INFO, &Felt::from(0x78u64).to_bytes_be(), &1u32.to_le_bytes());
+        let db = test_db.open();
+
+        assert_eq!(compiled_class_hash(&db, &Felt::from(0x77u64)), Ok(Some(compiled)));
+        assert!(compiled_class_hash(&db, &Felt::from(0x78u64)).unwrap_err().contains("legacy class"));
+        assert_eq!(compiled_class_hash(&db, &Felt::from(0x79u64)), Ok(None));
+    }
+
+    #[test]
+    fn reads_contract_history_at_a_block() {
+        let test_db = TestDb::new();
+        let address = Felt::from(0x11u64);
+        test_db.put(cf_map::CONTRACT_TO_CLASS_HASHES, &history_key(&address, 3), &bincode_felt(&Felt::from(0xc1a55u64)));
+        test_db.put(cf_map::CONTRACT_TO_CLASS_HASHES, &history_key(&address, 7), &bincode_felt(&Felt::from(0xc1a56u64)));
+        test_db.put(cf_map::CONTRACT_TO_NONCES, &history_key(&address, 5), &bincode_felt(&Felt::ONE));
+        test_db.put(cf_map::CONTRACT_TO_CLASS_HASHES, &history_key(&Felt::from(0x12u64), 3), &[0xff; 5]);
+        let db = test_db.open();
+
+        assert_eq!(contract_class_hash(&db, &address, Some(6)), Ok(Felt::from(0xc1a55u64)));
+        assert_eq!(contract_class_hash(&db, &address, None), Ok(Felt::from(0xc1a56u64)));
+        assert!(contract_class_hash(&db, &address, Some(2)).is_err());
+        assert_eq!(contract_nonce(&db, &address, Some(4)), Ok(Felt::ZERO));
+        assert_eq!(contract_nonce(&db, &address, None), Ok(Felt::ONE));
+        assert!(contract_class_hash(&db, &Felt::from(0x12u64), None).unwrap_err().contains("undecodable"));
+    }
+}
@@ -1,4 +1,5 @@
 pub mod cf_map;
+pub mod madara;
 mod rocks;
 
-pub use rocks::RocksDb;
+pub use rocks::{CatchUpStatus, OpenMode, RocksDb};
@@ -1,11 +1,17 @@
 use std::collections::BTreeSet;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
 
 use rocksdb::{ColumnFamily, Options, DB};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use crate::bonsai::diff_reader::FirstInsertCache;
+use crate::bonsai::historical::OverlayCache;
 
 use crate::db::cf_map::BONSAI_COLUMNS;
 
+pub type KvIter<'a> = Box<dyn Iterator<Item = (Vec<u8>, Vec<u8>)> + 'a>;
+
 #[derive(Debug, thiserror::Error)]
 pub enum DbOpenError {
     #[error("rocksdb error: {0}")]
@@ -14,10 +20,29 @@ pub enum DbOpenError {
     MissingCfs(Vec<String>),
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OpenMode {
+    ReadOnly,
+    Secondary,
+}
+
+/// Catch-up bookkeeping shared by every clone of a secondary instance.
+#[derive(Debug, Clone, Default)]
+pub struct CatchUpStatus {
+    pub catch_ups: u64,
+    pub last_catch_up_ms: Option<u64>,
+    pub last_error: Option<String>,
+}
+
 #[derive(Debug)]
 pub struct RocksDb {
     db: Arc<DB>,
     cf_names: Vec<String>,
+    mode: OpenMode,
+    secondary_path: Option<PathBuf>,
+    catch_up: Arc<Mutex<CatchUpStatus>>,
+    overlays: Arc<Mutex<OverlayCache>>,
+    first_inserts: Arc<Mutex<FirstInsertCache>>,
 }
 
 impl Clone for RocksDb {
@@ -25,6 +50,11 @@ impl Clone for RocksDb {
         Self {
             db: Arc::clone(&self.db),
             cf_names: self.cf_names.clone(),
+            mode: self.mode,
+            secondary_path: self.secondary_path.clone(),
+            catch_up: Arc::clone(&self.catch_up),
+            overlays: Arc::clone(&self.overlays),
+            first_inserts: Arc::clone(&self.first_inserts),
         }
     }
 }
@@ -35,22 +65,83 @@ impl RocksDb {
         let mut opts = Options::default();
         opts.set_max_open_files(256);
 
-        let cf_names = DB::list_cf(&opts, path)?;
-        let cf_set: BTreeSet<_> = cf_names.iter().cloned().collect();
+        let cf_names = list_bonsai_cfs(&opts, path)?;
+        let db = Arc::new(DB::open_cf_for_read_only(&opts, path, &cf_names, false)?);
+
+        Ok(Self {
+            db,
+            cf_names,
+            mode: OpenMode::ReadOnly,
+            secondary_path: None,
+            catch_up: Arc::default(),
+            overlays: Arc::default(),
+            first_inserts: Arc::default(),
+        })
+    }
+
+    /// Opens the DB as a RocksDB secondary instance that can follow a running primary.
+    /// `secondary_path` is a scratch directory RocksDB uses for the secondary's own logs.
+    pub fn open_secondary(path: impl AsRef<Path>, secondary_path: impl AsRef<Path>) -> Result<Self, DbOpenError> {
+        let path = path.as_ref();
+        let secondary_path = secondary_path.as_ref();
+        let mut opts = Options::default();
+        // Secondary instances must keep every table file open.
+        opts.set_max_open_files(-1);
+
+        let cf_names = list_bonsai_cfs(&opts, path)?;
+        let db = Arc::new(DB::open_cf_as_secondary(&opts, path, secondary_path, &cf_names)?);
+
+        Ok(Self {
+            db,
+            cf_names,
+            mode: OpenMode::Secondary,
+            secondary_path: Some(secondary_path.to_path_buf()),
+            catch_up: Arc::default(),
+            overlays: Arc::default(),
+            first_inserts: Arc::default(),
+        })
+    }
 
-        let missing: Vec<String> = BONSAI_COLUMNS
-            .iter()
-            .filter(|name| !cf_set.contains(**name))
-            .map(|name| name.to_string())
-            .collect();
+    pub fn mode(&self) -> OpenMode {
+        self.mode
+    }
+
+    pub fn secondary_path(&self) -> Option<&Path> {
+        self.secondary_path.as_deref()
+    }
 
-        if !missing.is_empty() {
-            return Err(DbOpenError::MissingCfs(missing));
+    /// Replays the primary's new WAL and manifest entries. No-op for read-only opens.
+    pub fn catch_up(&self) -> Result<(), rocksdb::Error> {
+        if self.mode != OpenMode::Secondary {
+            return Ok(());
         }
+        let result = self.db.try_catch_up_with_primary();
+        let mut status = self.catch_up.lock().unwrap_or_else(|e| e.into_inner());
+        match &result {
+            Ok(()) => {
+                status.catch_ups += 1;
+                status.last_catch_up_ms = Some(now_ms());
+                status.last_error = None;
+                self.overlay_cache().clear();
+                self.first_insert_cache().clear();
+            }
+            Err(err) => status.last_error = Some(err.to_string()),
+        }
+        result
+    }
 
-        let db = Arc::new(DB::open_cf_for_read_only(&opts, path, &cf_names, false)?);
+    pub fn catch_up_status(&self) -> CatchUpStatus {
+        self.catch_up.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
 
-        Ok(Self { db, cf_names })
+    /// Historical overlays built against this DB, shared by its clones.
+    pub fn overlay_cache(&self) -> MutexGuard<'_, OverlayCache> {
+        self.overlays.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Per log CF, the block that first inserted each flat key, shared by this DB's clones.
+    pub fn first_insert_cache(&self) -> MutexGuard<'_, FirstInsertCache> {
+        self.first_inserts.lock().unwrap_or_else(|e| e.into_inner())
     }
 
     pub fn cf_names(&self) -> &[String] {
@@ -72,7 +163,7 @@ impl RocksDb {
         &self,
         name: &str,
         prefix: &[u8],
-    ) -> Result<Box<dyn Iterator<Item = (Vec<u8>, Vec<u8>)> + '_>, rocksdb::Error> {
+    ) -> Result<KvIter<'_>, rocksdb::Error> {
         let Some(cf) = self.db.cf_handle(name) else {
             return Ok(Box::new(Vec::<(Vec<u8>, Vec<u8>)>::new().into_iter()));
         };
@@ -83,4 +174,48 @@ impl RocksDb {
             .map(|(k, v)| (k.to_vec(), v.to_vec()));
         Ok(Box::new(iter))
     }
+
+    /// Iterates the keys of `name` that start with `prefix`, in byte order.
+    pub fn iter_cf_prefix<'a>(
+        &'a self,
+        name: &str,
+        prefix: &'a [u8],
+    ) -> Result<KvIter<'a>, rocksdb::Error> {
+        let iter = self.iter_cf_from(name, prefix)?;
+        Ok(Box::new(iter.take_while(move |(k, _)| k.starts_with(prefix))))
+    }
+
+    /// Last key of a column family in byte order.
+    pub fn last_key_cf(&self, name: &str) -> Result<Option<Vec<u8>>, rocksdb::Error> {
+        let Some(cf) = self.db.cf_handle(name) else {
+            return Ok(None);
+        };
+        match self.db.iterator_cf(cf, rocksdb::IteratorMode::End).next() {
+            Some(res) => res.map(|(k, _)| Some(k.to_vec())),
+            None => Ok(None),
+        }
+    }
+}
+
+fn list_bonsai_cfs(opts: &Options, path: &Path) -> Result<Vec<String>, DbOpenError> {
+    let cf_names = DB::list_cf(opts, path)?;
+    let cf_set: BTreeSet<_> = cf_names.iter().cloned().collect();
+
+    let missing: Vec<String> = BONSAI_COLUMNS
+        .iter()
+        .filter(|name| !cf_set.contains(**name))
+        .map(|name| name.to_string())
+        .collect();
+
+    if !missing.is_empty() {
+        return Err(DbOpenError::MissingCfs(missing));
+    }
+    Ok(cf_names)
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
 }
@@ -1,21 +1,59 @@
 pub mod bonsai;
 pub mod db;
 pub mod util;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
 
-use bonsai::diff_reader::read_block_log;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::atomic::AtomicU64;
+
+use bonsai::consistency::{check_flat_consistency, IssueKind};
+use bonsai::diff_reader::{aggregate_range, check_block_logged, earliest_logged_block, first_inserted_blocks, key_history, latest_logged_block, log_entries, log_entries_from, pair_changes, read_block_log, scan_retention, LogValue, CHANGE_NEW, CHANGE_OLD, KEY_TYPE_FLAT, KEY_TYPE_TRIE};
+use bonsai::discovery::discover_storage_contracts;
+use bonsai::historical::historical_reader;
+use bonsai::integrity::{check_integrity as check_trie_integrity, MismatchKind};
 use bonsai::node::Node;
-use bonsai::path::{felt_to_path, PathBits};
-use bonsai::proof::{build_proof, verify_proof, ProofNode};
+use bonsai::path::{felt_to_bits, felt_to_path, path_to_felt, PathBits, LEAF_PATH_LEN};
+use bonsai::proof::{build_multi_proof, build_proof, order_node_map, proof_document, verify_multi_proof, verify_proof, verify_proof_steps, Membership, ProofError, ProofNode};
+use bonsai::stats::collect_stats;
 use bonsai::trie_reader::{TrieReader, TrieSpec};
-use db::cf_map;
-use db::RocksDb;
-use bonsai_types::{CfsResponse, DiffEntry, DiffResponse, LeafResponse, NodeResponse, NodeView, ProofNodeJson, ProofResponse, RootResponse, TrieKind};
+use db::{cf_map, madara};
+use db::{OpenMode, RocksDb};
+use bonsai_types::{ActivityCell, ActivityCount, ActivityResponse, BatchProofResponse, BlockActivity, ContractActivity, BisectRequest, BisectResponse, BisectStep, CfsResponse, ChangeValue, ClassResponse, ClassesResponse, ContractLeafData, ContractStorageKeys, ContractResponse, ContractStorageProofResponse, ContractsProof, ConsistencyIssue, ConsistencyResponse, DiffChange, DiffEntry, DiffResponse, FollowResponse, GlobalRoots, HistogramBucket, HistoryEntry, HistoryResponse, IntegrityMismatch, IntegrityResponse, KeyProof, LeafEntry, LeafResponse, LeavesResponse, LogGap, LogKeyType, LogRetentionView, MerkleNode, NodeHashToNode, NodeResponse, NodeView, ProofDocument, ProofMembership, ProofNodeJson, ProofResponse, RangeDiffEntry, RangeDiffResponse, ResolvedKeyResponse, RetentionResponse, RootResponse, StateRootResponse, StorageContractView, StorageContractsResponse, StorageLabel, StorageLabelsResponse, StorageVariableView, StorageProofResult, TrieKind, TrieStatsResponse, VerifyProofRequest, VerifyProofResponse, VerifyStepView};
+use starknet_types_core::felt::Felt;
 use util::hex::{bytes_to_hex, decode_felt_scale, format_felt_short, parse_felt_hex};
+use util::storage_key::{parse_storage_key, parse_trie_key, storage_address};
+use util::storage_layout::{decode_slot, SlotKind, StorageLayout};
 
 pub fn open_db(path: &str) -> Result<RocksDb, String> {
     RocksDb::open_read_only(path).map_err(|e| e.to_string())
 }
 
+pub fn open_db_secondary(path: &str, secondary_path: &str) -> Result<RocksDb, String> {
+    RocksDb::open_secondary(path, secondary_path).map_err(|e| e.to_string())
+}
+
+pub fn catch_up(db: &RocksDb) -> FollowResponse {
+    // Failures are recorded in the catch-up status, so the result is not needed here.
+    let _ = db.catch_up();
+    follow_status(db)
+}
+
+pub fn follow_status(db: &RocksDb) -> FollowResponse {
+    let status = db.catch_up_status();
+    FollowResponse {
+        mode: match db.mode() {
+            OpenMode::ReadOnly => "read_only".to_string(),
+            OpenMode::Secondary => "secondary".to_string(),
+        },
+        secondary_path: db.secondary_path().map(|p| p.display().to_string()),
+        interval_secs: None,
+        catch_ups: status.catch_ups,
+        last_catch_up_ms: status.last_catch_up_ms,
+        last_error: status.last_error,
+    }
+}
+
 pub fn list_cfs(db: &RocksDb) -> CfsResponse {
     CfsResponse {
         total: db.cf_names().len(),
@@ -23,123 +61,1283 @@ pub fn list_cfs(db: &RocksDb) -> CfsResponse {
     }
 }
 
-pub fn root_node(db: &RocksDb, trie: TrieKind, identifier: Option<String>) -> RootResponse {
-    let spec = match build_spec(trie, identifier) {
-        Ok(spec) => spec,
-        Err(_err) => return RootResponse { path_hex: "0x00".to_string(), node: None },
+pub fn root_node(db: &RocksDb, trie: TrieKind, identifier: Option<String>, block: Option<u64>) -> RootResponse {
+    let mut reader = match open_reader(db, trie, identifier, block) {
+        Ok(reader) => reader,
+        Err(err) => return RootResponse { path_hex: "0x00".to_string(), node: None, error: Some(err) },
     };
-    let mut reader = TrieReader::new(db.clone(), spec);
     let root_path = PathBits::default();
     let node = reader.load_root_node().map(node_to_view);
     RootResponse {
         path_hex: bytes_to_hex(&root_path.to_bytes()),
         node,
+        error: None,
     }
 }
 
-pub fn load_node(db: &RocksDb, trie: TrieKind, identifier: Option<String>, path_hex: &str) -> NodeResponse {
-    let spec = match build_spec(trie, identifier) {
-        Ok(spec) => spec,
-        Err(_) => return NodeResponse { path_hex: path_hex.to_string(), node: None },
+pub fn load_node(db: &RocksDb, trie: TrieKind, identifier: Option<String>, path_hex: &str, block: Option<u64>) -> NodeResponse {
+    let mut reader = match open_reader(db, trie, identifier, block) {
+        Ok(reader) => reader,
+        Err(err) => return NodeResponse { path_hex: path_hex.to_string(), node: None, error: Some(err) },
     };
     let path_bytes = match hex_to_bytes(path_hex) {
         Some(b) => b,
-        None => return NodeResponse { path_hex: path_hex.to_string(), node: None },
+        None => {
+            return NodeResponse {
+                path_hex: path_hex.to_string(),
+                node: None,
+                error: Some("invalid path hex".to_string()),
+            }
+        }
     };
     let path = PathBits::from_encoded(&path_bytes);
-    let mut reader = TrieReader::new(db.clone(), spec);
     let node = reader.load_node_by_path(&path).map(node_to_view);
-    NodeResponse { path_hex: path_hex.to_string(), node }
+    NodeResponse { path_hex: path_hex.to_string(), node, error: None }
 }
 
-pub fn leaf_value(db: &RocksDb, trie: TrieKind, identifier: Option<String>, key_hex: &str) -> LeafResponse {
-    let spec = match build_spec(trie, identifier) {
-        Ok(spec) => spec,
-        Err(_) => return LeafResponse { key: key_hex.to_string(), value: None },
+pub fn leaf_value(db: &RocksDb, trie: TrieKind, identifier: Option<String>, key_hex: &str, block: Option<u64>) -> LeafResponse {
+    let reader = match open_reader(db, trie, identifier, block) {
+        Ok(reader) => reader,
+        Err(err) => return LeafResponse { key: key_hex.to_string(), value: None, error: Some(err) },
     };
-    let felt = match parse_felt_hex(key_hex) {
+    let felt = match parse_trie_key(trie, key_hex) {
         Ok(f) => f,
-        Err(_) => return LeafResponse { key: key_hex.to_string(), value: None },
+        Err(err) => return LeafResponse { key: key_hex.to_string(), value: None, error: Some(err) },
     };
     let key_path = felt_to_path(&felt);
-    let reader = TrieReader::new(db.clone(), spec);
     let value = reader.load_flat_value(&key_path).map(|v| format!("{v:#x}"));
-    LeafResponse { key: key_hex.to_string(), value }
+    LeafResponse { key: key_hex.to_string(), value, error: None }
 }
 
-pub fn diff_for_block(db: &RocksDb, trie: TrieKind, block: u64) -> DiffResponse {
-    let log_cf = match trie {
-        TrieKind::Contract => cf_map::BONSAI_CONTRACT_LOG,
-        TrieKind::Storage => cf_map::BONSAI_CONTRACT_STORAGE_LOG,
-        TrieKind::Class => cf_map::BONSAI_CLASS_LOG,
+/// The storage address a key expression such as `ERC20_balances[0xabc]` stands for.
+pub fn resolve_key(expression: &str) -> ResolvedKeyResponse {
+    match parse_storage_key(expression) {
+        Ok(key) => ResolvedKeyResponse { expression: expression.to_string(), key: Some(format!("{key:#x}")), error: None },
+        Err(err) => ResolvedKeyResponse { expression: expression.to_string(), key: None, error: Some(err) },
+    }
+}
+
+/// Largest page `list_leaves` returns, whatever the caller asks for.
+pub const MAX_LEAVES_PAGE: usize = 1000;
+
+/// Pages through the flat CF in key order. `cursor` is the last key of the previous
+/// page; `start` and `end` bound the range, both inclusive. Always reads the latest state.
+pub fn list_leaves(
+    db: &RocksDb,
+    trie: TrieKind,
+    identifier: Option<String>,
+    cursor: Option<&str>,
+    start: Option<&str>,
+    end: Option<&str>,
+    limit: usize,
+) -> LeavesResponse {
+    let failed = |err: String| LeavesResponse { leaves: Vec::new(), next_cursor: None, error: Some(err) };
+    let parse = |value: Option<&str>| value.map(parse_felt_hex).transpose();
+    let (cursor, start, end) = match (parse(cursor), parse(start), parse(end)) {
+        (Ok(cursor), Ok(start), Ok(end)) => (cursor, start, end),
+        (Err(err), _, _) | (_, Err(err), _) | (_, _, Err(err)) => return failed(err),
     };
-    let entries = read_block_log(db, log_cf, block)
-        .into_iter()
+    let reader = match open_reader(db, trie, identifier, None) {
+        Ok(reader) => reader,
+        Err(err) => return failed(err),
+    };
+    // The cursor key was already returned, so the page starts just after it.
+    let after_cursor = cursor.map(|c| c + Felt::ONE);
+    let from = start.into_iter().chain(after_cursor).max().unwrap_or(Felt::ZERO);
+    let iter = match reader.flat_leaves_from(&from) {
+        Ok(iter) => iter,
+        Err(err) => return failed(err.to_string()),
+    };
+
+    let limit = limit.clamp(1, MAX_LEAVES_PAGE);
+    let mut leaves = Vec::new();
+    let mut next_cursor = None;
+    for (key, value) in iter.take_while(|(key, _)| end.is_none_or(|end| *key <= end)) {
+        if leaves.len() == limit {
+            next_cursor = leaves.last().map(|leaf: &LeafEntry| leaf.key.clone());
+            break;
+        }
+        leaves.push(LeafEntry { key: format!("{key:#x}"), value: value.map(|v| format!("{v:#x}")) });
+    }
+    LeavesResponse { leaves, next_cursor, error: None }
+}
+
+pub fn diff_for_block(db: &RocksDb, trie: TrieKind, block: u64) -> DiffResponse {
+    if let Err(err) = check_block_logged(db, log_cf_for(trie), block) {
+        return DiffResponse { entries: Vec::new(), changes: Vec::new(), error: Some(err.to_string()) };
+    }
+    let log = read_block_log(db, log_cf_for(trie), block);
+    let entries = log
+        .iter()
         .map(|entry| {
-            let change = match entry.change_type { 0 => "new", 1 => "old", _ => "unknown" };
-            let key_type = match entry.key_type { 0 => "trie", 1 => "flat", _ => "unknown" };
-            let value_display = decode_felt_scale(&entry.value)
-                .map(|felt| format_felt_short(&felt))
-                .unwrap_or_else(|| bytes_to_hex(&entry.value));
+            let change = match entry.change_type { CHANGE_NEW => "new", CHANGE_OLD => "old", _ => "unknown" };
+            let key = LogKey::decode(entry.trie_kind, &entry.identifier, entry.key_bits.as_ref());
             DiffEntry {
                 block: entry.block,
-                key_type: key_type.to_string(),
+                key_type: log_key_type(entry.key_type),
                 change_type: change.to_string(),
                 key_len: entry.key_bits.as_ref().map(|k| k.len()),
-                value: value_display,
+                path_hex: key.path_hex,
+                key: key.key,
+                contract: key.contract,
+                value: log_value_display(&entry.value),
             }
         })
         .collect();
-    DiffResponse { entries }
+    let changes = pair_changes(log)
+        .into_iter()
+        .map(|change| {
+            let key = LogKey::decode(change.trie_kind, &change.identifier, change.key_bits.as_ref());
+            DiffChange {
+                block: change.block,
+                key_type: log_key_type(change.key_type),
+                kind: change.kind,
+                key_len: change.key_bits.as_ref().map(|k| k.len()),
+                path_hex: key.path_hex,
+                key: key.key,
+                contract: key.contract,
+                before: change.before.map(change_value),
+                after: change.after.map(change_value),
+            }
+        })
+        .collect();
+    DiffResponse { entries, changes, error: None }
+}
+
+/// Net changes between the state before `start` and the state after `end`.
+pub fn diff_for_range(db: &RocksDb, trie: TrieKind, start: u64, end: u64) -> RangeDiffResponse {
+    if start > end {
+        return RangeDiffResponse {
+            start,
+            end,
+            entries: Vec::new(),
+            error: Some(format!("start block {start} is after end block {end}")),
+        };
+    }
+    let unavailable = check_block_logged(db, log_cf_for(trie), start)
+        .and_then(|_| check_block_logged(db, log_cf_for(trie), end));
+    if let Err(err) = unavailable {
+        return RangeDiffResponse { start, end, entries: Vec::new(), error: Some(err.to_string()) };
+    }
+    let entries = aggregate_range(db, log_cf_for(trie), start, end)
+        .into_iter()
+        .map(|change| {
+            let key = LogKey::decode(change.trie_kind, &change.identifier, change.key_bits.as_ref());
+            RangeDiffEntry {
+                first_block: change.first_block,
+                last_block: change.last_block,
+                key_type: log_key_type(change.key_type),
+                key_len: change.key_bits.as_ref().map(|k| k.len()),
+                path_hex: key.path_hex,
+                key: key.key,
+                contract: key.contract,
+                old: change.old.as_deref().map(log_value_display),
+                new: change.new.as_deref().map(log_value_display),
+            }
+        })
+        .collect();
+    RangeDiffResponse { start, end, entries, error: None }
 }
 
-pub fn proof_for_key(db: &RocksDb, trie: TrieKind, identifier: Option<String>, key_hex: &str) -> ProofResponse {
+/// Every block in which one leaf's value changed, read from the trie log.
+pub fn leaf_history(db: &RocksDb, trie: TrieKind, identifier: Option<String>, key: &str) -> HistoryResponse {
+    let failed = |err: String| HistoryResponse { key: key.to_string(), entries: Vec::new(), earliest_logged: None, error: Some(err) };
     let spec = match build_spec(trie, identifier) {
         Ok(spec) => spec,
-        Err(_) => return ProofResponse { verified: false, nodes: Vec::new() },
+        Err(err) => return failed(err),
     };
-    let felt = match parse_felt_hex(key_hex) {
-        Ok(f) => f,
-        Err(_) => return ProofResponse { verified: false, nodes: Vec::new() },
+    let felt = match parse_trie_key(trie, key) {
+        Ok(felt) => felt,
+        Err(err) => return failed(err),
     };
-    let key_path = felt_to_path(&felt);
-    let mut reader = TrieReader::new(db.clone(), spec);
-    let root_node = match reader.load_root_node() {
-        Some(n) => n,
-        None => return ProofResponse { verified: false, nodes: Vec::new() },
+    let mut trie_key = spec.identifier;
+    trie_key.extend_from_slice(&felt_to_path(&felt).to_bytes());
+    let log_value = |value: LogValue| match value {
+        LogValue::Felt(felt) => format!("{felt:#x}"),
+        LogValue::Node(_) => "node".to_string(),
+        LogValue::Raw(bytes) => bytes_to_hex(&bytes),
+    };
+    let entries = key_history(db, log_cf_for(trie), &trie_key)
+        .into_iter()
+        .map(|change| HistoryEntry {
+            block: change.block,
+            kind: change.kind,
+            before: change.before.map(log_value),
+            after: change.after.map(log_value),
+        })
+        .collect();
+    HistoryResponse {
+        key: format!("{felt:#x}"),
+        entries,
+        earliest_logged: earliest_logged_block(db, log_cf_for(trie)),
+        error: None,
+    }
+}
+
+/// Binary search over `start..=end` for the first block where a leaf takes a value or
+/// the root stops matching an expected hash, reading each probed block through the
+/// historical reader. Assumes the condition, once it holds, keeps holding up to `end`;
+/// otherwise it finds one block where it starts to hold.
+pub fn bisect(db: &RocksDb, req: &BisectRequest) -> BisectResponse {
+    let mut resp = BisectResponse { block: None, condition: String::new(), steps: Vec::new(), error: None };
+    let condition = match bisect_condition(req) {
+        Ok(condition) => condition,
+        Err(err) => {
+            resp.error = Some(err);
+            return resp;
+        }
+    };
+    resp.condition = match &condition {
+        BisectCondition::Value { key, value } => format!("leaf {key:#x} = {value:#x}"),
+        BisectCondition::RootDiffers(root) => format!("root != {root:#x}"),
+    };
+    if req.start > req.end {
+        resp.error = Some(format!("start block {} is after end block {}", req.start, req.end));
+        return resp;
+    }
+    let mut probe = |block: u64| -> Result<bool, String> {
+        let mut reader = open_reader(db, req.trie, req.identifier.clone(), Some(block))?;
+        let (observed, holds) = match &condition {
+            BisectCondition::Value { key, value } => {
+                let observed = reader.load_flat_value(&felt_to_path(key)).unwrap_or(Felt::ZERO);
+                (observed, observed == *value)
+            }
+            BisectCondition::RootDiffers(root) => {
+                let observed = root_hash(&mut reader).ok_or_else(|| format!("root hash missing at block {block}"))?;
+                (observed, observed != *root)
+            }
+        };
+        resp.steps.push(BisectStep { block, holds, observed: format!("{observed:#x}") });
+        Ok(holds)
+    };
+    let found = (|| {
+        if !probe(req.end)? {
+            return Ok(None);
+        }
+        if probe(req.start)? {
+            return Ok(Some(req.start));
+        }
+        // The condition fails at `low` and holds at `high`.
+        let (mut low, mut high) = (req.start, req.end);
+        while high - low > 1 {
+            let mid = low + (high - low) / 2;
+            if probe(mid)? {
+                high = mid;
+            } else {
+                low = mid;
+            }
+        }
+        Ok(Some(high))
+    })();
+    match found {
+        Ok(block) => resp.block = block,
+        Err(err) => resp.error = Some(err),
+    }
+    resp
+}
+
+enum BisectCondition {
+    Value { key: Felt, value: Felt },
+    RootDiffers(Felt),
+}
+
+fn bisect_condition(req: &BisectRequest) -> Result<BisectCondition, String> {
+    match (&req.key, &req.value, &req.root) {
+        (Some(key), Some(value), None) => Ok(BisectCondition::Value {
+            key: parse_trie_key(req.trie, key).map_err(|err| format!("key: {err}"))?,
+            value: parse_felt_hex(value).map_err(|err| format!("value: {err}"))?,
+        }),
+        (None, None, Some(root)) => Ok(BisectCondition::RootDiffers(parse_felt_hex(root).map_err(|err| format!("root: {err}"))?)),
+        _ => Err("give either key and value, or root".to_string()),
+    }
+}
+
+/// Earliest/latest logged block and gaps for each trie-log CF.
+pub fn log_retention(db: &RocksDb) -> RetentionResponse {
+    let logs = [TrieKind::Contract, TrieKind::Storage, TrieKind::Class]
+        .into_iter()
+        .map(|trie| {
+            let log_cf = log_cf_for(trie);
+            let retention = scan_retention(db, log_cf);
+            LogRetentionView {
+                trie,
+                log_cf: log_cf.to_string(),
+                earliest: retention.earliest,
+                latest: retention.latest,
+                logged_blocks: retention.logged_blocks,
+                gaps: retention.gaps.into_iter().map(|(start, end)| LogGap { start, end }).collect(),
+            }
+        })
+        .collect();
+    RetentionResponse { logs }
+}
+
+/// Widest block range `block_activity` counts in one request.
+pub const MAX_ACTIVITY_BLOCKS: u64 = 5000;
+/// Storage contracts `block_activity` breaks out per block.
+pub const TOP_ACTIVITY_CONTRACTS: usize = 20;
+
+/// Counts the log entries of every block in `start..=end`, per trie and key type, plus
+/// per storage contract for the busiest ones. Blocks outside the logged range count zero.
+pub fn block_activity(db: &RocksDb, start: u64, end: u64) -> ActivityResponse {
+    let mut resp = ActivityResponse { start, end, blocks: Vec::new(), contracts: Vec::new(), contract_count: 0, error: None };
+    if start > end {
+        resp.error = Some(format!("start block {start} is after end block {end}"));
+        return resp;
+    }
+    if end - start >= MAX_ACTIVITY_BLOCKS {
+        resp.error = Some(format!("range spans {} blocks, at most {MAX_ACTIVITY_BLOCKS} allowed", end - start + 1));
+        return resp;
+    }
+    let tries = [TrieKind::Contract, TrieKind::Storage, TrieKind::Class];
+    resp.blocks = (start..=end)
+        .map(|block| BlockActivity {
+            block,
+            total: 0,
+            tries: tries.iter().map(|&trie| ActivityCount { trie, trie_entries: 0, flat_entries: 0 }).collect(),
+        })
+        .collect();
+    let mut contracts: HashMap<Vec<u8>, BTreeMap<u64, u64>> = HashMap::new();
+    for (index, trie) in tries.into_iter().enumerate() {
+        for entry in log_entries_from(db, log_cf_for(trie), start).take_while(|entry| entry.block <= end) {
+            let block = &mut resp.blocks[(entry.block - start) as usize];
+            block.total += 1;
+            match entry.key_type {
+                KEY_TYPE_TRIE => block.tries[index].trie_entries += 1,
+                KEY_TYPE_FLAT => block.tries[index].flat_entries += 1,
+                _ => {}
+            }
+            if trie == TrieKind::Storage && entry.identifier.len() == 32 {
+                *contracts.entry(entry.identifier).or_default().entry(entry.block).or_default() += 1;
+            }
+        }
+    }
+    resp.contract_count = contracts.len() as u64;
+    let mut contracts: Vec<ContractActivity> = contracts
+        .into_iter()
+        .map(|(identifier, blocks)| ContractActivity {
+            contract: format!("{:#x}", Felt::from_bytes_be_slice(&identifier)),
+            entries: blocks.values().sum(),
+            blocks: blocks.into_iter().map(|(block, entries)| ActivityCell { block, entries }).collect(),
+        })
+        .collect();
+    contracts.sort_by(|a, b| b.entries.cmp(&a.entries).then_with(|| a.contract.cmp(&b.contract)));
+    contracts.truncate(TOP_ACTIVITY_CONTRACTS);
+    resp.contracts = contracts;
+    resp
+}
+
+fn log_cf_for(trie: TrieKind) -> &'static str {
+    match trie {
+        TrieKind::Contract => cf_map::BONSAI_CONTRACT_LOG,
+        TrieKind::Storage => cf_map::BONSAI_CONTRACT_STORAGE_LOG,
+        TrieKind::Class => cf_map::BONSAI_CLASS_LOG,
+    }
+}
+
+fn log_key_type(key_type: u8) -> LogKeyType {
+    match key_type { KEY_TYPE_TRIE => LogKeyType::Trie, KEY_TYPE_FLAT => LogKeyType::Flat, _ => LogKeyType::Unknown }
+}
+
+/// Display form of a trie-log key, split into its node path, leaf felt and owning contract.
+struct LogKey {
+    path_hex: Option<String>,
+    key: Option<String>,
+    contract: Option<String>,
+}
+
+impl LogKey {
+    fn decode(trie: TrieKind, identifier: &[u8], key_bits: Option<&PathBits>) -> Self {
+        let contract = (trie == TrieKind::Storage && identifier.len() == 32)
+            .then(|| format!("{:#x}", Felt::from_bytes_be_slice(identifier)));
+        let key = key_bits
+            .filter(|bits| bits.len() == LEAF_PATH_LEN)
+            .map(|bits| format!("{:#x}", path_to_felt(bits)));
+        Self { path_hex: key_bits.map(|bits| bytes_to_hex(&bits.to_bytes())), key, contract }
+    }
+}
+
+fn change_value(value: LogValue) -> ChangeValue {
+    match value {
+        LogValue::Felt(felt) => ChangeValue { felt: Some(format!("{felt:#x}")), node: None, raw: None },
+        LogValue::Node(node) => ChangeValue { felt: None, node: Some(node_to_view(node)), raw: None },
+        LogValue::Raw(bytes) => ChangeValue { felt: None, node: None, raw: Some(bytes_to_hex(&bytes)) },
+    }
+}
+
+fn log_value_display(value: &[u8]) -> String {
+    decode_felt_scale(value)
+        .map(|felt| format_felt_short(&felt))
+        .unwrap_or_else(|| bytes_to_hex(value))
+}
+
+/// Recomputes every hash of the trie; `progress` counts visited nodes while it runs.
+pub fn check_integrity(
+    db: &RocksDb,
+    trie: TrieKind,
+    identifier: Option<String>,
+    block: Option<u64>,
+    progress: &AtomicU64,
+) -> IntegrityResponse {
+    let mut resp = IntegrityResponse {
+        trie,
+        nodes_checked: 0,
+        leaves_checked: 0,
+        stored_root: None,
+        computed_root: None,
+        mismatches: Vec::new(),
+        error: None,
     };
-    let root_hash = match &root_node {
-        Node::Binary(bin) => bin.hash,
-        Node::Edge(edge) => edge.hash,
+    let reader = match open_reader(db, trie, identifier, block) {
+        Ok(reader) => reader,
+        Err(err) => {
+            resp.error = Some(err);
+            return resp;
+        }
     };
-    let Some(root_hash) = root_hash else {
-        return ProofResponse { verified: false, nodes: Vec::new() };
+    let report = check_trie_integrity(&reader, trie, progress);
+    resp.nodes_checked = report.nodes_checked;
+    resp.leaves_checked = report.leaves_checked;
+    resp.stored_root = report.stored_root.map(|h| format!("{h:#x}"));
+    resp.computed_root = report.computed_root.map(|h| format!("{h:#x}"));
+    resp.mismatches = report
+        .mismatches
+        .into_iter()
+        .map(|m| IntegrityMismatch {
+            path_hex: bytes_to_hex(&m.path.to_bytes()),
+            path_len: m.path.len(),
+            kind: match m.kind {
+                MismatchKind::NodeHash => "node_hash",
+                MismatchKind::ChildHash => "child_hash",
+                MismatchKind::MissingNode => "missing_node",
+                MismatchKind::MissingLeaf => "missing_leaf",
+            }
+            .to_string(),
+            expected: m.expected.map(|h| format!("{h:#x}")),
+            found: m.found.map(|h| format!("{h:#x}")),
+        })
+        .collect();
+    resp
+}
+
+/// Compares the flat CF with the trie leaves; `progress` counts keys compared.
+pub fn check_consistency(
+    db: &RocksDb,
+    trie: TrieKind,
+    identifier: Option<String>,
+    block: Option<u64>,
+    progress: &AtomicU64,
+) -> ConsistencyResponse {
+    let mut resp = ConsistencyResponse {
+        trie,
+        flat_entries: 0,
+        trie_leaves: 0,
+        matched: 0,
+        missing_nodes: 0,
+        issues: Vec::new(),
+        error: None,
+    };
+    let reader = match open_reader(db, trie, identifier, block) {
+        Ok(reader) => reader,
+        Err(err) => {
+            resp.error = Some(err);
+            return resp;
+        }
+    };
+    let report = check_flat_consistency(&reader, progress);
+    resp.flat_entries = report.flat_entries;
+    resp.trie_leaves = report.trie_leaves;
+    resp.matched = report.matched;
+    resp.missing_nodes = report.missing_nodes;
+    resp.issues = report
+        .issues
+        .into_iter()
+        .map(|issue| ConsistencyIssue {
+            key: format!("{:#x}", issue.key),
+            kind: match issue.kind {
+                IssueKind::FlatOnly => "flat_only",
+                IssueKind::TrieOnly => "trie_only",
+                IssueKind::ValueMismatch => "value_mismatch",
+            }
+            .to_string(),
+            flat: issue.flat.map(|v| format!("{v:#x}")),
+            trie: issue.trie.map(|v| format!("{v:#x}")),
+        })
+        .collect();
+    resp
+}
+
+/// Every contract with a storage trie, found by scanning the storage CFs; `progress` counts keys.
+pub fn storage_contracts(db: &RocksDb, progress: &AtomicU64) -> StorageContractsResponse {
+    match discover_storage_contracts(db, progress) {
+        Ok(contracts) => StorageContractsResponse {
+            contracts: contracts
+                .into_iter()
+                .map(|contract| StorageContractView {
+                    address: format!("{:#x}", contract.address),
+                    leaves: contract.leaves,
+                    trie_nodes: contract.trie_nodes,
+                    bytes: contract.bytes,
+                })
+                .collect(),
+            error: None,
+        },
+        Err(err) => StorageContractsResponse { contracts: Vec::new(), error: Some(err.to_string()) },
+    }
+}
+
+/// Node mix, depth and edge-length distributions of the trie; `progress` counts visited nodes.
+pub fn trie_stats(
+    db: &RocksDb,
+    trie: TrieKind,
+    identifier: Option<String>,
+    block: Option<u64>,
+    progress: &AtomicU64,
+) -> TrieStatsResponse {
+    let mut resp = TrieStatsResponse {
+        trie,
+        identifier: identifier.clone(),
+        binary_nodes: 0,
+        edge_nodes: 0,
+        leaves: 0,
+        missing_nodes: 0,
+        node_bytes: 0,
+        max_proof_len: 0,
+        avg_proof_len: 0.0,
+        leaf_depths: Vec::new(),
+        edge_lengths: Vec::new(),
+        error: None,
+    };
+    let reader = match open_reader(db, trie, identifier, block) {
+        Ok(reader) => reader,
+        Err(err) => {
+            resp.error = Some(err);
+            return resp;
+        }
+    };
+    let stats = collect_stats(&reader, progress);
+    let buckets = |map: &BTreeMap<usize, u64>| {
+        map.iter().map(|(value, count)| HistogramBucket { value: *value as u64, count: *count }).collect()
+    };
+    resp.binary_nodes = stats.binary_nodes;
+    resp.edge_nodes = stats.edge_nodes;
+    resp.leaves = stats.leaves;
+    resp.missing_nodes = stats.missing_nodes;
+    resp.node_bytes = stats.node_bytes;
+    resp.max_proof_len = stats.max_proof_len() as u64;
+    resp.avg_proof_len = stats.avg_proof_len();
+    resp.leaf_depths = buckets(&stats.leaf_depths);
+    resp.edge_lengths = buckets(&stats.edge_lengths);
+    resp
+}
+
+pub fn proof_for_key(db: &RocksDb, trie: TrieKind, identifier: Option<String>, key_hex: &str, block: Option<u64>) -> ProofResponse {
+    let failed = |err: String| ProofResponse { verified: false, membership: None, value: None, nodes: Vec::new(), document: None, error: Some(err) };
+    let mut reader = match open_reader(db, trie, identifier.clone(), block) {
+        Ok(reader) => reader,
+        Err(err) => return failed(err),
+    };
+    let felt = match parse_trie_key(trie, key_hex) {
+        Ok(f) => f,
+        Err(err) => return failed(err),
+    };
+    let key_path = felt_to_path(&felt);
+    let Some(root_hash) = root_hash(&mut reader) else {
+        return failed("root hash missing".to_string());
     };
     let proof = match build_proof(&mut reader, &key_path) {
         Some(p) => p,
-        None => return ProofResponse { verified: false, nodes: Vec::new() },
+        None => return failed("failed to build proof".to_string()),
     };
-    let verified = verify_proof(root_hash, &key_path, &proof, trie);
+    let leaf_value = reader.load_flat_value(&key_path);
+    let result = verify_proof(root_hash, &key_path, &proof, trie, leaf_value);
+    let document = result.as_ref().ok().map(|membership| proof_document(trie, identifier, block, root_hash, felt, *membership, &proof));
+    let outcome = ProofOutcome::from(result);
+    let nodes: Vec<ProofNodeJson> = proof.iter().map(|node| node.to_json(trie)).collect();
+    ProofResponse {
+        verified: outcome.verified,
+        membership: outcome.membership,
+        value: outcome.value,
+        nodes,
+        document,
+        error: outcome.error,
+    }
+}
+
+/// Verifies a `ProofDocument` as written by `proof_for_key` or the desktop export. Beyond
+/// the hash chain, every node hash the document states must match the one computed for
+/// it. `check` ties the membership claim to the value, which the chain then proves.
+pub fn verify_proof_document(doc: &ProofDocument) -> VerifyProofResponse {
+    if let Err(err) = doc.check() {
+        return VerifyProofResponse { verified: false, membership: None, value: None, steps: Vec::new(), error: Some(err) };
+    }
+    let mut response = verify_external_proof(&VerifyProofRequest::from(doc));
+    if !response.verified {
+        return response;
+    }
+    let stated_mismatch = response.steps.iter().find_map(|step| {
+        let stated = doc.nodes[step.index].hash.as_deref()?;
+        match parse_felt_hex(stated) {
+            Ok(hash) if format!("{hash:#x}") == step.computed => None,
+            Ok(_) => Some(format!("node {} states hash {stated} but hashes to {}", step.index, step.computed)),
+            Err(err) => Some(format!("node {} hash: {err}", step.index)),
+        }
+    });
+    if let Some(err) = stated_mismatch {
+        response.verified = false;
+        response.error = Some(err);
+    }
+    response
+}
+
+/// Most keys one `batch_proof` call accepts.
+pub const MAX_BATCH_PROOF_KEYS: usize = 1000;
+
+/// Proofs for many keys of one trie against a single root, sharing their common nodes.
+pub fn batch_proof(db: &RocksDb, trie: TrieKind, identifier: Option<String>, keys: &[String], block: Option<u64>) -> BatchProofResponse {
+    let failed = |err: String| BatchProofResponse { root: None, nodes: Vec::new(), keys: Vec::new(), error: Some(err) };
+    if keys.len() > MAX_BATCH_PROOF_KEYS {
+        return failed(format!("too many keys: {} (max {MAX_BATCH_PROOF_KEYS})", keys.len()));
+    }
+    let mut reader = match open_reader(db, trie, identifier, block) {
+        Ok(reader) => reader,
+        Err(err) => return failed(err),
+    };
+    let felts = match keys.iter().map(|key| parse_trie_key(trie, key)).collect::<Result<Vec<_>, _>>() {
+        Ok(felts) => felts,
+        Err(err) => return failed(err),
+    };
+    let key_paths: Vec<PathBits> = felts.iter().map(felt_to_path).collect();
+    let Some(root_hash) = root_hash(&mut reader) else {
+        return failed("root hash missing".to_string());
+    };
+    let Some(proof) = build_multi_proof(&mut reader, &key_paths) else {
+        return failed("failed to build proof".to_string());
+    };
+    let values: Vec<Option<Felt>> = key_paths.iter().map(|path| reader.load_flat_value(path)).collect();
+    let results = verify_multi_proof(root_hash, &key_paths, &proof, trie, &values);
+    let keys = felts
+        .iter()
+        .zip(results)
+        .zip(&proof.indices)
+        .map(|((felt, result), indices)| {
+            let outcome = ProofOutcome::from(result);
+            KeyProof {
+                key: format!("{felt:#x}"),
+                nodes: indices.clone(),
+                verified: outcome.verified,
+                membership: outcome.membership,
+                value: outcome.value,
+                error: outcome.error,
+            }
+        })
+        .collect();
+    BatchProofResponse {
+        root: Some(format!("{root_hash:#x}")),
+        nodes: proof.nodes.iter().map(|node| node.to_json(trie)).collect(),
+        keys,
+        error: None,
+    }
+}
+
+/// Checks a proof supplied by the caller, reporting every node on the hash chain.
+///
+/// Nodes in our JSON format from before edge paths were exported have no `path`; those
+/// edges take their path from the key. That reconstructs every edge on the key's path
+/// but not a diverging one, so such non-membership proofs cannot verify.
+pub fn verify_external_proof(req: &VerifyProofRequest) -> VerifyProofResponse {
+    let failed = |err: String| VerifyProofResponse { verified: false, membership: None, value: None, steps: Vec::new(), error: Some(err) };
+    let (root, key, value, proof, inferred) = match parse_external_proof(req) {
+        Ok(parsed) => parsed,
+        Err(err) => return failed(err),
+    };
+
+    let (steps, result) = verify_proof_steps(root, &key, &proof, req.trie, value);
+    let steps = steps
+        .iter()
+        .map(|step| {
+            let node = &proof[step.index];
+            VerifyStepView {
+                index: step.index,
+                kind: node.to_json(req.trie).kind,
+                depth: step.depth,
+                expected: format!("{:#x}", step.expected),
+                computed: format!("{:#x}", step.computed),
+                ok: step.ok(),
+                note: match node {
+                    ProofNode::Edge { .. } if inferred[step.index] && !step.ok() => {
+                        Some("edge path taken from the key; a diverging edge needs the node-map format".to_string())
+                    }
+                    ProofNode::Edge { path, .. } if key.0.get(step.depth..step.depth + path.len()) != Some(&path.0) => {
+                        Some(format!("{}-bit edge, leaves the key", path.len()))
+                    }
+                    ProofNode::Edge { path, .. } => Some(format!("{}-bit edge", path.len())),
+                    ProofNode::Binary { .. } => step.ok().then(|| format!("goes {}", if key.0[step.depth] { "right" } else { "left" })),
+                },
+            }
+        })
+        .collect();
+    let outcome = ProofOutcome::from(result);
+    VerifyProofResponse {
+        verified: outcome.verified,
+        membership: outcome.membership,
+        value: outcome.value,
+        steps,
+        error: outcome.error,
+    }
+}
+
+type ExternalProof = (Felt, PathBits, Option<Felt>, Vec<ProofNode>, Vec<bool>);
+
+fn parse_external_proof(req: &VerifyProofRequest) -> Result<ExternalProof, String> {
+    let root = parse_felt_hex(&req.root).map_err(|err| format!("root: {err}"))?;
+    let key = felt_to_path(&parse_felt_hex(&req.key).map_err(|err| format!("key: {err}"))?);
+    let value = req.value.as_deref().map(parse_felt_hex).transpose().map_err(|err| format!("value: {err}"))?;
+    let (proof, inferred) = match (&req.nodes, &req.node_map) {
+        (Some(nodes), None) => proof_from_json(nodes, &key)?,
+        (None, Some(node_map)) => {
+            let nodes = node_map
+                .iter()
+                .map(|entry| Ok((parse_felt_hex(&entry.node_hash)?, proof_node_from_merkle(&entry.node)?)))
+                .collect::<Result<HashMap<_, _>, String>>()?;
+            let proof = order_node_map(root, &key, &nodes);
+            let inferred = vec![false; proof.len()];
+            (proof, inferred)
+        }
+        _ => return Err("supply exactly one of nodes or node_map".to_string()),
+    };
+    Ok((root, key, value, proof, inferred))
+}
+
+/// Our JSON nodes as proof nodes. Edges without a `path` read theirs off `key`; the
+/// flags mark those edges.
+fn proof_from_json(nodes: &[ProofNodeJson], key: &PathBits) -> Result<(Vec<ProofNode>, Vec<bool>), String> {
+    let mut depth = 0;
+    let mut proof = Vec::with_capacity(nodes.len());
+    let mut inferred = Vec::with_capacity(nodes.len());
+    for (index, node) in nodes.iter().enumerate() {
+        let field = |value: &Option<String>, name: &str| {
+            let value = value.as_deref().ok_or_else(|| format!("node {index}: missing {name}"))?;
+            parse_felt_hex(value).map_err(|err| format!("node {index}: {name}: {err}"))
+        };
+        match node.kind.as_str() {
+            "binary" => {
+                if depth >= LEAF_PATH_LEN {
+                    return Err(format!("node {index}: binary node below the leaf"));
+                }
+                proof.push(ProofNode::Binary { left: field(&node.left, "left")?, right: field(&node.right, "right")? });
+                inferred.push(false);
+                depth += 1;
+            }
+            "edge" => {
+                let len = node.path_len.ok_or_else(|| format!("node {index}: missing path_len"))?;
+                // `path_len` comes from the caller, so the sum must not overflow.
+                if depth.checked_add(len).is_none_or(|end| end > LEAF_PATH_LEN) {
+                    return Err(format!("node {index}: edge runs past the key"));
+                }
+                let path = match &node.path {
+                    Some(_) => felt_to_bits(&field(&node.path, "path")?, len),
+                    None => PathBits(key.0[depth..depth + len].to_owned()),
+                };
+                proof.push(ProofNode::Edge { child: field(&node.child, "child")?, path });
+                inferred.push(node.path.is_none());
+                depth += len;
+            }
+            other => return Err(format!("node {index}: unknown kind {other}")),
+        }
+    }
+    Ok((proof, inferred))
+}
+
+fn proof_node_from_merkle(node: &MerkleNode) -> Result<ProofNode, String> {
+    Ok(match node {
+        MerkleNode::Binary { left, right } => ProofNode::Binary { left: parse_felt_hex(left)?, right: parse_felt_hex(right)? },
+        MerkleNode::Edge { path, length, child } => {
+            if *length > LEAF_PATH_LEN {
+                return Err(format!("edge length {length} exceeds {LEAF_PATH_LEN}"));
+            }
+            ProofNode::Edge { child: parse_felt_hex(child)?, path: felt_to_bits(&parse_felt_hex(path)?, *length) }
+        }
+    })
+}
+
+/// Proves `key` in `contract`'s storage against the contract trie root, as of `block`.
+///
+/// The contract leaf's preimage is the class hash and nonce from Madara's contract columns
+/// plus the storage trie root. It must hash to the leaf the contract proof shows, and the
+/// storage proof must start from that storage root.
+pub fn contract_storage_proof(db: &RocksDb, contract: &str, key: &str, block: Option<u64>) -> ContractStorageProofResponse {
+    let mut resp = ContractStorageProofResponse {
+        verified: false,
+        contract_proof: proof_for_key(db, TrieKind::Contract, None, contract, block),
+        leaf: None,
+        leaf_hash: None,
+        storage_proof: proof_for_key(db, TrieKind::Storage, Some(contract.to_string()), key, block),
+        error: None,
+    };
+    if let Err(err) = check_contract_leaf(db, contract, block, &mut resp) {
+        resp.error = Some(err);
+        return resp;
+    }
+    resp.verified = resp.contract_proof.verified && resp.storage_proof.verified;
+    resp
+}
+
+/// Fills in the contract leaf preimage and checks both links of a contract storage proof.
+fn check_contract_leaf(db: &RocksDb, contract: &str, block: Option<u64>, resp: &mut ContractStorageProofResponse) -> Result<(), String> {
+    if let Some(err) = &resp.contract_proof.error {
+        return Err(format!("contract proof: {err}"));
+    }
+    if let Some(err) = &resp.storage_proof.error {
+        return Err(format!("storage proof: {err}"));
+    }
+    let Some(proven_leaf) = resp.contract_proof.value.clone() else {
+        return Err("contract is not in the contract trie".to_string());
+    };
+    let (leaf, leaf_hash) = contract_leaf(db, &parse_felt_hex(contract)?, block)?;
+    let leaf_hash = format!("{leaf_hash:#x}");
+    let storage_root = leaf.storage_root.clone();
+    resp.leaf = Some(leaf);
+    resp.leaf_hash = Some(leaf_hash.clone());
+
+    if leaf_hash != proven_leaf {
+        return Err(format!("contract leaf is {proven_leaf}, but its preimage hashes to {leaf_hash}"));
+    }
+    let proven_root = resp.storage_proof.document.as_ref().map(|doc| doc.root.clone());
+    if proven_root.as_ref() != Some(&storage_root) {
+        return Err(format!("storage proof root {} is not the contract's storage root {storage_root}", proven_root.unwrap_or_default()));
+    }
+    Ok(())
+}
+
+/// What a contract's leaf commits to, with the leaf hash it gives: class hash and nonce
+/// from Madara's contract columns and the root of its storage trie.
+fn contract_leaf(db: &RocksDb, address: &Felt, block: Option<u64>) -> Result<(ContractLeafData, Felt), String> {
+    let mut storage = open_reader(db, TrieKind::Storage, Some(format!("{address:#x}")), block)?;
+    let storage_root = root_hash(&mut storage).ok_or_else(|| "storage root hash missing".to_string())?;
+    let class_hash = madara::contract_class_hash(db, address, block)?;
+    let nonce = madara::contract_nonce(db, address, block)?;
+    let data = ContractLeafData {
+        nonce: format!("{nonce:#x}"),
+        class_hash: format!("{class_hash:#x}"),
+        storage_root: format!("{storage_root:#x}"),
+    };
+    Ok((data, madara::contract_state_hash(&class_hash, &storage_root, &nonce)))
+}
+
+/// A contract's leaf in the contract trie, proven against the root, next to the hash
+/// recomputed from its class hash, nonce and storage root.
+pub fn contract_info(db: &RocksDb, address: &str, block: Option<u64>) -> ContractResponse {
+    let mut resp = ContractResponse { address: address.to_string(), leaf: None, state: None, computed_leaf: None, matches: false, error: None };
+    let felt = match parse_felt_hex(address) {
+        Ok(felt) => felt,
+        Err(err) => {
+            resp.error = Some(err);
+            return resp;
+        }
+    };
+    resp.address = format!("{felt:#x}");
+    let proof = proof_for_key(db, TrieKind::Contract, None, &resp.address, block);
+    match (proof.error, proof.membership) {
+        (Some(err), _) => resp.error = Some(format!("contract proof: {err}")),
+        (None, Some(ProofMembership::NonMember)) => resp.error = Some("contract is not in the contract trie".to_string()),
+        _ => resp.leaf = proof.value,
+    }
+    match contract_leaf(db, &felt, block) {
+        Ok((state, computed)) => {
+            let computed = format!("{computed:#x}");
+            resp.matches = resp.leaf.as_ref() == Some(&computed);
+            resp.state = Some(state);
+            resp.computed_leaf = Some(computed);
+        }
+        Err(err) => resp.error = resp.error.take().or(Some(err)),
+    }
+    resp
+}
+
+/// A class's leaf in the class trie, proven against the root, next to the leaf recomputed
+/// from its compiled class hash and the block whose class log declared it.
+pub fn class_info(db: &RocksDb, class_hash: &str, block: Option<u64>) -> ClassResponse {
+    let felt = match parse_felt_hex(class_hash) {
+        Ok(felt) => felt,
+        Err(err) => {
+            return ClassResponse {
+                class_hash: class_hash.to_string(),
+                leaf: None,
+                compiled_class_hash: None,
+                computed_leaf: None,
+                matches: false,
+                declared_block: None,
+                error: Some(err),
+            }
+        }
+    };
+    let proof = proof_for_key(db, TrieKind::Class, None, &format!("{felt:#x}"), block);
+    let (leaf, proof_error) = match (proof.error, proof.membership) {
+        (Some(err), _) => (None, Some(format!("class proof: {err}"))),
+        (None, Some(ProofMembership::NonMember)) => (None, Some("class is not in the class trie".to_string())),
+        _ => (proof.value, None),
+    };
+    let declared = declared_blocks(db, [felt]);
+    let mut resp = class_response(db, felt, leaf, declared.get(&felt).copied());
+    resp.error = proof_error.or(resp.error);
+    resp
+}
+
+/// One page of the class trie (latest state), each leaf checked as in [`class_info`].
+pub fn list_classes(db: &RocksDb, cursor: Option<&str>, limit: usize) -> ClassesResponse {
+    let page = list_leaves(db, TrieKind::Class, None, cursor, None, None, limit);
+    if let Some(err) = page.error {
+        return ClassesResponse { classes: Vec::new(), next_cursor: None, error: Some(err) };
+    }
+    let leaves: Vec<(Felt, Option<String>)> =
+        page.leaves.into_iter().filter_map(|leaf| Some((parse_felt_hex(&leaf.key).ok()?, leaf.value))).collect();
+    let declared = declared_blocks(db, leaves.iter().map(|(class_hash, _)| *class_hash));
+    let classes = leaves
+        .into_iter()
+        .map(|(class_hash, leaf)| class_response(db, class_hash, leaf, declared.get(&class_hash).copied()))
+        .collect();
+    ClassesResponse { classes, next_cursor: page.next_cursor, error: None }
+}
+
+/// Checks a class trie `leaf` against `Poseidon("CONTRACT_CLASS_LEAF_V0", compiled_class_hash)`.
+fn class_response(db: &RocksDb, class_hash: Felt, leaf: Option<String>, declared_block: Option<u64>) -> ClassResponse {
+    let mut resp = ClassResponse {
+        class_hash: format!("{class_hash:#x}"),
+        leaf,
+        compiled_class_hash: None,
+        computed_leaf: None,
+        matches: false,
+        declared_block,
+        error: None,
+    };
+    match madara::compiled_class_hash(db, &class_hash) {
+        Ok(Some(compiled)) => {
+            let computed = format!("{:#x}", madara::class_leaf_hash(&compiled));
+            resp.matches = resp.leaf.as_ref() == Some(&computed);
+            resp.compiled_class_hash = Some(format!("{compiled:#x}"));
+            resp.computed_leaf = Some(computed);
+        }
+        Ok(None) => resp.error = Some("no class info for this class".to_string()),
+        Err(err) => resp.error = Some(err),
+    }
+    resp
+}
+
+/// Block that first inserted each class into the class trie, read from the class log.
+fn declared_blocks(db: &RocksDb, class_hashes: impl IntoIterator<Item = Felt>) -> HashMap<Felt, u64> {
+    let inserted = first_inserted_blocks(db, cf_map::BONSAI_CLASS_LOG);
+    class_hashes
+        .into_iter()
+        .filter_map(|class_hash| {
+            let mut key = TrieKind::Class.identifier().to_vec();
+            key.extend_from_slice(&felt_to_path(&class_hash).to_bytes());
+            Some((class_hash, *inserted.get(&key)?))
+        })
+        .collect()
+}
+
+/// Most felts from the trie logs tried as keys of one-key mappings.
+pub const MAX_MAPPING_KEYS: usize = 2000;
+/// Two-key mappings are tried with every pair of the first this many keys.
+pub const MAX_PAIR_KEYS: usize = 100;
+
+/// Labels `contract`'s storage leaves (latest state) with the variables of a class or
+/// ABI: plain variables by their address, mapping entries by hashing each variable
+/// with felts seen in the trie logs, and values decoded by the variable's type.
+pub fn storage_labels(db: &RocksDb, contract: &str, layout_json: &str) -> StorageLabelsResponse {
+    let failed = |err: String| StorageLabelsResponse { variables: Vec::new(), labels: Vec::new(), leaves: 0, mapping_keys: 0, error: Some(err) };
+    let layout = match StorageLayout::from_json(layout_json) {
+        Ok(layout) => layout,
+        Err(err) => return failed(err),
+    };
+    let address = match parse_felt_hex(contract) {
+        Ok(address) => address,
+        Err(err) => return failed(err),
+    };
+    let reader = match open_reader(db, TrieKind::Storage, Some(contract.to_string()), None) {
+        Ok(reader) => reader,
+        Err(err) => return failed(err),
+    };
+    let iter = match reader.flat_leaves_from(&Felt::ZERO) {
+        Ok(iter) => iter,
+        Err(err) => return failed(err.to_string()),
+    };
+    let mapping_keys = known_mapping_keys(db, &address);
+    let slots = label_slots(&layout, &mapping_keys);
+
+    let mut labels = Vec::new();
+    let mut leaves = 0;
+    for (key, value) in iter {
+        leaves += 1;
+        if let Some((label, kind)) = slots.get(&key) {
+            labels.push(StorageLabel {
+                key: format!("{key:#x}"),
+                label: label.clone(),
+                value: value.map(|v| format!("{v:#x}")),
+                decoded: value.map(|v| decode_slot(*kind, &v)),
+            });
+        }
+    }
+    let variables = layout
+        .variables
+        .iter()
+        .map(|variable| StorageVariableView {
+            name: variable.name.clone(),
+            keys: variable.keys,
+            value_type: variable.value_type.clone(),
+            base: format!("{:#x}", variable.base),
+        })
+        .collect();
+    StorageLabelsResponse { variables, labels, leaves, mapping_keys: mapping_keys.len(), error: None }
+}
+
+/// Every storage address the layout can name, with its label and how to decode it.
+fn label_slots(layout: &StorageLayout, mapping_keys: &[Felt]) -> HashMap<Felt, (String, SlotKind)> {
+    let mut slots = HashMap::new();
+    for variable in &layout.variables {
+        let name = &variable.name;
+        let entries: Vec<(String, Felt)> = match variable.keys {
+            0 => vec![(name.clone(), storage_address(variable.base, &[]))],
+            1 => mapping_keys.iter().map(|key| (format!("{name}[{key:#x}]"), storage_address(variable.base, &[*key]))).collect(),
+            2 => {
+                let keys = &mapping_keys[..mapping_keys.len().min(MAX_PAIR_KEYS)];
+                keys.iter()
+                    .flat_map(|a| keys.iter().map(move |b| (*a, *b)))
+                    .map(|(a, b)| (format!("{name}[{a:#x}][{b:#x}]"), storage_address(variable.base, &[a, b])))
+                    .collect()
+            }
+            _ => continue,
+        };
+        for (label, address) in entries {
+            for slot in &variable.slots {
+                slots.entry(address + Felt::from(slot.offset)).or_insert_with(|| (format!("{label}{}", slot.suffix), slot.kind));
+            }
+        }
+    }
+    slots
+}
+
+/// Felts the trie logs suggest as mapping keys: addresses of contracts whose state or
+/// storage changed, then values written to `contract`'s storage, at most
+/// `MAX_MAPPING_KEYS` in all. Addresses come first since mappings are mostly keyed by them.
+fn known_mapping_keys(db: &RocksDb, contract: &Felt) -> Vec<Felt> {
+    let mut seen = HashSet::new();
+    let mut addresses = Vec::new();
+    let mut values = Vec::new();
+    for entry in log_entries(db, cf_map::BONSAI_CONTRACT_LOG) {
+        if addresses.len() >= MAX_MAPPING_KEYS {
+            break;
+        }
+        if let (KEY_TYPE_FLAT, Some(bits)) = (entry.key_type, &entry.key_bits) {
+            let address = path_to_felt(bits);
+            if seen.insert(address) {
+                addresses.push(address);
+            }
+        }
+    }
+    let identifier = contract.to_bytes_be();
+    for entry in log_entries(db, cf_map::BONSAI_CONTRACT_STORAGE_LOG) {
+        if addresses.len() >= MAX_MAPPING_KEYS {
+            break;
+        }
+        let address = Felt::from_bytes_be_slice(&entry.identifier);
+        if seen.insert(address) {
+            addresses.push(address);
+        }
+        if entry.key_type == KEY_TYPE_FLAT && entry.identifier == identifier && addresses.len() + values.len() < MAX_MAPPING_KEYS {
+            if let LogValue::Felt(value) = LogValue::decode(entry.key_type, &entry.value) {
+                if seen.insert(value) {
+                    values.push(value);
+                }
+            }
+        }
+    }
+    values.truncate(MAX_MAPPING_KEYS - addresses.len());
+    addresses.extend(values);
+    addresses
+}
+
+/// Computes the global state root as of `block` (latest when `None`) and checks it
+/// against that block's header. The latest state is compared with the header of the
+/// last block in the trie logs.
+pub fn state_root(db: &RocksDb, block: Option<u64>) -> StateRootResponse {
+    let mut resp = StateRootResponse {
+        block,
+        contract_root: None,
+        class_root: None,
+        state_root: None,
+        header_state_root: None,
+        matches: None,
+        error: None,
+    };
+    let roots = open_reader(db, TrieKind::Contract, None, block).and_then(|mut contract| {
+        let mut class = open_reader(db, TrieKind::Class, None, block)?;
+        match (root_hash(&mut contract), root_hash(&mut class)) {
+            (Some(contract), Some(class)) => Ok((contract, class)),
+            _ => Err("root hash missing".to_string()),
+        }
+    });
+    let (contract_root, class_root) = match roots {
+        Ok(roots) => roots,
+        Err(err) => {
+            resp.error = Some(err);
+            return resp;
+        }
+    };
+    let state_root = madara::global_state_root(&contract_root, &class_root);
+    resp.contract_root = Some(format!("{contract_root:#x}"));
+    resp.class_root = Some(format!("{class_root:#x}"));
+    resp.state_root = Some(format!("{state_root:#x}"));
+
+    resp.block = block.or_else(|| [cf_map::BONSAI_CONTRACT_LOG, cf_map::BONSAI_CLASS_LOG].into_iter().filter_map(|cf| latest_logged_block(db, cf)).max());
+    let Some(header_block) = resp.block else {
+        resp.error = Some("no trie logs to tell the latest block; pass a block".to_string());
+        return resp;
+    };
+    match madara::header_state_root(db, header_block) {
+        Ok(Some(header_root)) => {
+            resp.header_state_root = Some(format!("{header_root:#x}"));
+            resp.matches = Some(header_root == state_root);
+        }
+        Ok(None) => resp.error = Some(format!("no block header for block {header_block}")),
+        Err(err) => resp.error = Some(err),
+    }
+    resp
+}
+
+/// `starknet_getStorageProof` computed from the bonsai CFs, latest state or as of `block`.
+///
+/// Nonces and class hashes come from Madara's contract columns. Addresses with no leaf in
+/// the contract trie are not deployed and get all-zero leaf data. `global_roots.block_hash`
+/// is always zero: block hashes live outside the tries.
+pub fn storage_proof(
+    db: &RocksDb,
+    block: Option<u64>,
+    class_hashes: &[String],
+    contract_addresses: &[String],
+    contracts_storage_keys: &[ContractStorageKeys],
+) -> Result<StorageProofResult, String> {
+    let class_keys = parse_felts(class_hashes)?;
+    let contracts = parse_felts(contract_addresses)?;
+
+    let mut class_reader = open_reader(db, TrieKind::Class, None, block)?;
+    let (classes_proof, classes_root) = node_mapping(&mut class_reader, &class_keys, TrieKind::Class)?;
+    let mut contract_reader = open_reader(db, TrieKind::Contract, None, block)?;
+    let (contract_nodes, contracts_root) = node_mapping(&mut contract_reader, &contracts, TrieKind::Contract)?;
+
+    let contract_leaves_data = contracts
+        .iter()
+        .map(|address| {
+            if contract_reader.load_flat_value(&felt_to_path(address)).is_none() {
+                let zero = format!("{:#x}", Felt::ZERO);
+                return Ok(ContractLeafData { nonce: zero.clone(), class_hash: zero.clone(), storage_root: zero });
+            }
+            Ok(contract_leaf(db, address, block)?.0)
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+
+    let contracts_storage_proofs = contracts_storage_keys
+        .iter()
+        .map(|entry| {
+            let keys = parse_felts(&entry.storage_keys)?;
+            let mut reader = open_reader(db, TrieKind::Storage, Some(entry.contract_address.clone()), block)?;
+            Ok(node_mapping(&mut reader, &keys, TrieKind::Storage)?.0)
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+
+    Ok(StorageProofResult {
+        classes_proof,
+        contracts_proof: ContractsProof { nodes: contract_nodes, contract_leaves_data },
+        contracts_storage_proofs,
+        global_roots: GlobalRoots {
+            contracts_tree_root: format!("{contracts_root:#x}"),
+            classes_tree_root: format!("{classes_root:#x}"),
+            block_hash: format!("{:#x}", Felt::ZERO),
+        },
+    })
+}
+
+/// `starknet_getStorageAt`: the value of `key` in `contract`'s storage, zero when unset.
+/// `None` when `contract` has no leaf in the contract trie, i.e. is not deployed.
+pub fn storage_at(db: &RocksDb, contract: &str, key: &str, block: Option<u64>) -> Result<Option<String>, String> {
+    let address = parse_felt_hex(contract)?;
+    let contracts = open_reader(db, TrieKind::Contract, None, block)?;
+    if contracts.load_flat_value(&felt_to_path(&address)).is_none() {
+        return Ok(None);
+    }
+    let reader = open_reader(db, TrieKind::Storage, Some(contract.to_string()), block)?;
+    let key = parse_felt_hex(key)?;
+    let value = reader.load_flat_value(&felt_to_path(&key)).unwrap_or(Felt::ZERO);
+    Ok(Some(format!("{value:#x}")))
+}
+
+fn parse_felts(values: &[String]) -> Result<Vec<Felt>, String> {
+    values.iter().map(|value| parse_felt_hex(value)).collect()
+}
+
+/// Proof nodes for `keys` in RPC node-map form, deduplicated, plus the trie root.
+fn node_mapping(reader: &mut TrieReader, keys: &[Felt], kind: TrieKind) -> Result<(Vec<NodeHashToNode>, Felt), String> {
+    let root = root_hash(reader).ok_or_else(|| "root hash missing".to_string())?;
+    let paths: Vec<PathBits> = keys.iter().map(felt_to_path).collect();
+    let proof = build_multi_proof(reader, &paths).ok_or_else(|| "failed to build proof".to_string())?;
     let nodes = proof
+        .nodes
         .iter()
-        .map(|node| match node {
-            ProofNode::Binary { left, right } => ProofNodeJson {
-                kind: "binary".to_string(),
-                left: Some(format!("{left:#x}")),
-                right: Some(format!("{right:#x}")),
-                child: None,
-                path_len: None,
-            },
-            ProofNode::Edge { child, path } => ProofNodeJson {
-                kind: "edge".to_string(),
-                left: None,
-                right: None,
-                child: Some(format!("{child:#x}")),
-                path_len: Some(path.len()),
+        .map(|node| NodeHashToNode {
+            node_hash: format!("{:#x}", node.hash(kind)),
+            node: match node {
+                ProofNode::Binary { left, right } => MerkleNode::Binary { left: format!("{left:#x}"), right: format!("{right:#x}") },
+                ProofNode::Edge { child, path } => MerkleNode::Edge {
+                    path: format!("{:#x}", path_to_felt(path)),
+                    length: path.len(),
+                    child: format!("{child:#x}"),
+                },
             },
         })
         .collect();
-    ProofResponse { verified, nodes }
+    Ok((nodes, root))
+}
+
+/// An empty trie has no root node and commits to zero.
+fn root_hash(reader: &mut TrieReader) -> Option<Felt> {
+    match reader.load_root_node() {
+        None => Some(Felt::ZERO),
+        Some(Node::Binary(bin)) => bin.hash,
+        Some(Node::Edge(edge)) => edge.hash,
+    }
+}
+
+/// Response fields describing a verification result.
+struct ProofOutcome {
+    verified: bool,
+    membership: Option<ProofMembership>,
+    value: Option<String>,
+    error: Option<String>,
+}
+
+impl From<Result<Membership, ProofError>> for ProofOutcome {
+    fn from(result: Result<Membership, ProofError>) -> Self {
+        match result {
+            Ok(Membership::Member(value)) => Self {
+                verified: true,
+                membership: Some(ProofMembership::Member),
+                value: Some(format!("{value:#x}")),
+                error: None,
+            },
+            Ok(Membership::NonMember) => Self { verified: true, membership: Some(ProofMembership::NonMember), value: None, error: None },
+            Err(err) => Self { verified: false, membership: None, value: None, error: Some(err.to_string()) },
+        }
+    }
+}
+
+/// Latest state when `block` is `None`, otherwise the trie rewound to `block` via its logs.
+fn open_reader(db: &RocksDb, trie: TrieKind, identifier: Option<String>, block: Option<u64>) -> Result<TrieReader, String> {
+    let spec = build_spec(trie, identifier)?;
+    match block {
+        Some(block) => historical_reader(db.clone(), spec, block).map_err(|e| e.to_string()),
+        None => Ok(TrieReader::new(db.clone(), spec)),
+    }
 }
 
 fn build_spec(trie: TrieKind, identifier: Option<String>) -> Result<TrieSpec, String> {
@@ -206,7 +1404,7 @@ fn node_to_view(node: Node) -> NodeView {
 
 fn hex_to_bytes(hex: &str) -> Option<Vec<u8>> {
     let s = hex.trim().strip_prefix("0x").unwrap_or(hex.trim());
-    if s.len() % 2 != 0 {
+    if !s.len().is_multiple_of(2) {
         return None;
     }
     let mut out = Vec::with_capacity(s.len() / 2);
@@ -216,3 +1414,565 @@ fn hex_to_bytes(hex: &str) -> Option<Vec<u8>> {
     }
     Some(out)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{sierra_class_info, spec, TestDb};
+    use bonsai_types::ChangeKind;
+
+    fn edge(path_len: usize) -> ProofNodeJson {
+        ProofNodeJson { kind: "edge".to_string(), hash: None, left: None, right: None, child: Some("0x1".to_string()), path_len: Some(path_len), path: None }
+    }
+
+    fn binary() -> ProofNodeJson {
+        ProofNodeJson { kind: "binary".to_string(), hash: None, left: Some("0x1".to_string()), right: Some("0x2".to_string()), child: None, path_len: None, path: None }
+    }
+
+    #[test]
+    fn proof_json_rejects_paths_past_the_leaf() {
+        let key = felt_to_path(&Felt::from(5u64));
+        let (nodes, inferred) = proof_from_json(&[binary(), edge(LEAF_PATH_LEN - 1)], &key).unwrap();
+        assert_eq!((nodes.len(), inferred), (2, vec![false, true]));
+
+        assert!(proof_from_json(&[edge(usize::MAX)], &key).unwrap_err().contains("past the key"));
+        assert!(proof_from_json(&[binary(), edge(LEAF_PATH_LEN)], &key).unwrap_err().contains("past the key"));
+        assert!(proof_from_json(&[edge(LEAF_PATH_LEN), binary()], &key).unwrap_err().contains("below the leaf"));
+    }
+
+    #[test]
+    fn proof_documents_round_trip() {
+        let test_db = TestDb::new();
+        let spec = build_spec(TrieKind::Storage, Some("0x11".to_string())).unwrap();
+        test_db.commit(5, &spec, TrieKind::Storage, &[(Felt::from(1u64), Felt::from(10u64)), (Felt::from(2u64), Felt::from(20u64))]);
+        let db = test_db.open();
+
+        for (key, membership) in [("0x1", ProofMembership::Member), ("0x3", ProofMembership::NonMember)] {
+            let doc = proof_for_key(&db, TrieKind::Storage, Some("0x11".to_string()), key, None).document.unwrap();
+            assert_eq!(doc.membership, membership);
+            let json = serde_json::to_string(&doc).unwrap();
+            let response = verify_proof_document(&serde_json::from_str(&json).unwrap());
+            assert!(response.verified, "{key}: {:?}", response.error);
+            assert_eq!(response.membership, Some(membership));
+        }
+    }
+
+    #[test]
+    fn proof_documents_must_agree_with_themselves() {
+        let test_db = TestDb::new();
+        let spec = build_spec(TrieKind::Storage, Some("0x11".to_string())).unwrap();
+        test_db.commit(5, &spec, TrieKind::Storage, &[(Felt::from(1u64), Felt::from(10u64)), (Felt::from(2u64), Felt::from(20u64))]);
+        let db = test_db.open();
+        let doc = proof_for_key(&db, TrieKind::Storage, Some("0x11".to_string()), "0x1", None).document.unwrap();
+
+        let no_value = ProofDocument { value: None, ..doc.clone() };
+        assert!(!verify_proof_document(&no_value).verified);
+        let non_member = ProofDocument { membership: ProofMembership::NonMember, ..doc.clone() };
+        assert!(!verify_proof_document(&non_member).verified);
+
+        let mut wrong_hash = doc.clone();
+        wrong_hash.nodes[0].hash = Some("0x1234".to_string());
+        let response = verify_proof_document(&wrong_hash);
+        assert!(!response.verified);
+        assert!(response.error.unwrap().contains("states hash 0x1234"));
+    }
+
+    #[test]
+    fn mapping_keys_try_addresses_first() {
+        let test_db = TestDb::new();
+        let contract = Felt::from_hex_unchecked("0x49d36570d4e46f48e99674bd3fcc84644ddd6b96f7c741b1562b82f9e004dc7");
+        let other = Felt::from_hex_unchecked("0x53c91253bc9682c04929ca02ed00b3e423f6710d2ee7e0d5ebb06f3ecf368a8");
+        let contracts = build_spec(TrieKind::Contract, None).unwrap();
+        test_db.commit(5, &contracts, TrieKind::Contract, &[(contract, Felt::ONE), (other, Felt::TWO)]);
+        let storage = build_spec(TrieKind::Storage, Some(format!("{contract:#x}"))).unwrap();
+        test_db.commit(5, &storage, TrieKind::Storage, &[(Felt::ONE, Felt::from(7u64)), (Felt::TWO, other), (Felt::THREE, Felt::from(3u64))]);
+        let db = test_db.open();
+
+        // Small written values no longer crowd out the addresses, and `other` is tried once.
+        assert_eq!(known_mapping_keys(&db, &contract), vec![contract, other, Felt::from(7u64), Felt::from(3u64)]);
+    }
+
+    #[test]
+    fn storage_proofs_cover_absent_contracts() {
+        let test_db = TestDb::new();
+        let (deployed, absent) = (Felt::from(0x11u64), Felt::from(0x99u64));
+        test_db.commit(5, &build_spec(TrieKind::Contract, None).unwrap(), TrieKind::Contract, &[(deployed, Felt::from(0xabcu64))]);
+        test_db.commit(5, &build_spec(TrieKind::Storage, Some("0x11".to_string())).unwrap(), TrieKind::Storage, &[(Felt::ONE, Felt::from(7u64))]);
+        test_db.deploy(&deployed, 5, &Felt::from(0xc1a55u64));
+        let db = test_db.open();
+
+        let proof = storage_proof(&db, None, &[], &["0x11".to_string(), "0x99".to_string()], &[]).unwrap();
+        let leaves = &proof.contracts_proof.contract_leaves_data;
+        assert_eq!(leaves[0].class_hash, "0xc1a55");
+        assert_eq!((leaves[1].class_hash.as_str(), leaves[1].nonce.as_str(), leaves[1].storage_root.as_str()), ("0x0", "0x0", "0x0"));
+        let root = parse_felt_hex(&proof.global_roots.contracts_tree_root).unwrap();
+        let nodes: HashMap<Felt, ProofNode> = proof
+            .contracts_proof
+            .nodes
+            .iter()
+            .map(|entry| (parse_felt_hex(&entry.node_hash).unwrap(), proof_node_from_merkle(&entry.node).unwrap()))
+            .collect();
+        let key = felt_to_path(&absent);
+        assert!(matches!(verify_proof(root, &key, &order_node_map(root, &key, &nodes), TrieKind::Contract, None), Ok(Membership::NonMember)));
+
+        assert_eq!(storage_at(&db, "0x11", "0x1", None), Ok(Some("0x7".to_string())));
+        assert_eq!(storage_at(&db, "0x11", "0x2", None), Ok(Some("0x0".to_string())));
+        assert_eq!(storage_at(&db, "0x99", "0x1", None), Ok(None));
+    }
+
+    /// Storage of 0x11 over blocks 1..=8: key 1 stays 0xa, key 3 appears at block 2,
+    /// key 2 changes at block 8 and key 5 changes every block.
+    fn bisect_fixture() -> TestDb {
+        let test_db = TestDb::new();
+        let spec = build_spec(TrieKind::Storage, Some("0x11".to_string())).unwrap();
+        for block in 1..=8u64 {
+            let mut leaves = vec![(Felt::ONE, Felt::from(10u64)), (Felt::TWO, Felt::from(if block == 8 { 21u64 } else { 20 })), (Felt::from(5u64), Felt::from(block))];
+            if block >= 2 {
+                leaves.push((Felt::THREE, Felt::from(30u64)));
+            }
+            test_db.commit(block, &spec, TrieKind::Storage, &leaves);
+        }
+        test_db
+    }
+
+    fn bisect_value(db: &RocksDb, start: u64, end: u64, key: &str, value: &str) -> BisectResponse {
+        let req = BisectRequest {
+            trie: TrieKind::Storage,
+            identifier: Some("0x11".to_string()),
+            start,
+            end,
+            key: Some(key.to_string()),
+            value: Some(value.to_string()),
+            root: None,
+        };
+        bisect(db, &req)
+    }
+
+    #[test]
+    fn bisect_handles_unchanged_values() {
+        let test_db = bisect_fixture();
+        let db = test_db.open();
+
+        // Holding throughout finds the start after probing both ends.
+        let resp = bisect_value(&db, 2, 8, "0x1", "0xa");
+        assert_eq!((resp.block, resp.error), (Some(2), None));
+        assert_eq!(resp.steps.iter().map(|step| step.block).collect::<Vec<_>>(), vec![8, 2]);
+        // Never holding stops at the end probe.
+        let resp = bisect_value(&db, 1, 8, "0x1", "0xb");
+        assert_eq!((resp.block, resp.steps.len()), (None, 1));
+        assert_eq!(resp.steps[0].observed, "0xa");
+    }
+
+    #[test]
+    fn bisect_finds_changes_at_the_range_ends() {
+        let test_db = bisect_fixture();
+        let db = test_db.open();
+
+        assert_eq!(bisect_value(&db, 2, 8, "0x3", "0x1e").block, Some(2));
+        let resp = bisect_value(&db, 1, 8, "0x3", "0x1e");
+        assert_eq!((resp.block, resp.error), (Some(2), None));
+        assert!(resp.steps.iter().any(|step| step.block == 1 && !step.holds && step.observed == "0x0"));
+
+        let resp = bisect_value(&db, 1, 8, "0x2", "0x15");
+        assert_eq!((resp.block, resp.error), (Some(8), None));
+        assert!(resp.steps.iter().any(|step| step.block == 7 && !step.holds));
+    }
+
+    #[test]
+    fn bisect_rejects_inverted_ranges() {
+        let test_db = bisect_fixture();
+        let db = test_db.open();
+
+        let resp = bisect_value(&db, 8, 1, "0x1", "0xa");
+        assert_eq!(resp.block, None);
+        assert!(resp.steps.is_empty());
+        assert_eq!(resp.error.as_deref(), Some("start block 8 is after end block 1"));
+    }
+
+    #[test]
+    fn block_activity_counts_entries_per_block() {
+        let test_db = TestDb::new();
+        let log = |trie: TrieKind, identifier: Option<&str>, block: u64, leaf: Option<u64>, key_type: u8| {
+            let spec = build_spec(trie, identifier.map(str::to_string)).unwrap();
+            let path = leaf.map(|key| felt_to_path(&Felt::from(key))).unwrap_or_default();
+            let trie_key = [spec.identifier.as_slice(), &path.to_bytes()].concat();
+            test_db.log(&spec.log_cf, block, &trie_key, key_type, CHANGE_NEW, &[]);
+        };
+        log(TrieKind::Storage, Some("0x11"), 1, Some(1), KEY_TYPE_FLAT);
+        log(TrieKind::Storage, Some("0x11"), 3, Some(1), KEY_TYPE_FLAT);
+        log(TrieKind::Storage, Some("0x11"), 3, Some(2), KEY_TYPE_FLAT);
+        log(TrieKind::Storage, Some("0x11"), 3, None, KEY_TYPE_TRIE);
+        log(TrieKind::Contract, None, 4, Some(0x11), KEY_TYPE_FLAT);
+        log(TrieKind::Contract, None, 4, None, KEY_TYPE_TRIE);
+        log(TrieKind::Storage, Some("0x11"), 5, Some(3), KEY_TYPE_FLAT);
+        for key in 1..=3 {
+            log(TrieKind::Storage, Some("0x22"), 5, Some(key), KEY_TYPE_FLAT);
+        }
+        log(TrieKind::Class, None, 7, Some(0xc1a55), KEY_TYPE_FLAT);
+        let db = test_db.open();
+
+        let resp = block_activity(&db, 2, 6);
+        assert_eq!(resp.error, None);
+        let totals: Vec<(u64, u64)> = resp.blocks.iter().map(|block| (block.block, block.total)).collect();
+        assert_eq!(totals, vec![(2, 0), (3, 3), (4, 2), (5, 4), (6, 0)]);
+        let counts = |block: usize, trie: usize| (resp.blocks[block].tries[trie].trie_entries, resp.blocks[block].tries[trie].flat_entries);
+        assert_eq!(counts(1, 1), (1, 2));
+        assert_eq!((counts(2, 0), counts(2, 1)), ((1, 1), (0, 0)));
+        assert_eq!(counts(3, 1), (0, 4));
+
+        assert_eq!(resp.contract_count, 2);
+        let cells = |contract: &ContractActivity| contract.blocks.iter().map(|cell| (cell.block, cell.entries)).collect::<Vec<_>>();
+        assert_eq!((resp.contracts[0].contract.as_str(), resp.contracts[0].entries), ("0x11", 4));
+        assert_eq!(cells(&resp.contracts[0]), vec![(3, 3), (5, 1)]);
+        assert_eq!((resp.contracts[1].contract.as_str(), resp.contracts[1].entries), ("0x22", 3));
+        assert_eq!(cells(&resp.contracts[1]), vec![(5, 3)]);
+
+        assert!(block_activity(&db, 6, 2).error.is_some());
+        assert!(block_activity(&db, 0, MAX_ACTIVITY_BLOCKS).error.is_some());
+    }
+
+    #[test]
+    fn leaf_pages_skip_deleted_keys() {
+        let test_db = TestDb::new();
+        let spec = build_spec(TrieKind::Storage, Some("0x11".to_string())).unwrap();
+        test_db.commit(5, &spec, TrieKind::Storage, &[(Felt::ONE, Felt::from(10u64)), (Felt::TWO, Felt::from(20u64)), (Felt::THREE, Felt::from(30u64))]);
+        test_db.commit(6, &spec, TrieKind::Storage, &[(Felt::ONE, Felt::from(10u64)), (Felt::THREE, Felt::from(31u64)), (Felt::from(4u64), Felt::from(40u64))]);
+        let db = test_db.open();
+        let page = |cursor: Option<&str>| list_leaves(&db, TrieKind::Storage, Some("0x11".to_string()), cursor, None, None, 2);
+        let keys = |resp: &LeavesResponse| resp.leaves.iter().map(|leaf| leaf.key.clone()).collect::<Vec<_>>();
+
+        let first = page(None);
+        assert_eq!(keys(&first), vec!["0x1", "0x3"]);
+        assert_eq!(first.leaves[1].value.as_deref(), Some("0x1f"));
+        assert_eq!(first.next_cursor.as_deref(), Some("0x3"));
+        let second = page(first.next_cursor.as_deref());
+        assert_eq!((keys(&second), second.next_cursor), (vec!["0x4".to_string()], None));
+        // A cursor on the deleted key resumes at the next live one.
+        assert_eq!(keys(&page(Some("0x2"))), vec!["0x3", "0x4"]);
+    }
+
+    #[test]
+    fn trie_stats_cover_one_storage_contract() {
+        let test_db = TestDb::new();
+        let spec = |contract: &str| build_spec(TrieKind::Storage, Some(contract.to_string())).unwrap();
+        test_db.commit(5, &spec("0x11"), TrieKind::Storage, &[(Felt::ONE, Felt::from(10u64))]);
+        test_db.commit(5, &spec("0x22"), TrieKind::Storage, &[(Felt::ONE, Felt::from(10u64))]);
+        test_db.commit(6, &spec("0x11"), TrieKind::Storage, &[(Felt::ONE, Felt::from(10u64)), (Felt::TWO, Felt::from(20u64)), (Felt::THREE, Felt::from(30u64))]);
+        let db = test_db.open();
+        let stats = |contract: &str, block: Option<u64>| trie_stats(&db, TrieKind::Storage, Some(contract.to_string()), block, &AtomicU64::new(0));
+        let buckets = |buckets: &[HistogramBucket]| buckets.iter().map(|b| (b.value, b.count)).collect::<Vec<_>>();
+
+        // Root edge of 249 bits, then key 1 under a one-bit edge and keys 2 and 3 under a binary node.
+        let resp = stats("0x11", None);
+        assert_eq!((resp.identifier.as_deref(), resp.error), (Some("0x11"), None));
+        assert_eq!((resp.binary_nodes, resp.edge_nodes, resp.leaves, resp.missing_nodes), (2, 2, 3, 0));
+        assert_eq!(buckets(&resp.leaf_depths), vec![(3, 3)]);
+        assert_eq!(buckets(&resp.edge_lengths), vec![(1, 1), (249, 1)]);
+        assert_eq!((resp.max_proof_len, resp.avg_proof_len), (3, 3.0));
+
+        for resp in [stats("0x22", None), stats("0x11", Some(5))] {
+            assert_eq!((resp.binary_nodes, resp.edge_nodes, resp.leaves), (0, 1, 1));
+            assert_eq!(buckets(&resp.edge_lengths), vec![(LEAF_PATH_LEN as u64, 1)]);
+        }
+        assert!(stats("0xzz", None).error.is_some());
+    }
+
+    #[test]
+    fn block_diffs_decode_keys_and_contracts() {
+        let test_db = TestDb::new();
+        let contract = "0x49d36570d4e46f48e99674bd3fcc84644ddd6b96f7c741b1562b82f9e004dc7";
+        let key = Felt::from_hex_unchecked("0x3c4be0ef2b2b2a3cfe9bc1d2d5d1e5cf0a2d0fd7e1d9f2a8c4b6a3e2d1c0b9a");
+        let spec = build_spec(TrieKind::Storage, Some(contract.to_string())).unwrap();
+        test_db.commit(5, &spec, TrieKind::Storage, &[(Felt::ONE, Felt::from(10u64))]);
+        test_db.commit(6, &spec, TrieKind::Storage, &[(Felt::ONE, Felt::from(11u64)), (key, Felt::from(20u64))]);
+        let db = test_db.open();
+
+        let diff = diff_for_block(&db, TrieKind::Storage, 6);
+        assert_eq!(diff.error, None);
+        let leaves: Vec<&DiffChange> = diff.changes.iter().filter(|change| change.key_type == LogKeyType::Flat).collect();
+        assert_eq!(leaves.len(), 2);
+        assert!(leaves.iter().all(|change| change.contract.as_deref() == Some(contract) && change.key_len == Some(LEAF_PATH_LEN)));
+        let updated = leaves.iter().find(|change| change.key.as_deref() == Some("0x1")).unwrap();
+        assert_eq!(updated.kind, ChangeKind::Update);
+        assert_eq!(updated.before.as_ref().and_then(|value| value.felt.as_deref()), Some("0xa"));
+        assert_eq!(updated.after.as_ref().and_then(|value| value.felt.as_deref()), Some("0xb"));
+        let inserted = leaves.iter().find(|change| change.key.as_deref() == Some(&format!("{key:#x}"))).unwrap();
+        assert_eq!((inserted.kind, inserted.before.is_none()), (ChangeKind::Insert, true));
+
+        // Node records keep their path but have no leaf key.
+        let root = diff.changes.iter().find(|change| change.key_type == LogKeyType::Trie && change.key_len == Some(0)).unwrap();
+        assert_eq!((root.key.as_deref(), root.contract.as_deref()), (None, Some(contract)));
+        assert_eq!(root.path_hex.as_deref(), Some(bytes_to_hex(&PathBits::default().to_bytes()).as_str()));
+        assert!(diff.entries.iter().all(|entry| entry.block == 6 && entry.contract.as_deref() == Some(contract)));
+
+        // Contract-trie records carry the address as their key and no owning contract.
+        let contracts = build_spec(TrieKind::Contract, None).unwrap();
+        test_db.commit(6, &contracts, TrieKind::Contract, &[(Felt::from_hex_unchecked(contract), Felt::ONE)]);
+        let diff = diff_for_block(&test_db.open(), TrieKind::Contract, 6);
+        let leaf = diff.changes.iter().find(|change| change.key_type == LogKeyType::Flat).unwrap();
+        assert_eq!((leaf.key.as_deref(), leaf.contract.as_deref()), (Some(contract), None));
+    }
+
+    #[test]
+    fn leaf_pages_respect_bounds_and_limits() {
+        let test_db = TestDb::new();
+        let spec = build_spec(TrieKind::Storage, Some("0x11".to_string())).unwrap();
+        let leaves: Vec<(Felt, Felt)> = (1..=6u64).map(|key| (Felt::from(key), Felt::from(key * 10))).collect();
+        test_db.commit(5, &spec, TrieKind::Storage, &leaves);
+        test_db.commit(5, &build_spec(TrieKind::Storage, Some("0x12".to_string())).unwrap(), TrieKind::Storage, &[(Felt::ONE, Felt::ONE)]);
+        let db = test_db.open();
+        let list = |cursor: Option<&str>, start: Option<&str>, end: Option<&str>, limit: usize| {
+            list_leaves(&db, TrieKind::Storage, Some("0x11".to_string()), cursor, start, end, limit)
+        };
+        let keys = |resp: LeavesResponse| resp.leaves.into_iter().map(|leaf| leaf.key).collect::<Vec<_>>();
+
+        // Another contract's leaves never leak into the page.
+        assert_eq!(keys(list(None, None, None, 10)), vec!["0x1", "0x2", "0x3", "0x4", "0x5", "0x6"]);
+        assert_eq!(keys(list(None, Some("0x2"), Some("0x4"), 10)), vec!["0x2", "0x3", "0x4"]);
+        // The later of start and cursor wins.
+        assert_eq!(keys(list(Some("0x3"), Some("0x2"), None, 10)), vec!["0x4", "0x5", "0x6"]);
+        let page = list(None, Some("0x2"), Some("0x4"), 2);
+        assert_eq!(page.next_cursor.as_deref(), Some("0x3"));
+        // A page ending exactly at `end` has no next cursor.
+        assert_eq!(list(Some("0x3"), None, Some("0x4"), 1).next_cursor, None);
+        // Limits are clamped to at least one leaf.
+        assert_eq!(keys(list(None, None, None, 0)), vec!["0x1"]);
+        assert_eq!(list(None, None, None, 10).leaves[0].value.as_deref(), Some("0xa"));
+
+        assert!(list(Some("0xzz"), None, None, 10).error.is_some());
+        assert!(list(None, Some("5"), Some("0x1"), 10).leaves.is_empty());
+    }
+
+    #[test]
+    fn contract_storage_proofs_link_the_leaf_to_the_storage_root() {
+        let test_db = TestDb::new();
+        let (good, bad, class) = (Felt::from(0x11u64), Felt::from(0x12u64), Felt::from(0x99u64));
+        let storage_root = test_db.commit(1, &spec(TrieKind::Storage, Some("0x11")), TrieKind::Storage, &[(Felt::ONE, Felt::from(10u64))]);
+        test_db.commit(1, &spec(TrieKind::Storage, Some("0x12")), TrieKind::Storage, &[(Felt::ONE, Felt::from(10u64))]);
+        test_db.deploy(&good, 1, &class);
+        test_db.deploy(&bad, 1, &class);
+        let leaf = madara::contract_state_hash(&class, &storage_root, &Felt::ZERO);
+        test_db.commit(1, &spec(TrieKind::Contract, None), TrieKind::Contract, &[(good, leaf), (bad, Felt::from(5u64))]);
+        let db = test_db.open();
+
+        let resp = contract_storage_proof(&db, "0x11", "0x1", None);
+        assert_eq!(resp.error, None);
+        assert!(resp.verified);
+        assert_eq!(resp.leaf_hash, Some(format!("{leaf:#x}")));
+        assert_eq!(resp.leaf.unwrap().storage_root, format!("{storage_root:#x}"));
+        assert_eq!(resp.storage_proof.value.as_deref(), Some("0xa"));
+
+        let resp = contract_storage_proof(&db, "0x12", "0x1", None);
+        assert!(!resp.verified);
+        assert!(resp.error.unwrap().contains("preimage hashes to"));
+
+        let resp = contract_storage_proof(&db, "0x13", "0x1", None);
+        assert!(!resp.verified);
+        assert!(resp.error.is_some());
+    }
+
+    #[test]
+    fn state_roots_are_checked_against_block_headers() {
+        let test_db = TestDb::new();
+        let contracts = spec(TrieKind::Contract, None);
+        let contract_root = test_db.commit(1, &contracts, TrieKind::Contract, &[(Felt::from(0x11u64), Felt::from(7u64))]);
+        let class_root = test_db.commit(1, &spec(TrieKind::Class, None), TrieKind::Class, &[(Felt::from(0x99u64), Felt::from(8u64))]);
+        let first_root = madara::global_state_root(&contract_root, &class_root);
+        test_db.header(1, &first_root);
+        test_db.commit(2, &contracts, TrieKind::Contract, &[(Felt::from(0x11u64), Felt::from(9u64))]);
+        test_db.header(2, &first_root);
+        test_db.commit(3, &contracts, TrieKind::Contract, &[(Felt::from(0x11u64), Felt::from(10u64))]);
+        let db = test_db.open();
+
+        let resp = state_root(&db, Some(1));
+        assert_eq!(resp.error, None);
+        assert_eq!(resp.contract_root, Some(format!("{contract_root:#x}")));
+        assert_eq!(resp.class_root, Some(format!("{class_root:#x}")));
+        assert_eq!(resp.state_root, Some(format!("{first_root:#x}")));
+        assert_eq!(resp.matches, Some(true));
+
+        let resp = state_root(&db, Some(2));
+        assert_eq!(resp.header_state_root, Some(format!("{first_root:#x}")));
+        assert_eq!(resp.matches, Some(false));
+
+        // The latest block comes from the logs, and block 3 has no header.
+        let resp = state_root(&db, None);
+        assert_eq!(resp.block, Some(3));
+        assert_eq!(resp.matches, None);
+        assert_eq!(resp.error.as_deref(), Some("no block header for block 3"));
+    }
+
+    #[test]
+    fn contract_info_recomputes_leaves_from_class_nonce_and_storage() {
+        let test_db = TestDb::new();
+        let (address, other, class) = (Felt::from(0x11u64), Felt::from(0x12u64), Felt::from(0x99u64));
+        let storage_root = test_db.commit(1, &spec(TrieKind::Storage, Some("0x11")), TrieKind::Storage, &[(Felt::ONE, Felt::from(10u64))]);
+        test_db.deploy(&address, 1, &class);
+        test_db.deploy(&other, 1, &class);
+        let contracts = spec(TrieKind::Contract, None);
+        let first = madara::contract_state_hash(&class, &storage_root, &Felt::ZERO);
+        test_db.commit(1, &contracts, TrieKind::Contract, &[(address, first), (other, Felt::from(5u64))]);
+        test_db.set_nonce(&address, 2, &Felt::from(3u64));
+        let second = madara::contract_state_hash(&class, &storage_root, &Felt::from(3u64));
+        test_db.commit(2, &contracts, TrieKind::Contract, &[(address, second), (other, Felt::from(5u64))]);
+        let db = test_db.open();
+
+        let resp = contract_info(&db, "0x0011", None);
+        assert_eq!(resp.address, "0x11");
+        assert_eq!(resp.error, None);
+        assert!(resp.matches);
+        assert_eq!(resp.leaf, Some(format!("{second:#x}")));
+        let state = resp.state.unwrap();
+        assert_eq!((state.nonce.as_str(), state.class_hash.as_str()), ("0x3", "0x99"));
+
+        // Before the nonce was bumped.
+        let resp = contract_info(&db, "0x11", Some(1));
+        assert!(resp.matches);
+        assert_eq!(resp.state.unwrap().nonce, "0x0");
+
+        let resp = contract_info(&db, "0x12", None);
+        assert_eq!(resp.error, None);
+        assert!(!resp.matches);
+        assert_eq!(resp.leaf.as_deref(), Some("0x5"));
+
+        let resp = contract_info(&db, "0x13", None);
+        assert!(!resp.matches);
+        assert_eq!(resp.error.as_deref(), Some("contract is not in the contract trie"));
+    }
+
+    #[test]
+    fn storage_contracts_count_leaves_and_nodes_per_contract() {
+        let test_db = TestDb::new();
+        let leaves = [(Felt::ONE, Felt::ONE), (Felt::TWO, Felt::TWO)];
+        test_db.commit(1, &spec(TrieKind::Storage, Some("0x11")), TrieKind::Storage, &leaves);
+        test_db.commit(1, &spec(TrieKind::Storage, Some("0x12")), TrieKind::Storage, &leaves[..1]);
+        test_db.commit(1, &spec(TrieKind::Contract, None), TrieKind::Contract, &leaves);
+        let db = test_db.open();
+        let progress = AtomicU64::new(0);
+
+        let resp = storage_contracts(&db, &progress);
+        assert_eq!(resp.error, None);
+        let counts: Vec<_> = resp.contracts.iter().map(|c| (c.address.as_str(), c.leaves, c.trie_nodes)).collect();
+        // Two keys split below a 249-bit edge into a binary node and two 1-bit edges.
+        assert_eq!(counts, vec![("0x11", 2, 4), ("0x12", 1, 1)]);
+        assert!(resp.contracts.iter().all(|c| c.bytes > 0));
+        // The contract trie's own entries are not scanned.
+        assert_eq!(progress.into_inner(), 8);
+    }
+
+    #[test]
+    fn leaf_history_follows_one_key_across_blocks() {
+        let test_db = TestDb::new();
+        let storage = spec(TrieKind::Storage, Some("0x11"));
+        test_db.commit(3, &storage, TrieKind::Storage, &[(Felt::ONE, Felt::from(10u64))]);
+        test_db.commit(4, &storage, TrieKind::Storage, &[(Felt::ONE, Felt::from(10u64)), (Felt::TWO, Felt::TWO)]);
+        test_db.commit(5, &storage, TrieKind::Storage, &[(Felt::ONE, Felt::from(11u64)), (Felt::TWO, Felt::TWO)]);
+        test_db.commit(6, &storage, TrieKind::Storage, &[(Felt::TWO, Felt::TWO)]);
+        let db = test_db.open();
+
+        let resp = leaf_history(&db, TrieKind::Storage, Some("0x11".to_string()), "0x01");
+        assert_eq!(resp.error, None);
+        assert_eq!(resp.key, "0x1");
+        assert_eq!(resp.earliest_logged, Some(3));
+        let entry = |block, kind, before: Option<&str>, after: Option<&str>| HistoryEntry {
+            block,
+            kind,
+            before: before.map(str::to_string),
+            after: after.map(str::to_string),
+        };
+        // Block 4 only touched the other key.
+        assert_eq!(
+            resp.entries,
+            vec![
+                entry(3, ChangeKind::Insert, None, Some("0xa")),
+                entry(5, ChangeKind::Update, Some("0xa"), Some("0xb")),
+                entry(6, ChangeKind::Delete, Some("0xb"), None),
+            ]
+        );
+
+        assert!(leaf_history(&db, TrieKind::Storage, Some("0x11".to_string()), "0x3").entries.is_empty());
+        assert!(leaf_history(&db, TrieKind::Storage, Some("0xzz".to_string()), "0x1").error.is_some());
+    }
+
+    #[test]
+    fn class_leaves_are_checked_against_compiled_class_hashes() {
+        let test_db = TestDb::new();
+        let (sierra, legacy, unknown) = (Felt::from(0x77u64), Felt::from(0x78u64), Felt::from(0x79u64));
+        let compiled = Felt::from(0xcc77u64);
+        test_db.put(cf_map::CLASS_INFO, &sierra.to_bytes_be(), &sierra_class_info(&compiled, Some(2)));
+        test_db.put(cf_map::CLASS_INFO, &legacy.to_bytes_be(), &1u32.to_le_bytes());
+        let classes = spec(TrieKind::Class, None);
+        let leaf = madara::class_leaf_hash(&compiled);
+        test_db.commit(2, &classes, TrieKind::Class, &[(sierra, leaf), (legacy, Felt::from(5u64))]);
+        test_db.commit(3, &classes, TrieKind::Class, &[(sierra, leaf), (legacy, Felt::from(5u64)), (unknown, Felt::from(6u64))]);
+        let db = test_db.open();
+
+        let resp = class_info(&db, "0x077", None);
+        assert_eq!(resp.error, None);
+        assert_eq!(resp.class_hash, "0x77");
+        assert!(resp.matches);
+        assert_eq!(resp.compiled_class_hash.as_deref(), Some("0xcc77"));
+        assert_eq!(resp.declared_block, Some(2));
+
+        assert!(class_info(&db, "0x78", None).error.unwrap().contains("legacy class"));
+        // Not declared yet as of block 2.
+        assert_eq!(class_info(&db, "0x79", Some(2)).error.as_deref(), Some("class is not in the class trie"));
+
+        let page = list_classes(&db, None, 2);
+        assert_eq!(page.error, None);
+        let hashes: Vec<_> = page.classes.iter().map(|class| class.class_hash.as_str()).collect();
+        assert_eq!(hashes, vec!["0x77", "0x78"]);
+        assert!(page.classes[0].matches);
+        assert_eq!(page.next_cursor.as_deref(), Some("0x78"));
+
+        let page = list_classes(&db, Some("0x78"), 2);
+        assert_eq!(page.classes.len(), 1);
+        assert_eq!(page.classes[0].declared_block, Some(3));
+        assert_eq!(page.classes[0].error.as_deref(), Some("no class info for this class"));
+        assert_eq!(page.next_cursor, None);
+    }
+
+    #[test]
+    fn storage_labels_name_variables_and_logged_mapping_keys() {
+        use util::storage_key::storage_var_address;
+
+        let test_db = TestDb::new();
+        let (holder, owner) = (Felt::from(0x22u64), Felt::from(0x33u64));
+        let leaves = [
+            (storage_var_address("total", &[]), Felt::from(100u64)),
+            (storage_var_address("owner", &[]), owner),
+            (storage_var_address("balances", &[holder]), Felt::from(7u64)),
+            (storage_var_address("balances", &[owner]), Felt::from(9u64)),
+            (Felt::from(5u64), Felt::ONE),
+        ];
+        test_db.commit(1, &spec(TrieKind::Storage, Some("0x11")), TrieKind::Storage, &leaves);
+        test_db.commit(1, &spec(TrieKind::Contract, None), TrieKind::Contract, &[(holder, Felt::ONE)]);
+        let db = test_db.open();
+        let layout = serde_json::json!({ "storage": [
+            { "name": "total", "type": "u256" },
+            { "name": "owner", "type": "ContractAddress" },
+            { "name": "balances", "type": "Map::<ContractAddress, felt252>" },
+        ] });
+
+        let resp = storage_labels(&db, "0x11", &layout.to_string());
+        assert_eq!(resp.error, None);
+        assert_eq!(resp.variables.len(), 3);
+        assert_eq!(resp.leaves, 5);
+        // 0x22 from the contract log, 0x11 from the storage log, then the contract's five values.
+        assert_eq!(resp.mapping_keys, 7);
+        let labels: BTreeMap<&str, Option<&str>> = resp.labels.iter().map(|label| (label.label.as_str(), label.decoded.as_deref())).collect();
+        // The balance keyed by the owner is found through the value stored in `owner`.
+        assert_eq!(
+            labels,
+            BTreeMap::from([
+                ("balances[0x22]", Some("0x7")),
+                ("balances[0x33]", Some("0x9")),
+                ("owner", Some("0x33")),
+                ("total.low", Some("100")),
+            ])
+        );
+
+        assert!(storage_labels(&db, "0x11", "[").error.is_some());
+        assert!(storage_labels(&db, "0xzz", &layout.to_string()).error.is_some());
+    }
+}
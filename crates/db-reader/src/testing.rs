@@ -0,0 +1,217 @@
+//! Scratch RocksDB instances laid out like a bonsai DB, for unit tests.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use parity_scale_codec::Encode;
+use rocksdb::{Options, DB};
+use starknet_types_core::felt::Felt;
+
+use crate::bonsai::diff_reader::{CHANGE_NEW, CHANGE_OLD, KEY_TYPE_FLAT, KEY_TYPE_TRIE};
+use crate::bonsai::node::{BinaryNode, EdgeNode, Node, NodeHandle, Path};
+use crate::bonsai::path::{felt_to_path, PathBits, LEAF_PATH_LEN};
+use crate::bonsai::proof::ProofNode;
+use crate::bonsai::trie_reader::TrieSpec;
+use crate::db::{cf_map, RocksDb};
+use bonsai_types::TrieKind;
+
+/// The spec of a trie, as the reader functions build it; panics on a bad identifier.
+pub fn spec(trie: TrieKind, identifier: Option<&str>) -> TrieSpec {
+    crate::build_spec(trie, identifier.map(str::to_string)).unwrap()
+}
+
+/// A felt as Madara's bincode writes it: a `u64` length, then the big-endian bytes
+/// without leading zeros.
+pub fn bincode_felt(felt: &Felt) -> Vec<u8> {
+    let bytes = felt.to_bytes_be();
+    let start = bytes.iter().position(|b| *b != 0).unwrap_or(bytes.len());
+    let mut value = ((32 - start) as u64).to_le_bytes().to_vec();
+    value.extend_from_slice(&bytes[start..]);
+    value
+}
+
+fn bincode_str(value: &str) -> Vec<u8> {
+    [(value.len() as u64).to_le_bytes().as_slice(), value.as_bytes()].concat()
+}
+
+/// A Sierra `ClassInfoWithBlockNumber` as Madara writes it, declared at `block`.
+pub fn sierra_class_info(compiled_class_hash: &Felt, block: Option<u64>) -> Vec<u8> {
+    let mut value = 0u32.to_le_bytes().to_vec();
+    let program = [Felt::from(0x1u64), Felt::from_hex_unchecked("0x2b21eb1"), Felt::from(0x7u64)];
+    value.extend_from_slice(&(program.len() as u64).to_le_bytes());
+    program.iter().for_each(|felt| value.extend(bincode_felt(felt)));
+    value.extend(bincode_str("0.1.0"));
+    // One constructor, two external entry points, no L1 handlers.
+    for count in [1u64, 2, 0] {
+        value.extend_from_slice(&count.to_le_bytes());
+        for index in 0..count {
+            value.extend(bincode_felt(&Felt::from_hex_unchecked("0x28ffe4ff0f226a9107253e17a904099aa4f63a02a5621de0576e5aa71bc5194")));
+            value.extend_from_slice(&index.to_le_bytes());
+        }
+    }
+    value.extend(bincode_str(r#"[{"type":"function","name":"constructor","inputs":[],"outputs":[]}]"#));
+    value.extend(bincode_felt(compiled_class_hash));
+    match block {
+        None => value.extend_from_slice(&0u32.to_le_bytes()),
+        Some(block) => {
+            value.extend_from_slice(&1u32.to_le_bytes());
+            value.extend_from_slice(&block.to_le_bytes());
+        }
+    }
+    value
+}
+
+static NEXT_DB: AtomicU64 = AtomicU64::new(0);
+
+/// A writable DB in a temporary directory, removed on drop.
+pub struct TestDb {
+    path: PathBuf,
+    db: DB,
+}
+
+impl TestDb {
+    pub fn new() -> Self {
+        let id = NEXT_DB.fetch_add(1, Ordering::Relaxed);
+        let path = std::env::temp_dir().join(format!("bonsai-db-reader-test-{}-{id}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&path);
+        let mut opts = Options::default();
+        opts.create_if_missing(true);
+        opts.create_missing_column_families(true);
+        let mut columns = cf_map::BONSAI_COLUMNS.to_vec();
+        columns.extend([cf_map::CONTRACT_TO_CLASS_HASHES, cf_map::CONTRACT_TO_NONCES, cf_map::BLOCK_N_TO_BLOCK_INFO, cf_map::CLASS_INFO]);
+        let db = DB::open_cf(&opts, &path, &columns).unwrap();
+        Self { path, db }
+    }
+
+    /// A read-only view of everything written so far.
+    pub fn open(&self) -> RocksDb {
+        self.db.flush_wal(true).unwrap();
+        RocksDb::open_read_only(&self.path).unwrap()
+    }
+
+    pub fn put(&self, cf: &str, key: &[u8], value: &[u8]) {
+        self.db.put_cf(self.db.cf_handle(cf).unwrap(), key, value).unwrap();
+    }
+
+    /// Records in Madara's history columns that `address` got `class_hash` at `block`.
+    pub fn deploy(&self, address: &Felt, block: u32, class_hash: &Felt) {
+        self.put_history(cf_map::CONTRACT_TO_CLASS_HASHES, address, block, class_hash);
+    }
+
+    /// Records in Madara's history columns that `address` has `nonce` from `block` on.
+    pub fn set_nonce(&self, address: &Felt, block: u32, nonce: &Felt) {
+        self.put_history(cf_map::CONTRACT_TO_NONCES, address, block, nonce);
+    }
+
+    fn put_history(&self, cf: &str, address: &Felt, block: u32, value: &Felt) {
+        let key = [address.to_bytes_be().as_slice(), &block.to_be_bytes()].concat();
+        self.put(cf, &key, &bincode_felt(value));
+    }
+
+    /// Stores a block header for `block` committing to `state_root`, with a zero parent hash.
+    pub fn header(&self, block: u64, state_root: &Felt) {
+        let value = [bincode_felt(&Felt::ZERO), block.to_le_bytes().to_vec(), bincode_felt(state_root)].concat();
+        self.put(cf_map::BLOCK_N_TO_BLOCK_INFO, &block.to_le_bytes(), &value);
+    }
+
+    /// Replaces the trie of `spec` with one holding `leaves` and logs every changed
+    /// node and leaf under `block`, as bonsai-trie does on commit. Returns the root hash.
+    pub fn commit(&self, block: u64, spec: &TrieSpec, kind: TrieKind, leaves: &[(Felt, Felt)]) -> Felt {
+        let before = [self.take_prefixed(&spec.trie_cf, &spec.identifier), self.take_prefixed(&spec.flat_cf, &spec.identifier)];
+
+        let mut paths: Vec<(PathBits, Felt)> = leaves.iter().map(|(key, value)| (felt_to_path(key), *value)).collect();
+        paths.sort_by(|a, b| a.0 .0.cmp(&b.0 .0));
+        for (path, value) in &paths {
+            let mut key = spec.identifier.clone();
+            key.extend_from_slice(&path.to_bytes());
+            self.put(&spec.flat_cf, &key, &value.encode());
+        }
+        let root = if paths.is_empty() { Felt::ZERO } else { self.build(spec, PathBits::default(), &paths, kind) };
+
+        let after = [self.read_prefixed(&spec.trie_cf, &spec.identifier), self.read_prefixed(&spec.flat_cf, &spec.identifier)];
+        for (key_type, (before, after)) in [KEY_TYPE_TRIE, KEY_TYPE_FLAT].into_iter().zip(before.iter().zip(&after)) {
+            let keys: BTreeSet<&Vec<u8>> = before.keys().chain(after.keys()).collect();
+            for key in keys {
+                let (old, new) = (before.get(key), after.get(key));
+                if old == new {
+                    continue;
+                }
+                if let Some(old) = old {
+                    self.log(&spec.log_cf, block, key, key_type, CHANGE_OLD, old);
+                }
+                if let Some(new) = new {
+                    self.log(&spec.log_cf, block, key, key_type, CHANGE_NEW, new);
+                }
+            }
+        }
+        root
+    }
+
+    pub fn log(&self, log_cf: &str, block: u64, trie_key: &[u8], key_type: u8, change_type: u8, value: &[u8]) {
+        let mut key = block.to_be_bytes().to_vec();
+        key.push(0);
+        key.extend_from_slice(trie_key);
+        key.push(key_type);
+        key.push(change_type);
+        self.put(log_cf, &key, value);
+    }
+
+    fn read_prefixed(&self, cf: &str, prefix: &[u8]) -> BTreeMap<Vec<u8>, Vec<u8>> {
+        self.db
+            .prefix_iterator_cf(self.db.cf_handle(cf).unwrap(), prefix)
+            .map(Result::unwrap)
+            .take_while(|(key, _)| key.starts_with(prefix))
+            .map(|(key, value)| (key.to_vec(), value.to_vec()))
+            .collect()
+    }
+
+    fn take_prefixed(&self, cf: &str, prefix: &[u8]) -> BTreeMap<Vec<u8>, Vec<u8>> {
+        let entries = self.read_prefixed(cf, prefix);
+        for key in entries.keys() {
+            self.db.delete_cf(self.db.cf_handle(cf).unwrap(), key).unwrap();
+        }
+        entries
+    }
+
+    fn build(&self, spec: &TrieSpec, prefix: PathBits, leaves: &[(PathBits, Felt)], kind: TrieKind) -> Felt {
+        if prefix.len() == LEAF_PATH_LEN {
+            return leaves[0].1;
+        }
+        let start = prefix.len();
+        let mut common = 0;
+        while start + common < LEAF_PATH_LEN && leaves.iter().all(|(path, _)| path.0[start + common] == leaves[0].0 .0[start + common]) {
+            common += 1;
+        }
+        let mut key = spec.identifier.clone();
+        key.extend_from_slice(&prefix.to_bytes());
+        let (hash, node) = if common > 0 {
+            let bits = leaves[0].0 .0[start..start + common].to_bitvec();
+            let mut child_path = prefix.clone();
+            child_path.extend_from_bitslice(&bits);
+            let child = self.build(spec, child_path, leaves, kind);
+            let hash = ProofNode::Edge { child, path: PathBits(bits.clone()) }.hash(kind);
+            (hash, Node::Edge(EdgeNode { hash: Some(hash), height: start as u64, path: Path(bits), child: NodeHandle::Hash(child) }))
+        } else {
+            let split = leaves.iter().position(|(path, _)| path.0[start]).unwrap();
+            let left = self.build(spec, prefix.with_bit(false), &leaves[..split], kind);
+            let right = self.build(spec, prefix.with_bit(true), &leaves[split..], kind);
+            let hash = ProofNode::Binary { left, right }.hash(kind);
+            (hash, Node::Binary(BinaryNode { hash: Some(hash), height: start as u64, left: NodeHandle::Hash(left), right: NodeHandle::Hash(right) }))
+        };
+        self.put(&spec.trie_cf, &key, &node.encode());
+        hash
+    }
+}
+
+impl Default for TestDb {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for TestDb {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.path);
+    }
+}
@@ -1 +1,3 @@
 pub mod hex;
+pub mod storage_key;
+pub mod storage_layout;
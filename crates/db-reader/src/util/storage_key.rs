@@ -0,0 +1,112 @@
+use sha3::{Digest, Keccak256};
+use starknet_types_core::felt::Felt;
+use starknet_types_core::hash::{Pedersen, StarkHash};
+
+use crate::util::hex::parse_felt_hex;
+use bonsai_types::{is_storage_expression, TrieKind};
+
+/// Storage addresses are reduced below `2^251 - 256`.
+const ADDR_BOUND: Felt = Felt::from_hex_unchecked("0x7ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff00");
+
+/// Parses a storage key given either as a felt or as a storage variable expression:
+/// `balance`, `ERC20_balances[0xabc]`, `allowances[0xa][0xb]`. See
+/// [`is_storage_expression`] for which input is an expression; mapping keys inside
+/// brackets are felts.
+pub fn parse_storage_key(input: &str) -> Result<Felt, String> {
+    let input = input.trim();
+    if !is_storage_expression(input) {
+        return parse_felt_hex(input);
+    }
+    let (name, mut rest) = input.split_at(input.find('[').unwrap_or(input.len()));
+    let name = name.trim();
+    if !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        return Err(format!("invalid storage variable name: {name}"));
+    }
+    let mut keys = Vec::new();
+    while !rest.is_empty() {
+        let Some(inner) = rest.strip_prefix('[') else {
+            return Err(format!("expected '[' at {rest}"));
+        };
+        let Some(end) = inner.find(']') else {
+            return Err(format!("missing ']' after {inner}"));
+        };
+        keys.push(parse_felt_hex(&inner[..end]).map_err(|err| format!("mapping key {}: {err}", &inner[..end]))?);
+        rest = inner[end + 1..].trim_start();
+    }
+    Ok(storage_var_address(name, &keys))
+}
+
+/// Parses a key of `trie`. Only storage tries take storage variable expressions; contract
+/// addresses and class hashes are always felts.
+pub fn parse_trie_key(trie: TrieKind, input: &str) -> Result<Felt, String> {
+    match trie {
+        TrieKind::Storage => parse_storage_key(input),
+        TrieKind::Contract | TrieKind::Class => parse_felt_hex(input),
+    }
+}
+
+/// Address of storage variable `name`, or of its entry at `keys` for a mapping:
+/// `sn_keccak(name)` Pedersen-chained with each key, reduced mod `2^251 - 256`.
+pub fn storage_var_address(name: &str, keys: &[Felt]) -> Felt {
+    storage_address(sn_keccak(name.as_bytes()), keys)
+}
+
+/// Entry at `keys` of the mapping whose base address is `base` (`sn_keccak` of its name).
+pub fn storage_address(base: Felt, keys: &[Felt]) -> Felt {
+    let hash = keys.iter().fold(base, |hash, key| Pedersen::hash(&hash, key));
+    if hash >= ADDR_BOUND {
+        hash - ADDR_BOUND
+    } else {
+        hash
+    }
+}
+
+/// Keccak-256 truncated to its low 250 bits, as Starknet uses for selectors and variable names.
+pub fn sn_keccak(data: &[u8]) -> Felt {
+    let mut hash: [u8; 32] = Keccak256::digest(data).into();
+    hash[0] &= 0x03;
+    Felt::from_bytes_be(&hash)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn felt(hex: &str) -> Felt {
+        Felt::from_hex_unchecked(hex)
+    }
+
+    #[test]
+    fn sn_keccak_matches_starknet_selectors() {
+        assert_eq!(sn_keccak(b"transfer"), felt("0x83afd3f4caedc6eebf44246fe54e38c95e3179a5ec9ea81740eca5b482d12e"));
+        assert_eq!(sn_keccak(b"balance"), felt("0x206f38f7e4f15e87567361213c28f235cccdaa1d7fd34c9db1dfe9489c6a091"));
+    }
+
+    #[test]
+    fn storage_address_chains_keys_with_pedersen() {
+        let base = felt("0x3d937c035c878245caf64531a5756109c53068da139362728feb561405371cb");
+        let key = felt("0x208a0a10250e382e1e4bbe2880906c2791bf6275695e02fbbc6aeff9cd8b31a");
+        assert_eq!(storage_address(base, &[]), base);
+        assert_eq!(storage_address(base, &[key]), felt("0x30e480bed5fe53fa909cc0f8c4d99b8f9f2c016be4c41e13a4848797979c662"));
+        assert_eq!(storage_address(ADDR_BOUND, &[]), Felt::ZERO);
+        assert_eq!(parse_storage_key("balance"), Ok(sn_keccak(b"balance")));
+        assert_eq!(parse_storage_key("ERC20_balances[0xa][0xb]"), Ok(storage_var_address("ERC20_balances", &[Felt::from(0xau64), Felt::from(0xbu64)])));
+    }
+
+    #[test]
+    fn unprefixed_hex_is_a_felt() {
+        assert_eq!(parse_storage_key("deadbeef"), Ok(felt("0xdeadbeef")));
+        assert_eq!(parse_storage_key("abc123"), Ok(felt("0xabc123")));
+        assert_eq!(parse_storage_key("  0x10 "), Ok(Felt::from(0x10u64)));
+        assert_eq!(parse_storage_key("fee[0x1]"), Ok(storage_var_address("fee", &[Felt::ONE])));
+        assert!(parse_storage_key("0x1[0x2]").is_err());
+    }
+
+    #[test]
+    fn only_storage_tries_take_expressions() {
+        assert_eq!(parse_trie_key(TrieKind::Storage, "balance"), Ok(sn_keccak(b"balance")));
+        assert!(parse_trie_key(TrieKind::Contract, "balance").is_err());
+        assert!(parse_trie_key(TrieKind::Class, "ERC20_balances[0x1]").is_err());
+        assert_eq!(parse_trie_key(TrieKind::Class, "abc"), Ok(felt("0xabc")));
+    }
+}
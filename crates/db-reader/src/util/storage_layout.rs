@@ -0,0 +1,320 @@
+use std::collections::HashMap;
+
+use serde_json::Value;
+use starknet_types_core::felt::Felt;
+
+use crate::util::storage_key::sn_keccak;
+
+/// Nesting limit when flattening ABI structs into storage slots.
+const MAX_STRUCT_DEPTH: usize = 8;
+
+/// How one storage slot's felt is shown.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SlotKind {
+    /// `felt252`, shown as a short string when it is printable ASCII.
+    Felt,
+    Bool,
+    /// `ContractAddress`, `ClassHash` and similar, shown as hex.
+    Address,
+    /// Unsigned integers up to `u128`, shown in decimal.
+    Uint,
+    U256Low,
+    U256High,
+}
+
+/// One slot of a variable's value: its offset from the entry's address, a label
+/// suffix such as `.low` or `.owner`, and how to decode it.
+#[derive(Debug, Clone)]
+pub struct Slot {
+    pub offset: u64,
+    pub suffix: String,
+    pub kind: SlotKind,
+}
+
+#[derive(Debug, Clone)]
+pub struct StorageVariable {
+    pub name: String,
+    /// `sn_keccak(name)`: the variable's address, or the base its mapping entries hash from.
+    pub base: Felt,
+    /// Number of mapping keys; 0 for a plain variable.
+    pub keys: usize,
+    pub value_type: String,
+    pub slots: Vec<Slot>,
+}
+
+/// Storage variables guessed from a contract class or ABI.
+///
+/// A Sierra ABI does not declare storage, so variables come from two places: an explicit
+/// `storage` list of `{"name", "type"}` next to the ABI (types like `Map::<K, V>` give
+/// mapping keys), and the ABI's view functions, read as getters named after a variable
+/// with one mapping key per input. Getters are also tried with each component's prefix
+/// (`ERC20Impl` gives `ERC20_`).
+#[derive(Debug, Clone, Default)]
+pub struct StorageLayout {
+    pub variables: Vec<StorageVariable>,
+}
+
+impl StorageLayout {
+    /// Accepts a contract class (whose `abi` may be a JSON string), a bare ABI array, or
+    /// an object with an `abi` and/or `storage` list.
+    pub fn from_json(text: &str) -> Result<Self, String> {
+        let value: Value = serde_json::from_str(text).map_err(|err| format!("invalid JSON: {err}"))?;
+        let (abi, storage) = match &value {
+            Value::Array(_) => (value.clone(), None),
+            Value::Object(object) => {
+                let abi = match object.get("abi") {
+                    Some(Value::String(abi)) => serde_json::from_str(abi).map_err(|err| format!("invalid abi string: {err}"))?,
+                    Some(abi) => abi.clone(),
+                    None => Value::Array(Vec::new()),
+                };
+                (abi, object.get("storage").cloned())
+            }
+            _ => return Err("expected a contract class, an ABI array or a layout object".to_string()),
+        };
+        let items = abi_items(&abi);
+        let structs: HashMap<&str, Vec<(&str, &str)>> = items
+            .iter()
+            .filter(|item| item["type"] == "struct")
+            .filter_map(|item| {
+                let members = item["members"].as_array()?;
+                let members = members.iter().filter_map(|member| Some((member["name"].as_str()?, member["type"].as_str()?))).collect();
+                Some((item["name"].as_str()?, members))
+            })
+            .collect();
+
+        let mut layout = StorageLayout::default();
+        for entry in storage.as_ref().and_then(Value::as_array).into_iter().flatten() {
+            let (Some(name), Some(ty)) = (entry["name"].as_str(), entry["type"].as_str()) else {
+                return Err(format!("storage entry without name and type: {entry}"));
+            };
+            let (keys, value_type) = split_map_type(ty);
+            layout.push(name, keys, value_type, &structs);
+        }
+
+        let prefixes: Vec<String> = items
+            .iter()
+            .filter(|item| item["type"] == "impl")
+            .filter_map(|item| item["name"].as_str()?.strip_suffix("Impl"))
+            .map(|name| format!("{}_", name.strip_suffix("Mixin").unwrap_or(name)))
+            .collect();
+        for item in items.iter().filter(|item| item["type"] == "function" && is_view(item)) {
+            let (Some(name), Some(output)) = (item["name"].as_str(), item["outputs"].get(0).and_then(|output| output["type"].as_str())) else {
+                continue;
+            };
+            let keys = item["inputs"].as_array().map_or(0, Vec::len);
+            layout.push(name, keys, output, &structs);
+            for prefix in &prefixes {
+                layout.push(&format!("{prefix}{name}"), keys, output, &structs);
+            }
+        }
+        Ok(layout)
+    }
+
+    fn push(&mut self, name: &str, keys: usize, value_type: &str, structs: &HashMap<&str, Vec<(&str, &str)>>) {
+        if self.variables.iter().any(|variable| variable.name == name) {
+            return;
+        }
+        let mut slots = Vec::new();
+        push_slots(value_type, String::new(), structs, 0, &mut slots);
+        self.variables.push(StorageVariable {
+            name: name.to_string(),
+            base: sn_keccak(name.as_bytes()),
+            keys,
+            value_type: value_type.to_string(),
+            slots,
+        });
+    }
+}
+
+/// Top-level ABI items plus the functions nested in `interface` items.
+fn abi_items(abi: &Value) -> Vec<&Value> {
+    let mut items = Vec::new();
+    for item in abi.as_array().into_iter().flatten() {
+        items.push(item);
+        if item["type"] == "interface" {
+            items.extend(item["items"].as_array().into_iter().flatten());
+        }
+    }
+    items
+}
+
+/// Cairo 1 ABIs say `state_mutability`, Cairo 0 ABIs `stateMutability`.
+fn is_view(item: &Value) -> bool {
+    item["state_mutability"] == "view" || item["stateMutability"] == "view"
+}
+
+/// `Map::<K, Map::<K2, V>>` (or `LegacyMap`) into its key count and value type. A tuple
+/// key `(A, B)` hashes element by element, so it counts as one key per element.
+fn split_map_type(ty: &str) -> (usize, &str) {
+    let Some(start) = ty.find("Map::<") else {
+        return (0, ty.trim());
+    };
+    let inner = &ty[start + "Map::<".len()..];
+    let inner = inner.trim_end().strip_suffix('>').unwrap_or(inner);
+    let [key, value] = top_level_split(inner)[..] else {
+        return (1, inner.trim());
+    };
+    let key_count = match key.trim().strip_prefix('(').and_then(|tuple| tuple.strip_suffix(')')) {
+        Some(tuple) => top_level_split(tuple).len(),
+        None => 1,
+    };
+    let (keys, value) = split_map_type(value);
+    (key_count + keys, value)
+}
+
+/// Splits `s` at commas outside `<>` and `()`.
+fn top_level_split(s: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let (mut depth, mut from) = (0usize, 0);
+    for (index, c) in s.char_indices() {
+        match c {
+            '<' | '(' => depth += 1,
+            '>' | ')' => depth = depth.saturating_sub(1),
+            ',' if depth == 0 => {
+                parts.push(&s[from..index]);
+                from = index + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(&s[from..]);
+    parts
+}
+
+fn push_slots(ty: &str, suffix: String, structs: &HashMap<&str, Vec<(&str, &str)>>, depth: usize, slots: &mut Vec<Slot>) {
+    let kinds = match ty.rsplit("::").next().unwrap_or(ty) {
+        "u256" | "Uint256" => vec![(".low", SlotKind::U256Low), (".high", SlotKind::U256High)],
+        "bool" => vec![("", SlotKind::Bool)],
+        "ContractAddress" | "ClassHash" | "EthAddress" => vec![("", SlotKind::Address)],
+        "u8" | "u16" | "u32" | "u64" | "u128" | "usize" => vec![("", SlotKind::Uint)],
+        _ => match structs.get(ty).filter(|_| depth < MAX_STRUCT_DEPTH) {
+            Some(members) => {
+                for (name, member_ty) in members {
+                    push_slots(member_ty, format!("{suffix}.{name}"), structs, depth + 1, slots);
+                }
+                return;
+            }
+            None => vec![("", SlotKind::Felt)],
+        },
+    };
+    for (part, kind) in kinds {
+        slots.push(Slot { offset: slots.len() as u64, suffix: format!("{suffix}{part}"), kind });
+    }
+}
+
+/// A slot's value as its type reads: decimal integers, `true`/`false`, hex addresses,
+/// and felts as short strings when every byte is printable ASCII.
+pub fn decode_slot(kind: SlotKind, value: &Felt) -> String {
+    match kind {
+        SlotKind::Uint | SlotKind::U256Low | SlotKind::U256High => value.to_string(),
+        SlotKind::Bool if *value == Felt::ZERO => "false".to_string(),
+        SlotKind::Bool if *value == Felt::ONE => "true".to_string(),
+        SlotKind::Bool => format!("{value:#x} (not a bool)"),
+        SlotKind::Address => format!("{value:#x}"),
+        SlotKind::Felt => short_string(value).map(|text| format!("'{text}'")).unwrap_or_else(|| format!("{value:#x}")),
+    }
+}
+
+fn short_string(value: &Felt) -> Option<String> {
+    let bytes = value.to_bytes_be();
+    let text = &bytes[bytes.iter().position(|b| *b != 0)?..];
+    text.iter().all(|b| b.is_ascii_graphic() || *b == b' ').then(|| String::from_utf8_lossy(text).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_map_type_counts_keys() {
+        assert_eq!(split_map_type("core::felt252"), (0, "core::felt252"));
+        assert_eq!(split_map_type("Map::<ContractAddress, u256>"), (1, "u256"));
+        assert_eq!(split_map_type("LegacyMap::<felt252, felt252>"), (1, "felt252"));
+        assert_eq!(
+            split_map_type("core::starknet::storage::Map::<ContractAddress, Map::<ContractAddress, core::integer::u256>>"),
+            (2, "core::integer::u256")
+        );
+        assert_eq!(split_map_type("Map::<(ContractAddress, felt252), Map::<u8, (u64, u64)>>"), (3, "(u64, u64)"));
+        assert_eq!(split_map_type("Map::<felt252>"), (1, "felt252"));
+    }
+
+    fn slots(variable: &StorageVariable) -> Vec<(u64, &str, SlotKind)> {
+        variable.slots.iter().map(|slot| (slot.offset, slot.suffix.as_str(), slot.kind)).collect()
+    }
+
+    #[test]
+    fn from_json_reads_class_abis() {
+        let abi = serde_json::json!([
+            { "type": "impl", "name": "ERC20Impl", "interface_name": "IERC20" },
+            { "type": "struct", "name": "Position", "members": [{ "name": "owner", "type": "ContractAddress" }, { "name": "open", "type": "bool" }] },
+            { "type": "interface", "name": "IERC20", "items": [
+                { "type": "function", "name": "balance_of", "inputs": [{ "name": "account", "type": "ContractAddress" }], "outputs": [{ "type": "core::integer::u256" }], "state_mutability": "view" },
+                { "type": "function", "name": "transfer", "inputs": [], "outputs": [{ "type": "bool" }], "state_mutability": "external" }
+            ] },
+            { "type": "function", "name": "name", "inputs": [], "outputs": [{ "type": "felt252" }], "stateMutability": "view" }
+        ]);
+        let class = serde_json::json!({
+            "abi": abi.to_string(),
+            "storage": [{ "name": "positions", "type": "Map::<ContractAddress, Position>" }, { "name": "name", "type": "u8" }]
+        });
+        let layout = StorageLayout::from_json(&class.to_string()).unwrap();
+
+        let names: Vec<&str> = layout.variables.iter().map(|variable| variable.name.as_str()).collect();
+        assert_eq!(names, vec!["positions", "name", "balance_of", "ERC20_balance_of", "ERC20_name"]);
+        let positions = &layout.variables[0];
+        assert_eq!((positions.keys, positions.base), (1, sn_keccak(b"positions")));
+        assert_eq!(slots(positions), vec![(0, ".owner", SlotKind::Address), (1, ".open", SlotKind::Bool)]);
+        // The explicit storage entry wins over the getter of the same name.
+        assert_eq!(slots(&layout.variables[1]), vec![(0, "", SlotKind::Uint)]);
+        let balance = &layout.variables[3];
+        assert_eq!((balance.keys, balance.value_type.as_str()), (1, "core::integer::u256"));
+        assert_eq!(slots(balance), vec![(0, ".low", SlotKind::U256Low), (1, ".high", SlotKind::U256High)]);
+
+        // A bare ABI array reads the same getters.
+        assert_eq!(StorageLayout::from_json(&abi.to_string()).unwrap().variables.len(), 4);
+        assert!(StorageLayout::from_json("7").is_err());
+        assert!(StorageLayout::from_json(r#"{"abi": "[", "storage": []}"#).unwrap_err().contains("abi string"));
+        assert!(StorageLayout::from_json(r#"{"storage": [{"name": "x"}]}"#).is_err());
+    }
+
+    #[test]
+    fn push_slots_flattens_structs() {
+        let structs: HashMap<&str, Vec<(&str, &str)>> = HashMap::from([
+            ("Outer", vec![("amount", "u256"), ("inner", "Inner"), ("tail", "u64")]),
+            ("Inner", vec![("flag", "bool"), ("label", "felt252")]),
+            ("Loop", vec![("next", "Loop")]),
+        ]);
+        let mut flat = Vec::new();
+        push_slots("Outer", String::new(), &structs, 0, &mut flat);
+        let flat: Vec<(u64, &str, SlotKind)> = flat.iter().map(|slot| (slot.offset, slot.suffix.as_str(), slot.kind)).collect();
+        assert_eq!(
+            flat,
+            vec![
+                (0, ".amount.low", SlotKind::U256Low),
+                (1, ".amount.high", SlotKind::U256High),
+                (2, ".inner.flag", SlotKind::Bool),
+                (3, ".inner.label", SlotKind::Felt),
+                (4, ".tail", SlotKind::Uint),
+            ]
+        );
+
+        // A self-referencing struct stops at the depth limit as a plain felt.
+        let mut looped = Vec::new();
+        push_slots("Loop", String::new(), &structs, 0, &mut looped);
+        assert_eq!(looped.len(), 1);
+        assert_eq!((looped[0].suffix.matches(".next").count(), looped[0].kind), (MAX_STRUCT_DEPTH, SlotKind::Felt));
+    }
+
+    #[test]
+    fn decode_slot_reads_values_as_typed() {
+        let eth = Felt::from_hex_unchecked("0x455448");
+        assert_eq!(decode_slot(SlotKind::Felt, &eth), "'ETH'");
+        assert_eq!(decode_slot(SlotKind::Felt, &Felt::from_hex_unchecked("0x4554480a")), "0x4554480a");
+        assert_eq!(decode_slot(SlotKind::Felt, &Felt::ZERO), "0x0");
+        assert_eq!(decode_slot(SlotKind::Bool, &Felt::ZERO), "false");
+        assert_eq!(decode_slot(SlotKind::Bool, &Felt::ONE), "true");
+        assert_eq!(decode_slot(SlotKind::Bool, &Felt::TWO), "0x2 (not a bool)");
+        assert_eq!(decode_slot(SlotKind::Uint, &eth), "4543560");
+        assert_eq!(decode_slot(SlotKind::Address, &eth), "0x455448");
+    }
+}
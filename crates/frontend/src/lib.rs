@@ -1,4 +1,4 @@
-use bonsai_types::{CfsResponse, DiffResponse, LeafResponse, NodeResponse, ProofResponse, RootResponse, TrieKind};
+use bonsai_types::{is_storage_expression, ActivityResponse, BisectResponse, CfsResponse, ChangeKind, ChangeValue, ClassResponse, ClassesResponse, ContractResponse, ContractStorageProofResponse, DiffResponse, HistogramBucket, HistoryResponse, JobResponse, JobState, LeafResponse, LeavesResponse, LogGap, LogKeyType, NodeHashToNode, NodeResponse, ProofDocument, ProofMembership, ProofNodeJson, ProofResponse, RangeDiffResponse, ResolvedKeyResponse, RetentionResponse, RootResponse, StateRootResponse, StorageLabel, StorageLabelsRequest, StorageLabelsResponse, TrieKind, VerifyProofRequest, VerifyProofResponse};
 use gloo_net::http::Request;
 use gloo_timers::future::TimeoutFuture;
 use leptos::prelude::*;
@@ -6,6 +6,7 @@ use leptos::prelude::IntoAny;
 use leptos::task::spawn_local;
 use leptos::prelude::Callback;
 use wasm_bindgen::prelude::wasm_bindgen;
+use std::collections::HashMap;
 
 const API_BASE: &str = "http://127.0.0.1:4010";
 
@@ -17,7 +18,10 @@ pub fn App() -> impl IntoView {
 
     let (trie_kind, set_trie_kind) = signal(TrieKind::Contract);
     let (identifier, set_identifier) = signal(String::new());
+    let (block_input, set_block_input) = signal(String::new());
     let (key_input, set_key_input) = signal(String::new());
+    let (resolved_key, set_resolved_key) = signal::<Option<ResolvedKeyResponse>>(None);
+    let (history_resp, set_history_resp) = signal::<Option<HistoryResponse>>(None);
 
     let (root, set_root) = signal::<Option<RootResponse>>(None);
     let (nodes, set_nodes) = signal(std::collections::HashMap::<String, NodeResponse>::new());
@@ -29,13 +33,45 @@ pub fn App() -> impl IntoView {
     let (search_target, set_search_target) = signal::<Option<String>>(None);
 
     let (diff_block, set_diff_block) = signal(String::new());
+    let (diff_end, set_diff_end) = signal(String::new());
     let (diff_resp, set_diff_resp) = signal::<Option<DiffResponse>>(None);
+    let (range_resp, set_range_resp) = signal::<Option<RangeDiffResponse>>(None);
+    let (retention_resp, set_retention_resp) = signal::<Option<RetentionResponse>>(None);
+    let (bisect_resp, set_bisect_resp) = signal::<Option<BisectResponse>>(None);
+    let (activity_resp, set_activity_resp) = signal::<Option<ActivityResponse>>(None);
 
     let (proof_resp, set_proof_resp) = signal::<Option<ProofResponse>>(None);
+    let (verify_resp, set_verify_resp) = signal::<Option<VerifyProofResponse>>(None);
+    let (bundle_resp, set_bundle_resp) = signal::<Option<ContractStorageProofResponse>>(None);
     let (leaf_resp, set_leaf_resp) = signal::<Option<LeafResponse>>(None);
     let (trace_bits, set_trace_bits) = signal::<Option<String>>(None);
 
+    let (leaves_start, set_leaves_start) = signal(String::new());
+    let (leaves_end, set_leaves_end) = signal(String::new());
+    let (leaves_resp, set_leaves_resp) = signal::<Option<LeavesResponse>>(None);
+    let (labels_resp, set_labels_resp) = signal::<Option<StorageLabelsResponse>>(None);
+
+    let (contract_address, set_contract_address) = signal(String::new());
+    let (contract_resp, set_contract_resp) = signal::<Option<ContractResponse>>(None);
+
+    let (class_hash, set_class_hash) = signal(String::new());
+    let (class_resp, set_class_resp) = signal::<Option<ClassResponse>>(None);
+    let (classes_resp, set_classes_resp) = signal::<Option<ClassesResponse>>(None);
+
     let (cfs_resp, set_cfs_resp) = signal::<Option<CfsResponse>>(None);
+    let (state_root_resp, set_state_root_resp) = signal::<Option<StateRootResponse>>(None);
+    let (check_job, set_check_job) = signal::<Option<JobResponse>>(None);
+    let (storage_contracts, set_storage_contracts) = signal::<Vec<String>>(Vec::new());
+
+    let fetch_state_root = move || {
+        let block = block_input.get_untracked();
+        spawn_local(async move {
+            let url = format!("{API_BASE}/api/state-root?{}", block_param(&block).trim_start_matches('&'));
+            let Ok(resp) = Request::get(&url).send().await else { return; };
+            let Ok(data) = resp.json::<StateRootResponse>().await else { return; };
+            set_state_root_resp.set(Some(data));
+        });
+    };
 
     let open_db = move || {
         let path = db_path.get();
@@ -48,12 +84,11 @@ pub fn App() -> impl IntoView {
             let url = format!("{API_BASE}/api/open?db_path={}", urlencoding::encode(&path));
             let _ = Request::post(&url).send().await;
             set_db_status.set(format!("DB open requested: {path}"));
+            fetch_state_root();
         });
     };
 
     let fetch_node = {
-        let set_nodes = set_nodes;
-        let set_loading_paths = set_loading_paths;
         move |trie: TrieKind, ident: String, path_hex: String| async move {
             set_loading_paths.update(|set| {
                 set.insert(path_hex.clone());
@@ -62,6 +97,7 @@ pub fn App() -> impl IntoView {
             if trie == TrieKind::Storage && !ident.is_empty() {
                 url.push_str(&format!("&identifier={}", urlencoding::encode(&ident)));
             }
+            url.push_str(&block_param(&block_input.get_untracked()));
             let Ok(resp) = Request::get(&url).send().await else { return None; };
             let Ok(data) = resp.json::<NodeResponse>().await else { return None; };
             let path_key = path_hex.clone();
@@ -76,7 +112,6 @@ pub fn App() -> impl IntoView {
     };
 
     let on_node = {
-        let fetch_node = fetch_node.clone();
         Callback::new(move |path_hex: String| {
             let trie = trie_kind.get();
             let ident = identifier.get();
@@ -87,16 +122,17 @@ pub fn App() -> impl IntoView {
     };
 
     let fetch_root = {
-        let on_node = on_node.clone();
-        let fetch_node = fetch_node.clone();
         move || {
             let trie = trie_kind.get();
             let ident = identifier.get();
+            let block = block_input.get();
+            set_nodes.set(std::collections::HashMap::new());
             spawn_local(async move {
                 let mut url = format!("{API_BASE}/api/trie/root?trie={}", format_trie(trie));
                 if trie == TrieKind::Storage && !ident.is_empty() {
                     url.push_str(&format!("&identifier={}", urlencoding::encode(&ident)));
                 }
+                url.push_str(&block_param(&block));
                 let Ok(resp) = Request::get(&url).send().await else { return; };
                 let Ok(data) = resp.json::<RootResponse>().await else { return; };
                 if let Some(node) = data.node.clone() {
@@ -112,12 +148,6 @@ pub fn App() -> impl IntoView {
     };
 
     let expand_all = {
-        let fetch_node = fetch_node.clone();
-        let set_expand_running = set_expand_running;
-        let set_expand_progress = set_expand_progress;
-        let expand_limit = expand_limit;
-        let root = root;
-        let expand_running = expand_running;
         move || {
             let trie = trie_kind.get();
             let ident = identifier.get();
@@ -152,7 +182,7 @@ pub fn App() -> impl IntoView {
                         }
                     }
                     set_expand_progress.set((loaded, queue.len()));
-                    if loaded % 25 == 0 {
+                    if loaded.is_multiple_of(25) {
                         TimeoutFuture::new(16).await;
                     }
                 }
@@ -165,6 +195,7 @@ pub fn App() -> impl IntoView {
         let trie = trie_kind.get();
         let ident = identifier.get();
         let key = key_input.get();
+        let block = block_input.get();
         if key.is_empty() {
             return;
         }
@@ -173,18 +204,74 @@ pub fn App() -> impl IntoView {
             if trie == TrieKind::Storage && !ident.is_empty() {
                 url.push_str(&format!("&identifier={}", urlencoding::encode(&ident)));
             }
+            url.push_str(&block_param(&block));
             let Ok(resp) = Request::get(&url).send().await else { return; };
             let Ok(data) = resp.json::<LeafResponse>().await else { return; };
             set_leaf_resp.set(Some(data));
         });
     };
 
+    let fetch_leaves = move |cursor: Option<String>| {
+        let trie = trie_kind.get();
+        let ident = identifier.get();
+        let start = leaves_start.get();
+        let end = leaves_end.get();
+        spawn_local(async move {
+            let mut url = format!("{API_BASE}/api/trie/leaves?trie={}&limit=100", format_trie(trie));
+            if trie == TrieKind::Storage && !ident.is_empty() {
+                url.push_str(&format!("&identifier={}", urlencoding::encode(&ident)));
+            }
+            for (name, value) in [("cursor", cursor.unwrap_or_default()), ("start", start), ("end", end)] {
+                if !value.trim().is_empty() {
+                    url.push_str(&format!("&{name}={}", urlencoding::encode(value.trim())));
+                }
+            }
+            let Ok(resp) = Request::get(&url).send().await else { return; };
+            let Ok(data) = resp.json::<LeavesResponse>().await else { return; };
+            set_leaves_resp.set(Some(data));
+        });
+    };
+
+    let fetch_labels = move |layout: String| {
+        let contract = identifier.get();
+        if trie_kind.get() != TrieKind::Storage || contract.is_empty() {
+            set_labels_resp.set(Some(StorageLabelsResponse {
+                variables: Vec::new(),
+                labels: Vec::new(),
+                leaves: 0,
+                mapping_keys: 0,
+                error: Some("Pick the storage trie and a contract first".to_string()),
+            }));
+            return;
+        }
+        let body = StorageLabelsRequest { contract, layout };
+        spawn_local(async move {
+            let url = format!("{API_BASE}/api/storage/labels");
+            let Ok(req) = Request::post(&url).json(&body) else { return; };
+            let Ok(resp) = req.send().await else { return; };
+            let Ok(data) = resp.json::<StorageLabelsResponse>().await else { return; };
+            set_labels_resp.set(Some(data));
+        });
+    };
+
     let fetch_diff = move || {
         let trie = trie_kind.get();
         let block = diff_block.get();
+        let end = diff_end.get();
         if block.is_empty() {
             return;
         }
+        if !end.is_empty() {
+            set_diff_resp.set(None);
+            spawn_local(async move {
+                let url = format!("{API_BASE}/api/diff/range?trie={}&start={}&end={}", format_trie(trie), block, end);
+                let Ok(resp) = Request::get(&url).send().await else { return; };
+                let Ok(data) = resp.json::<RangeDiffResponse>().await else { return; };
+                set_range_resp.set(Some(data));
+            });
+            return;
+        }
+        set_range_resp.set(None);
         spawn_local(async move {
             let url = format!("{API_BASE}/api/diff?trie={}&block={}", format_trie(trie), block);
             let Ok(resp) = Request::get(&url).send().await else { return; };
@@ -193,10 +280,97 @@ pub fn App() -> impl IntoView {
         });
     };
 
+    let start_check = move |kind: &'static str| {
+        let trie = trie_kind.get();
+        let ident = identifier.get();
+        let block = block_input.get();
+        spawn_local(async move {
+            let mut url = format!("{API_BASE}/api/jobs/{kind}?trie={}", format_trie(trie));
+            if trie == TrieKind::Storage && !ident.is_empty() {
+                url.push_str(&format!("&identifier={}", urlencoding::encode(&ident)));
+            }
+            url.push_str(&block_param(&block));
+            let Ok(resp) = Request::post(&url).send().await else { return; };
+            let Ok(mut job) = resp.json::<JobResponse>().await else { return; };
+            set_check_job.set(Some(job.clone()));
+            while job.state == JobState::Running {
+                TimeoutFuture::new(500).await;
+                let url = format!("{API_BASE}/api/jobs/status?id={}", job.id);
+                let Ok(resp) = Request::get(&url).send().await else { return; };
+                let Ok(next) = resp.json::<JobResponse>().await else { return; };
+                job = next;
+                set_check_job.set(Some(job.clone()));
+            }
+            if let Some(found) = &job.contracts {
+                set_storage_contracts.set(found.contracts.iter().map(|c| c.address.clone()).collect());
+            }
+        });
+    };
+
+    let pick_storage_contract = move |address: String| {
+        set_identifier.set(address);
+        set_trie_kind.set(TrieKind::Storage);
+    };
+
+    let fetch_retention = move || {
+        spawn_local(async move {
+            let url = format!("{API_BASE}/api/logs/retention");
+            let Ok(resp) = Request::get(&url).send().await else { return; };
+            let Ok(data) = resp.json::<RetentionResponse>().await else { return; };
+            set_retention_resp.set(Some(data));
+        });
+    };
+
+    let fetch_activity = move || {
+        let (start, end) = (diff_block.get(), diff_end.get());
+        if start.is_empty() || end.is_empty() {
+            return;
+        }
+        spawn_local(async move {
+            let url = format!("{API_BASE}/api/activity?start={start}&end={end}");
+            let Ok(resp) = Request::get(&url).send().await else { return; };
+            let Ok(data) = resp.json::<ActivityResponse>().await else { return; };
+            set_activity_resp.set(Some(data));
+        });
+    };
+
+    let open_block_diff = move |block: u64| {
+        set_diff_block.set(block.to_string());
+        set_diff_end.set(String::new());
+        fetch_diff();
+    };
+
+    // Bisects the Diff tab's block range: for the sidebar key taking `target` as its
+    // value, or, with `root`, for the trie root differing from `target`.
+    let fetch_bisect = move |target: String, root: bool| {
+        let trie = trie_kind.get();
+        let ident = identifier.get();
+        let key = key_input.get();
+        let (start, end) = (diff_block.get(), diff_end.get());
+        if start.is_empty() || end.is_empty() || target.is_empty() || (!root && key.is_empty()) {
+            return;
+        }
+        spawn_local(async move {
+            let mut url = format!("{API_BASE}/api/bisect?trie={}&start={}&end={}", format_trie(trie), start, end);
+            if trie == TrieKind::Storage && !ident.is_empty() {
+                url.push_str(&format!("&identifier={}", urlencoding::encode(&ident)));
+            }
+            if root {
+                url.push_str(&format!("&root={}", urlencoding::encode(&target)));
+            } else {
+                url.push_str(&format!("&key={}&value={}", urlencoding::encode(&key), urlencoding::encode(&target)));
+            }
+            let Ok(resp) = Request::get(&url).send().await else { return; };
+            let Ok(data) = resp.json::<BisectResponse>().await else { return; };
+            set_bisect_resp.set(Some(data));
+        });
+    };
+
     let fetch_proof = move || {
         let trie = trie_kind.get();
         let ident = identifier.get();
         let key = key_input.get();
+        let block = block_input.get();
         if key.is_empty() {
             return;
         }
@@ -205,29 +379,164 @@ pub fn App() -> impl IntoView {
             if trie == TrieKind::Storage && !ident.is_empty() {
                 url.push_str(&format!("&identifier={}", urlencoding::encode(&ident)));
             }
+            url.push_str(&block_param(&block));
             let Ok(resp) = Request::get(&url).send().await else { return; };
             let Ok(data) = resp.json::<ProofResponse>().await else { return; };
             set_proof_resp.set(Some(data));
         });
     };
 
+    let fetch_bundle = move || {
+        let contract = identifier.get();
+        let key = key_input.get();
+        let block = block_input.get();
+        if contract.is_empty() || key.is_empty() {
+            return;
+        }
+        spawn_local(async move {
+            let mut url = format!(
+                "{API_BASE}/api/proof/contract-storage?contract={}&key={}",
+                urlencoding::encode(&contract),
+                urlencoding::encode(&key)
+            );
+            url.push_str(&block_param(&block));
+            let Ok(resp) = Request::get(&url).send().await else { return; };
+            let Ok(data) = resp.json::<ContractStorageProofResponse>().await else { return; };
+            set_bundle_resp.set(Some(data));
+        });
+    };
+
+    let verify_proof = move |pasted: String, root: String, value: String| {
+        let failed = |err: String| VerifyProofResponse { verified: false, membership: None, value: None, steps: Vec::new(), error: Some(err) };
+        let body = match pasted_request(&pasted, trie_kind.get(), key_input.get(), &root, &value) {
+            Ok(body) => body,
+            Err(err) => {
+                set_verify_resp.set(Some(failed(err)));
+                return;
+            }
+        };
+        spawn_local(async move {
+            let req = match &body {
+                PastedProof::Document(doc) => Request::post(&format!("{API_BASE}/api/proof/verify-document")).json(doc),
+                PastedProof::Nodes(req) => Request::post(&format!("{API_BASE}/api/proof/verify")).json(req),
+            };
+            let Ok(req) = req else { return; };
+            let Ok(resp) = req.send().await else { return; };
+            let Ok(data) = resp.json::<VerifyProofResponse>().await else { return; };
+            set_verify_resp.set(Some(data));
+        });
+    };
+
+    let fetch_history = move |key: String| {
+        let trie = trie_kind.get();
+        let ident = identifier.get();
+        spawn_local(async move {
+            let mut url = format!("{API_BASE}/api/history?trie={}&key={}", format_trie(trie), urlencoding::encode(&key));
+            if trie == TrieKind::Storage && !ident.is_empty() {
+                url.push_str(&format!("&identifier={}", urlencoding::encode(&ident)));
+            }
+            let Ok(resp) = Request::get(&url).send().await else { return; };
+            let Ok(data) = resp.json::<HistoryResponse>().await else { return; };
+            set_history_resp.set(Some(data));
+        });
+    };
+
+    let on_key_input = move |key: String| {
+        set_key_input.set(key.clone());
+        if !is_key_expression(trie_kind.get_untracked(), &key) {
+            return;
+        }
+        spawn_local(async move {
+            let url = format!("{API_BASE}/api/key/resolve?expression={}", urlencoding::encode(&key));
+            let Ok(resp) = Request::get(&url).send().await else { return; };
+            let Ok(data) = resp.json::<ResolvedKeyResponse>().await else { return; };
+            set_resolved_key.set(Some(data));
+        });
+    };
+
+    // The resolved felt of the Key box, if it holds an expression that has been resolved.
+    let current_resolution = move || resolved_key.get().filter(|resolved| resolved.expression == key_input.get() && is_key_expression(trie_kind.get(), &resolved.expression));
+
     let fetch_trace = {
-        let fetch_leaf = fetch_leaf;
-        let fetch_proof = fetch_proof;
         move || {
-            let key = key_input.get();
+            let mut key = key_input.get();
             if key.is_empty() {
                 return;
             }
+            if is_key_expression(trie_kind.get(), &key) {
+                let Some(resolved) = current_resolution().and_then(|resolved| resolved.key) else { return; };
+                key = resolved;
+            }
             let bits = felt_hex_to_bits(&key);
             set_trace_bits.set(Some(format_bits_preview(&bits)));
             fetch_leaf();
             fetch_proof();
+            fetch_history(key);
+        }
+    };
+
+    let fetch_contract = move || {
+        let address = contract_address.get();
+        let block = block_input.get();
+        if address.is_empty() {
+            return;
+        }
+        spawn_local(async move {
+            let url = format!("{API_BASE}/api/contract?address={}{}", urlencoding::encode(&address), block_param(&block));
+            let Ok(resp) = Request::get(&url).send().await else { return; };
+            let Ok(data) = resp.json::<ContractResponse>().await else { return; };
+            set_contract_resp.set(Some(data));
+        });
+    };
+
+    let fetch_class = move || {
+        let hash = class_hash.get();
+        let block = block_input.get();
+        if hash.is_empty() {
+            return;
+        }
+        spawn_local(async move {
+            let url = format!("{API_BASE}/api/class?class_hash={}{}", urlencoding::encode(&hash), block_param(&block));
+            let Ok(resp) = Request::get(&url).send().await else { return; };
+            let Ok(data) = resp.json::<ClassResponse>().await else { return; };
+            set_class_resp.set(Some(data));
+        });
+    };
+
+    let fetch_classes = move |cursor: Option<String>| {
+        spawn_local(async move {
+            let mut url = format!("{API_BASE}/api/classes?limit=100");
+            if let Some(cursor) = cursor {
+                url.push_str(&format!("&cursor={}", urlencoding::encode(&cursor)));
+            }
+            let Ok(resp) = Request::get(&url).send().await else { return; };
+            let Ok(data) = resp.json::<ClassesResponse>().await else { return; };
+            set_classes_resp.set(Some(data));
+        });
+    };
+
+    let pick_class = move |hash: String| {
+        set_class_hash.set(hash);
+        fetch_class();
+    };
+
+    let open_storage_trie = move |address: String| {
+        set_identifier.set(address);
+        set_trie_kind.set(TrieKind::Storage);
+        set_active_tab.set(Tab::Tree);
+        fetch_root();
+    };
+
+    let on_diff_select = move |key: String, contract: Option<String>| {
+        if let Some(contract) = contract {
+            set_identifier.set(contract);
         }
+        set_key_input.set(key);
+        set_active_tab.set(Tab::Path);
+        fetch_trace();
     };
 
     let on_search = {
-        let on_node = on_node.clone();
         move || {
             let query = search_input.get();
             if query.is_empty() {
@@ -265,6 +574,9 @@ pub fn App() -> impl IntoView {
                 <nav>
                     <button class=tab_class(active_tab, Tab::Tree) on:click=move |_| set_active_tab.set(Tab::Tree)>"Tree"</button>
                     <button class=tab_class(active_tab, Tab::Path) on:click=move |_| set_active_tab.set(Tab::Path)>"Path Trace"</button>
+                    <button class=tab_class(active_tab, Tab::Leaves) on:click=move |_| set_active_tab.set(Tab::Leaves)>"Leaves"</button>
+                    <button class=tab_class(active_tab, Tab::Contract) on:click=move |_| set_active_tab.set(Tab::Contract)>"Contract"</button>
+                    <button class=tab_class(active_tab, Tab::Class) on:click=move |_| set_active_tab.set(Tab::Class)>"Class"</button>
                     <button class=tab_class(active_tab, Tab::Diff) on:click=move |_| set_active_tab.set(Tab::Diff)>"Diff"</button>
                     <button class=tab_class(active_tab, Tab::Proof) on:click=move |_| set_active_tab.set(Tab::Proof)>"Proof"</button>
                     <button class=tab_class(active_tab, Tab::Stats) on:click=move |_| set_active_tab.set(Tab::Stats)>"Stats"</button>
@@ -275,23 +587,40 @@ pub fn App() -> impl IntoView {
                     <button on:click=move |_| open_db()>"Open"</button>
                     <p class="muted">{move || db_status.get()}</p>
                 </div>
+                <div class="panel">
+                    <label>"State root"</label>
+                    <button on:click=move |_| fetch_state_root()>"Check header"</button>
+                    {move || state_root_resp.get().map(|resp| {
+                        let (class, text) = format_state_root(&resp);
+                        view! { <p class=class title=resp.state_root.unwrap_or_default()>{text}</p> }
+                    })}
+                </div>
                 <div class="panel">
                     <label>"Trie"</label>
                     <select on:change=move |ev| {
                         let v = event_target_value(&ev);
                         let kind = match v.as_str() { "contract" => TrieKind::Contract, "storage" => TrieKind::Storage, _ => TrieKind::Class };
                         set_trie_kind.set(kind);
-                    }>
+                    } prop:value=move || format_trie(trie_kind.get())>
                         <option value="contract">"Contract"</option>
                         <option value="storage">"Storage"</option>
                         <option value="class">"Class"</option>
                     </select>
                     <label>"Storage Identifier"</label>
-                    <input type="text" value=identifier on:input=move |ev| set_identifier.set(event_target_value(&ev)) />
+                    <input type="text" list="storage-contracts" value=identifier on:input=move |ev| set_identifier.set(event_target_value(&ev)) />
+                    <datalist id="storage-contracts">
+                        {move || storage_contracts.get().into_iter().map(|address| view! { <option value=address></option> }).collect_view()}
+                    </datalist>
+                    <label>"Block (empty = latest)"</label>
+                    <input type="text" value=block_input on:input=move |ev| set_block_input.set(event_target_value(&ev)) />
                 </div>
                 <div class="panel">
                     <label>"Key"</label>
-                    <input type="text" value=key_input on:input=move |ev| set_key_input.set(event_target_value(&ev)) />
+                    <input type="text" placeholder="0x… or balances[0xabc]" value=key_input on:input=move |ev| on_key_input(event_target_value(&ev)) />
+                    {move || current_resolution().map(|resolved| match (resolved.key, resolved.error) {
+                        (Some(key), _) => view! { <p class="mono muted">"= " {key}</p> }.into_any(),
+                        (None, err) => view! { <p class="error">{err.unwrap_or_default()}</p> }.into_any(),
+                    })}
                     <div class="row">
                         <button on:click=move |_| fetch_leaf()>"Leaf"</button>
                         <button on:click=move |_| fetch_proof()>"Proof"</button>
@@ -323,16 +652,27 @@ pub fn App() -> impl IntoView {
                     />
                 </Show>
                 <Show when=move || active_tab.get() == Tab::Path fallback=|| ()>
-                    <PathView leaf=leaf_resp proof=proof_resp trace=trace_bits />
+                    <PathView leaf=leaf_resp proof=proof_resp trace=trace_bits history=history_resp />
+                </Show>
+                <Show when=move || active_tab.get() == Tab::Leaves fallback=|| ()>
+                    <LeavesView start=leaves_start end=leaves_end leaves=leaves_resp labels=labels_resp on_start=set_leaves_start on_end=set_leaves_end on_fetch=fetch_leaves on_layout=fetch_labels on_select=on_diff_select />
+                </Show>
+                <Show when=move || active_tab.get() == Tab::Contract fallback=|| ()>
+                    <ContractView address=contract_address contract=contract_resp on_address=set_contract_address on_fetch=fetch_contract on_open_storage=open_storage_trie />
+                </Show>
+                <Show when=move || active_tab.get() == Tab::Class fallback=|| ()>
+                    <ClassView class_hash=class_hash class=class_resp classes=classes_resp on_class_hash=set_class_hash on_fetch=fetch_class on_list=fetch_classes on_pick=pick_class />
                 </Show>
                 <Show when=move || active_tab.get() == Tab::Diff fallback=|| ()>
-                    <DiffView block=diff_block diff=diff_resp on_block=set_diff_block on_fetch=fetch_diff />
+                    <DiffView block=diff_block end=diff_end diff=diff_resp range=range_resp on_block=set_diff_block on_end=set_diff_end on_fetch=fetch_diff on_select=on_diff_select retention=retention_resp on_retention=fetch_retention />
+                    <ActivityView activity=activity_resp on_fetch=fetch_activity on_block=open_block_diff />
+                    <BisectView bisect=bisect_resp on_bisect=fetch_bisect />
                 </Show>
                 <Show when=move || active_tab.get() == Tab::Proof fallback=|| ()>
-                    <ProofView proof=proof_resp />
+                    <ProofView proof=proof_resp verify=verify_resp on_verify=verify_proof bundle=bundle_resp on_bundle=fetch_bundle />
                 </Show>
                 <Show when=move || active_tab.get() == Tab::Stats fallback=|| ()>
-                    <StatsView cfs=cfs_resp on_fetch=fetch_cfs />
+                    <StatsView cfs=cfs_resp on_fetch=fetch_cfs job=check_job on_check=start_check on_pick=pick_storage_contract />
                 </Show>
             </main>
         </div>
@@ -360,6 +700,7 @@ fn TreeView(
                 <h2>"Trie Tree"</h2>
                 <button on:click=move |_| on_root()>"Load Root"</button>
             </div>
+            {move || root.get().and_then(|r| r.error).map(|err| view! { <p class="error">{err}</p> })}
             <Show when=move || root.get().is_some() fallback=|| view! { <p class="muted">"No root loaded."</p> }>
                 {move || {
                     let root = root.get().unwrap();
@@ -510,9 +851,6 @@ fn GraphView(
                                 }
                             }).collect_view()}
                             {data.nodes.into_iter().map(|node| {
-                                let on_node = on_node.clone();
-                                let set_selected = set_selected;
-                                let set_hovered = set_hovered;
                                 let node_path = node.path.clone();
                                 let node_path_click = node_path.clone();
                                 let node_path_hover_in = node_path.clone();
@@ -754,6 +1092,7 @@ fn PathView(
     leaf: ReadSignal<Option<LeafResponse>>,
     proof: ReadSignal<Option<ProofResponse>>,
     trace: ReadSignal<Option<String>>,
+    history: ReadSignal<Option<HistoryResponse>>,
 ) -> impl IntoView {
     view! {
         <section>
@@ -775,7 +1114,10 @@ fn PathView(
             <Show when=move || leaf.get().is_some() fallback=|| ()>
                 {move || {
                     let resp = leaf.get().unwrap();
-                    view! { <p>"Value: " {resp.value.unwrap_or_else(|| "None".to_string())}</p> }
+                    match resp.error {
+                        Some(err) => view! { <p class="error">{err}</p> }.into_any(),
+                        None => view! { <p>"Value: " {resp.value.unwrap_or_else(|| "None".to_string())}</p> }.into_any(),
+                    }
                 }}
             </Show>
             <Show when=move || proof.get().is_some() fallback=|| ()>
@@ -784,7 +1126,8 @@ fn PathView(
                     view! {
                         <div class="detail-card">
                             <h3>"Proof Trace"</h3>
-                            <p class="muted">{if resp.verified { "Verified" } else { "Not verified" }}</p>
+                            <p class="muted">{format_proof_status(resp.verified, resp.membership, resp.value.as_deref())}</p>
+                            {resp.error.map(|err| view! { <p class="error">{err}</p> })}
                             <ul class="list">
                                 {resp.nodes.into_iter().map(|n| view!{
                                     <li>{n.kind} " path_len=" {n.path_len.unwrap_or_default()}</li>
@@ -794,6 +1137,244 @@ fn PathView(
                     }
                 }}
             </Show>
+            {move || history.get().map(|resp| view! {
+                <div class="detail-card">
+                    <h3>"Value History"</h3>
+                    {resp.error.map(|err| view! { <p class="error">{err}</p> })}
+                    <p class="muted">{match resp.earliest_logged {
+                        Some(block) => format!("{} changes; the trie log starts at block {block}", resp.entries.len()),
+                        None => "No trie logs for this trie".to_string(),
+                    }}</p>
+                    <ol class="timeline">
+                        {resp.entries.into_iter().map(|entry| view! {
+                            <li>
+                                <span class="block">"Block " {entry.block}</span>
+                                <span>{format_change_kind(entry.kind)}</span>
+                                <p class="mono">{entry.before.unwrap_or_else(|| "-".to_string())} " → " {entry.after.unwrap_or_else(|| "-".to_string())}</p>
+                            </li>
+                        }).collect_view()}
+                    </ol>
+                </div>
+            })}
+        </section>
+    }
+}
+
+#[component]
+fn LeavesView(
+    start: ReadSignal<String>,
+    end: ReadSignal<String>,
+    leaves: ReadSignal<Option<LeavesResponse>>,
+    labels: ReadSignal<Option<StorageLabelsResponse>>,
+    on_start: WriteSignal<String>,
+    on_end: WriteSignal<String>,
+    on_fetch: impl Fn(Option<String>) + 'static + Copy + Send + Sync,
+    on_layout: impl Fn(String) + 'static + Copy + Send + Sync,
+    on_select: impl Fn(String, Option<String>) + 'static + Copy + Send + Sync,
+) -> impl IntoView {
+    let on_upload = move |ev: leptos::ev::Event| {
+        let input = event_target::<web_sys::HtmlInputElement>(&ev);
+        let Some(file) = input.files().and_then(|files| files.get(0)) else { return; };
+        spawn_local(async move {
+            if let Ok(text) = wasm_bindgen_futures::JsFuture::from(file.text()).await {
+                on_layout(text.as_string().unwrap_or_default());
+            }
+        });
+    };
+    view! {
+        <section>
+            <div class="header-row">
+                <h2>"Leaves"</h2>
+                <div class="row">
+                    <input type="text" placeholder="Start key (optional)" value=start on:input=move |ev| on_start.set(event_target_value(&ev)) />
+                    <input type="text" placeholder="End key (optional)" value=end on:input=move |ev| on_end.set(event_target_value(&ev)) />
+                    <button on:click=move |_| on_fetch(None)>"Load"</button>
+                    <label>"Class / ABI JSON"</label>
+                    <input type="file" accept=".json,application/json" on:change=on_upload />
+                </div>
+            </div>
+            {move || labels.get().map(|resp| view! {
+                <div class="detail-card">
+                    {resp.error.map(|err| view! { <p class="error">{err}</p> })}
+                    <p class="muted">{format!(
+                        "{} variables; {} of {} storage leaves labeled; {} mapping keys from the trie logs",
+                        resp.variables.len(),
+                        resp.labels.len(),
+                        resp.leaves,
+                        resp.mapping_keys,
+                    )}</p>
+                </div>
+            })}
+            <Show when=move || leaves.get().is_some() fallback=|| view! { <p class="muted">"No leaves loaded."</p> }>
+                {move || {
+                    let resp = leaves.get().unwrap();
+                    let next = resp.next_cursor.clone();
+                    let labeled: HashMap<String, StorageLabel> =
+                        labels.get().map(|labels| labels.labels.into_iter().map(|label| (label.key.clone(), label)).collect()).unwrap_or_default();
+                    let show_labels = !labeled.is_empty();
+                    view! {
+                        <div class="detail-card">
+                            {resp.error.map(|err| view! { <p class="error">{err}</p> })}
+                            <p class="muted">"Leaves on this page: " {resp.leaves.len()}</p>
+                            <table class="diff-table">
+                                <thead>
+                                    <tr>
+                                        <th>"Key"</th>
+                                        <th>"Value"</th>
+                                        {show_labels.then(|| view! {
+                                            <th>"Variable"</th>
+                                            <th>"Decoded"</th>
+                                        })}
+                                    </tr>
+                                </thead>
+                                <tbody>
+                                    {resp.leaves.into_iter().map(|leaf| {
+                                        let on_click = select_key(Some(leaf.key.clone()), None, on_select);
+                                        let label = labeled.get(&leaf.key).cloned();
+                                        view!{
+                                        <tr class="clickable" on:click=on_click>
+                                            <td class="mono">{leaf.key}</td>
+                                            <td class="mono">{leaf.value.unwrap_or_else(|| "undecodable".to_string())}</td>
+                                            {show_labels.then(|| view! {
+                                                <td class="mono">{label.as_ref().map(|label| label.label.clone()).unwrap_or_default()}</td>
+                                                <td class="mono">{label.and_then(|label| label.decoded).unwrap_or_default()}</td>
+                                            })}
+                                        </tr>
+                                    }}).collect_view()}
+                                </tbody>
+                            </table>
+                            <div class="row">
+                                <button on:click=move |_| on_fetch(None)>"First page"</button>
+                                {next.map(|cursor| view! {
+                                    <button on:click=move |_| on_fetch(Some(cursor.clone()))>"Next page"</button>
+                                })}
+                            </div>
+                        </div>
+                    }
+                }}
+            </Show>
+        </section>
+    }
+}
+
+#[component]
+fn ContractView(
+    address: ReadSignal<String>,
+    contract: ReadSignal<Option<ContractResponse>>,
+    on_address: WriteSignal<String>,
+    on_fetch: impl Fn() + 'static + Copy + Send + Sync,
+    on_open_storage: impl Fn(String) + 'static + Copy + Send + Sync,
+) -> impl IntoView {
+    view! {
+        <section>
+            <div class="header-row">
+                <h2>"Contract"</h2>
+                <div class="row">
+                    <input type="text" placeholder="Contract address" value=address on:input=move |ev| on_address.set(event_target_value(&ev)) />
+                    <button on:click=move |_| on_fetch()>"Load"</button>
+                </div>
+            </div>
+            <Show when=move || contract.get().is_some() fallback=|| view! { <p class="muted">"No contract loaded."</p> }>
+                {move || {
+                    let resp = contract.get().unwrap();
+                    let storage_address = resp.address.clone();
+                    view! {
+                        <div class="detail-card">
+                            {resp.error.map(|err| view! { <p class="error">{err}</p> })}
+                            <p class="mono">"Address: " {resp.address}</p>
+                            <p class="mono">"Leaf in contract trie: " {resp.leaf.unwrap_or_else(|| "-".to_string())}</p>
+                            {resp.state.map(|state| view! {
+                                <p class="mono">"Class hash: " {state.class_hash}</p>
+                                <p class="mono">"Nonce: " {state.nonce}</p>
+                                <p class="mono">"Storage root: " {state.storage_root}</p>
+                            })}
+                            <p class="mono">"Recomputed leaf: " {resp.computed_leaf.unwrap_or_else(|| "-".to_string())}</p>
+                            <p class=if resp.matches { "muted" } else { "error" }>
+                                {if resp.matches { "Leaf matches its class hash, nonce and storage root" } else { "Leaf does not match" }}
+                            </p>
+                            <button on:click=move |_| on_open_storage(storage_address.clone())>"Open storage trie"</button>
+                        </div>
+                    }
+                }}
+            </Show>
+        </section>
+    }
+}
+
+#[component]
+fn ClassView(
+    class_hash: ReadSignal<String>,
+    class: ReadSignal<Option<ClassResponse>>,
+    classes: ReadSignal<Option<ClassesResponse>>,
+    on_class_hash: WriteSignal<String>,
+    on_fetch: impl Fn() + 'static + Copy + Send + Sync,
+    on_list: impl Fn(Option<String>) + 'static + Copy + Send + Sync,
+    on_pick: impl Fn(String) + 'static + Copy + Send + Sync,
+) -> impl IntoView {
+    view! {
+        <section>
+            <div class="header-row">
+                <h2>"Class"</h2>
+                <div class="row">
+                    <input type="text" placeholder="Class hash" value=class_hash on:input=move |ev| on_class_hash.set(event_target_value(&ev)) />
+                    <button on:click=move |_| on_fetch()>"Load"</button>
+                    <button on:click=move |_| on_list(None)>"List classes"</button>
+                </div>
+            </div>
+            {move || class.get().map(|resp| view! {
+                <div class="detail-card">
+                    {resp.error.map(|err| view! { <p class="error">{err}</p> })}
+                    <p class="mono">"Class hash: " {resp.class_hash}</p>
+                    <p class="mono">"Compiled class hash: " {resp.compiled_class_hash.unwrap_or_else(|| "-".to_string())}</p>
+                    <p class="mono">"Leaf in class trie: " {resp.leaf.unwrap_or_else(|| "-".to_string())}</p>
+                    <p class="mono">"Recomputed leaf: " {resp.computed_leaf.unwrap_or_else(|| "-".to_string())}</p>
+                    <p class=if resp.matches { "muted" } else { "error" }>
+                        {if resp.matches { "Leaf matches its compiled class hash" } else { "Leaf does not match" }}
+                    </p>
+                    <p class="muted">"Declared in block: " {format_declared_block(resp.declared_block)}</p>
+                </div>
+            })}
+            <Show when=move || classes.get().is_some() fallback=|| view! { <p class="muted">"No classes listed."</p> }>
+                {move || {
+                    let resp = classes.get().unwrap();
+                    let next = resp.next_cursor.clone();
+                    view! {
+                        <div class="detail-card">
+                            {resp.error.map(|err| view! { <p class="error">{err}</p> })}
+                            <p class="muted">"Classes on this page: " {resp.classes.len()}</p>
+                            <table class="diff-table">
+                                <thead>
+                                    <tr>
+                                        <th>"Class hash"</th>
+                                        <th>"Compiled class hash"</th>
+                                        <th>"Declared"</th>
+                                        <th>"Leaf"</th>
+                                    </tr>
+                                </thead>
+                                <tbody>
+                                    {resp.classes.into_iter().map(|class| {
+                                        let hash = class.class_hash.clone();
+                                        view! {
+                                            <tr class=if class.matches { "clickable" } else { "clickable step-failed" } on:click=move |_| on_pick(hash.clone())>
+                                                <td class="mono">{class.class_hash}</td>
+                                                <td class="mono">{class.compiled_class_hash.unwrap_or_else(|| "-".to_string())}</td>
+                                                <td>{format_declared_block(class.declared_block)}</td>
+                                                <td>{if class.matches { "ok".to_string() } else { class.error.unwrap_or_else(|| "mismatch".to_string()) }}</td>
+                                            </tr>
+                                        }
+                                    }).collect_view()}
+                                </tbody>
+                            </table>
+                            <div class="row">
+                                <button on:click=move |_| on_list(None)>"First page"</button>
+                                {next.map(|cursor| view! {
+                                    <button on:click=move |_| on_list(Some(cursor.clone()))>"Next page"</button>
+                                })}
+                            </div>
+                        </div>
+                    }
+                }}
+            </Show>
         </section>
     }
 }
@@ -801,9 +1382,15 @@ fn PathView(
 #[component]
 fn DiffView(
     block: ReadSignal<String>,
+    end: ReadSignal<String>,
     diff: ReadSignal<Option<DiffResponse>>,
+    range: ReadSignal<Option<RangeDiffResponse>>,
     on_block: WriteSignal<String>,
+    on_end: WriteSignal<String>,
     on_fetch: impl Fn() + 'static + Copy,
+    on_select: impl Fn(String, Option<String>) + 'static + Copy + Send + Sync,
+    retention: ReadSignal<Option<RetentionResponse>>,
+    on_retention: impl Fn() + 'static + Copy,
 ) -> impl IntoView {
     view! {
         <section>
@@ -811,37 +1398,146 @@ fn DiffView(
                 <h2>"Diff"</h2>
                 <div class="row">
                     <input type="text" placeholder="Block number" value=block on:input=move |ev| on_block.set(event_target_value(&ev)) />
+                    <input type="text" placeholder="End block (optional)" value=end on:input=move |ev| on_end.set(event_target_value(&ev)) />
                     <button on:click=move |_| on_fetch()>"Load"</button>
+                    <button on:click=move |_| on_retention()>"Retention"</button>
                 </div>
             </div>
-            <Show when=move || diff.get().is_some() fallback=|| view! { <p class="muted">"No diff loaded."</p> }>
+            <Show when=move || retention.get().is_some() fallback=|| ()>
+                {move || {
+                    let resp = retention.get().unwrap();
+                    view! {
+                        <div class="detail-card">
+                            <h3>"Log Retention"</h3>
+                            <table class="diff-table">
+                                <thead>
+                                    <tr>
+                                        <th>"Trie"</th>
+                                        <th>"Earliest"</th>
+                                        <th>"Latest"</th>
+                                        <th>"Blocks"</th>
+                                        <th>"Gaps"</th>
+                                    </tr>
+                                </thead>
+                                <tbody>
+                                    {resp.logs.into_iter().map(|log| view!{
+                                        <tr>
+                                            <td>{format_trie(log.trie)}</td>
+                                            <td>{log.earliest.map(|b| b.to_string()).unwrap_or_else(|| "—".to_string())}</td>
+                                            <td>{log.latest.map(|b| b.to_string()).unwrap_or_else(|| "—".to_string())}</td>
+                                            <td>{log.logged_blocks}</td>
+                                            <td class="mono">{format_gaps(&log.gaps)}</td>
+                                        </tr>
+                                    }).collect_view()}
+                                </tbody>
+                            </table>
+                        </div>
+                    }
+                }}
+            </Show>
+            <Show when=move || range.get().is_some() fallback=|| ()>
+                {move || {
+                    let resp = range.get().unwrap();
+                    view! {
+                        <div class="detail-card">
+                            <h3>"Net changes " {resp.start} " → " {resp.end}</h3>
+                            {resp.error.map(|err| view! { <p class="error">{err}</p> })}
+                            <p class="muted">"Changed keys: " {resp.entries.len()}</p>
+                            <table class="diff-table">
+                                <thead>
+                                    <tr>
+                                        <th>"Key Type"</th>
+                                        <th>"Key"</th>
+                                        <th>"Contract"</th>
+                                        <th>"Blocks"</th>
+                                        <th>"Before"</th>
+                                        <th>"After"</th>
+                                    </tr>
+                                </thead>
+                                <tbody>
+                                    {resp.entries.into_iter().map(|e| {
+                                        let class = row_class(&e.key);
+                                        let on_click = select_key(e.key.clone(), e.contract.clone(), on_select);
+                                        view!{
+                                        <tr class=class on:click=on_click>
+                                            <td>{format_key_type(e.key_type)}</td>
+                                            <td class="mono">{display_log_key(e.key, e.path_hex, e.key_len)}</td>
+                                            <td class="mono">{e.contract.unwrap_or_default()}</td>
+                                            <td>{format!("{}..{}", e.first_block, e.last_block)}</td>
+                                            <td class="mono">{e.old.unwrap_or_else(|| "—".to_string())}</td>
+                                            <td class="mono">{e.new.unwrap_or_else(|| "—".to_string())}</td>
+                                        </tr>
+                                    }}).collect_view()}
+                                </tbody>
+                            </table>
+                        </div>
+                    }
+                }}
+            </Show>
+            <Show when=move || diff.get().is_some() fallback=move || range.get().is_none().then(|| view! { <p class="muted">"No diff loaded."</p> })>
                 {move || {
                     let resp = diff.get().unwrap();
                     view! {
                         <div class="detail-card">
                             <h3>"Changes"</h3>
-                            <p class="muted">"Total entries: " {resp.entries.len()}</p>
+                            {resp.error.map(|err| view! { <p class="error">{err}</p> })}
+                            <p class="muted">"Changed keys: " {resp.changes.len()} " · log records: " {resp.entries.len()}</p>
+                            <table class="diff-table">
+                                <thead>
+                                    <tr>
+                                        <th>"Kind"</th>
+                                        <th>"Key Type"</th>
+                                        <th>"Key"</th>
+                                        <th>"Contract"</th>
+                                        <th>"Before"</th>
+                                        <th>"After"</th>
+                                    </tr>
+                                </thead>
+                                <tbody>
+                                    {resp.changes.into_iter().map(|c| {
+                                        let class = row_class(&c.key);
+                                        let on_click = select_key(c.key.clone(), c.contract.clone(), on_select);
+                                        view!{
+                                        <tr class=class on:click=on_click>
+                                            <td>{format_change_kind(c.kind)}</td>
+                                            <td>{format_key_type(c.key_type)}</td>
+                                            <td class="mono">{display_log_key(c.key, c.path_hex, c.key_len)}</td>
+                                            <td class="mono">{c.contract.unwrap_or_default()}</td>
+                                            <td class="mono">{format_change_value(c.before)}</td>
+                                            <td class="mono">{format_change_value(c.after)}</td>
+                                        </tr>
+                                    }}).collect_view()}
+                                </tbody>
+                            </table>
+                        </div>
+                        <details class="detail-card">
+                            <summary>"Raw log records"</summary>
                             <table class="diff-table">
                                 <thead>
                                     <tr>
                                         <th>"Key Type"</th>
                                         <th>"Change"</th>
-                                        <th>"Key Len"</th>
+                                        <th>"Key"</th>
+                                        <th>"Contract"</th>
                                         <th>"Value"</th>
                                     </tr>
                                 </thead>
                                 <tbody>
-                                    {resp.entries.into_iter().map(|e| view!{
-                                        <tr>
-                                            <td>{e.key_type}</td>
+                                    {resp.entries.into_iter().map(|e| {
+                                        let class = row_class(&e.key);
+                                        let on_click = select_key(e.key.clone(), e.contract.clone(), on_select);
+                                        view!{
+                                        <tr class=class on:click=on_click>
+                                            <td>{format_key_type(e.key_type)}</td>
                                             <td>{e.change_type}</td>
-                                            <td>{e.key_len.unwrap_or_default()}</td>
+                                            <td class="mono">{display_log_key(e.key, e.path_hex, e.key_len)}</td>
+                                            <td class="mono">{e.contract.unwrap_or_default()}</td>
                                             <td class="mono">{e.value}</td>
                                         </tr>
-                                    }).collect_view()}
+                                    }}).collect_view()}
                                 </tbody>
                             </table>
-                        </div>
+                        </details>
                     }
                 }}
             </Show>
@@ -850,22 +1546,164 @@ fn DiffView(
 }
 
 #[component]
-fn ProofView(proof: ReadSignal<Option<ProofResponse>>) -> impl IntoView {
+fn ActivityView(
+    activity: ReadSignal<Option<ActivityResponse>>,
+    on_fetch: impl Fn() + 'static + Copy + Send + Sync,
+    on_block: impl Fn(u64) + 'static + Copy + Send + Sync,
+) -> impl IntoView {
+    view! {
+        <div class="detail-card">
+            <div class="header-row">
+                <h3>"Activity"</h3>
+                <button on:click=move |_| on_fetch()>"Count log entries"</button>
+            </div>
+            {move || activity.get().map(|resp| {
+                let start = resp.start;
+                let rows = heatmap_rows(&resp);
+                view! {
+                    {resp.error.map(|err| view! { <p class="error">{err}</p> })}
+                    <p class="muted">
+                        "Blocks " {resp.start} " → " {resp.end} " · storage contracts: " {resp.contract_count}
+                        {(resp.contract_count as usize > resp.contracts.len()).then(|| format!(" (busiest {} shown)", resp.contracts.len()))}
+                    </p>
+                    <div class="heatmap">
+                        {rows.into_iter().map(|(label, counts)| {
+                            let max = counts.iter().copied().max().unwrap_or(0).max(1);
+                            view! {
+                                <div class="heatmap-row">
+                                    <span class="heatmap-label mono" title=label.clone()>{label.clone()}</span>
+                                    {counts.into_iter().enumerate().map(|(offset, count)| {
+                                        let block = start + offset as u64;
+                                        let style = format!("opacity: {}", heat_opacity(count, max));
+                                        view! {
+                                            <span class="heatmap-cell" style=style title=format!("{label} · block {block}: {count}") on:click=move |_| on_block(block)></span>
+                                        }
+                                    }).collect_view()}
+                                </div>
+                            }
+                        }).collect_view()}
+                    </div>
+                }
+            })}
+        </div>
+    }
+}
+
+#[component]
+fn BisectView(
+    bisect: ReadSignal<Option<BisectResponse>>,
+    on_bisect: impl Fn(String, bool) + 'static + Copy + Send + Sync,
+) -> impl IntoView {
+    let (target, set_target) = signal(String::new());
+    view! {
+        <div class="detail-card">
+            <h3>"Bisect"</h3>
+            <p class="muted">"Searches the block → end block range above for the first block where the sidebar key holds a value, or where the root differs from a hash."</p>
+            <div class="row">
+                <input type="text" placeholder="Value or expected root" value=target on:input=move |ev| set_target.set(event_target_value(&ev)) />
+                <button on:click=move |_| on_bisect(target.get(), false)>"Find value"</button>
+                <button on:click=move |_| on_bisect(target.get(), true)>"Find root change"</button>
+            </div>
+            {move || bisect.get().map(|resp| view! {
+                <p class="mono">{resp.condition.clone()}</p>
+                {resp.error.map(|err| view! { <p class="error">{err}</p> })}
+                <p>{match resp.block {
+                    Some(block) => format!("First block: {block}"),
+                    None => "Condition does not hold at the end block.".to_string(),
+                }}</p>
+                <table class="diff-table">
+                    <thead>
+                        <tr>
+                            <th>"Block"</th>
+                            <th>"Holds"</th>
+                            <th>"Observed"</th>
+                        </tr>
+                    </thead>
+                    <tbody>
+                        {resp.steps.into_iter().map(|step| view! {
+                            <tr>
+                                <td>{step.block}</td>
+                                <td>{if step.holds { "yes" } else { "no" }}</td>
+                                <td class="mono">{step.observed}</td>
+                            </tr>
+                        }).collect_view()}
+                    </tbody>
+                </table>
+            })}
+        </div>
+    }
+}
+
+#[component]
+fn ProofView(
+    proof: ReadSignal<Option<ProofResponse>>,
+    verify: ReadSignal<Option<VerifyProofResponse>>,
+    on_verify: impl Fn(String, String, String) + 'static + Copy + Send + Sync,
+    bundle: ReadSignal<Option<ContractStorageProofResponse>>,
+    on_bundle: impl Fn() + 'static + Copy + Send + Sync,
+) -> impl IntoView {
     let (show_json, set_show_json) = signal(false);
+    let (pasted, set_pasted) = signal(String::new());
+    let (root, set_root) = signal(String::new());
+    let (value, set_value) = signal(String::new());
+    let on_upload = move |ev: leptos::ev::Event| {
+        let input = event_target::<web_sys::HtmlInputElement>(&ev);
+        let Some(file) = input.files().and_then(|files| files.get(0)) else { return; };
+        spawn_local(async move {
+            if let Ok(text) = wasm_bindgen_futures::JsFuture::from(file.text()).await {
+                set_pasted.set(text.as_string().unwrap_or_default());
+            }
+        });
+    };
     view! {
         <section>
-            <h2>"Proof"</h2>
+            <div class="header-row">
+                <h2>"Proof"</h2>
+                <button on:click=move |_| on_bundle()>"Prove slot from contract root"</button>
+            </div>
+            {move || bundle.get().map(|resp| view! {
+                <div class="detail-card">
+                    <h3>"Contract storage proof"</h3>
+                    {resp.error.clone().map(|err| view! { <p class="error">{err}</p> })}
+                    <p class="muted">{if resp.verified { "Verified against the contract trie root" } else { "Not verified" }}</p>
+                    <p class="muted">{format!(
+                        "Contract: {} ({} nodes) · Storage: {} ({} nodes)",
+                        format_proof_status(resp.contract_proof.verified, resp.contract_proof.membership, resp.contract_proof.value.as_deref()),
+                        resp.contract_proof.nodes.len(),
+                        format_proof_status(resp.storage_proof.verified, resp.storage_proof.membership, resp.storage_proof.value.as_deref()),
+                        resp.storage_proof.nodes.len(),
+                    )}</p>
+                    {resp.leaf.map(|leaf| view! {
+                        <p class="mono">"Class hash: " {leaf.class_hash}</p>
+                        <p class="mono">"Nonce: " {leaf.nonce}</p>
+                        <p class="mono">"Storage root: " {leaf.storage_root}</p>
+                    })}
+                    {resp.leaf_hash.map(|hash| view! { <p class="mono">"Leaf hash: " {hash}</p> })}
+                </div>
+            })}
             <Show when=move || proof.get().is_some() fallback=|| view! { <div><p class="muted">"No proof loaded."</p></div> }>
                 {move || {
                     let resp = proof.get().unwrap();
-                    let json = StoredValue::new(serde_json::to_string_pretty(&resp).unwrap_or_default());
+                    // Export the standalone document when there is one; it verifies again anywhere.
+                    let json = match &resp.document {
+                        Some(doc) => serde_json::to_string_pretty(doc),
+                        None => serde_json::to_string_pretty(&resp),
+                    };
+                    let json = StoredValue::new(json.unwrap_or_default());
+                    let download = resp.document.is_some().then(|| {
+                        format!("data:application/json;charset=utf-8,{}", urlencoding::encode(&json.get_value()))
+                    });
                     view! {
                         <div>
+                            {resp.error.clone().map(|err| view! { <p class="error">{err}</p> })}
                             <div class="header-row">
-                                <p class="muted">{if resp.verified { "Verified" } else { "Not verified" }}</p>
-                                <button on:click=move |_| set_show_json.set(!show_json.get())>
-                                    {if show_json.get() { "Hide JSON" } else { "Show JSON" }}
-                                </button>
+                                <p class="muted">{format_proof_status(resp.verified, resp.membership, resp.value.as_deref())}</p>
+                                <div class="row">
+                                    <button on:click=move |_| set_show_json.set(!show_json.get())>
+                                        {if show_json.get() { "Hide JSON" } else { "Show JSON" }}
+                                    </button>
+                                    {download.map(|href| view! { <a href=href download="proof.json">"Download"</a> })}
+                                </div>
                             </div>
                             <div class="detail-card">
                                 <h3>"Proof Nodes"</h3>
@@ -883,18 +1721,172 @@ fn ProofView(proof: ReadSignal<Option<ProofResponse>>) -> impl IntoView {
                     }
                 }}
             </Show>
+            <div class="detail-card">
+                <h3>"Verify a proof"</h3>
+                <p class="muted">"Paste or upload proof nodes or an RPC node map; the trie and key come from the sidebar. Leave the value empty to expect non-membership."</p>
+                <textarea class="code-block" rows="8" placeholder="Proof JSON" prop:value=pasted on:input=move |ev| set_pasted.set(event_target_value(&ev))></textarea>
+                <div class="row">
+                    <input type="file" accept=".json,application/json" on:change=on_upload />
+                    <input type="text" placeholder="Root hash" value=root on:input=move |ev| set_root.set(event_target_value(&ev)) />
+                    <input type="text" placeholder="Leaf value (optional)" value=value on:input=move |ev| set_value.set(event_target_value(&ev)) />
+                    <button on:click=move |_| on_verify(pasted.get(), root.get(), value.get())>"Verify"</button>
+                </div>
+                {move || verify.get().map(|resp| view! {
+                    {resp.error.clone().map(|err| view! { <p class="error">{err}</p> })}
+                    <p class="muted">{format_proof_status(resp.verified, resp.membership, resp.value.as_deref())}</p>
+                    <table class="diff-table">
+                        <thead>
+                            <tr>
+                                <th>"#"</th>
+                                <th>"Kind"</th>
+                                <th>"Depth"</th>
+                                <th>"Expected"</th>
+                                <th>"Computed"</th>
+                                <th>"Note"</th>
+                            </tr>
+                        </thead>
+                        <tbody>
+                            {resp.steps.into_iter().map(|step| view!{
+                                <tr class=if step.ok { "" } else { "step-failed" }>
+                                    <td>{step.index}</td>
+                                    <td>{step.kind}</td>
+                                    <td>{step.depth}</td>
+                                    <td class="mono">{step.expected}</td>
+                                    <td class="mono">{step.computed}</td>
+                                    <td>{step.note.unwrap_or_default()}</td>
+                                </tr>
+                            }).collect_view()}
+                        </tbody>
+                    </table>
+                })}
+            </div>
         </section>
     }
 }
 
 #[component]
-fn StatsView(cfs: ReadSignal<Option<CfsResponse>>, on_fetch: impl Fn() + 'static + Copy) -> impl IntoView {
+fn StatsView(
+    cfs: ReadSignal<Option<CfsResponse>>,
+    on_fetch: impl Fn() + 'static + Copy,
+    job: ReadSignal<Option<JobResponse>>,
+    on_check: impl Fn(&'static str) + 'static + Copy,
+    on_pick: impl Fn(String) + 'static + Copy + Send + Sync,
+) -> impl IntoView {
     view! {
         <section>
             <div class="header-row">
                 <h2>"Stats"</h2>
-                <button on:click=move |_| on_fetch()>"Load CFs"</button>
+                <div class="row">
+                    <button on:click=move |_| on_fetch()>"Load CFs"</button>
+                    <button on:click=move |_| on_check("integrity")>"Check integrity"</button>
+                    <button on:click=move |_| on_check("consistency")>"Check flat/trie"</button>
+                    <button on:click=move |_| on_check("stats")>"Trie stats"</button>
+                    <button on:click=move |_| on_check("contracts")>"Find contracts"</button>
+                </div>
             </div>
+            <Show when=move || job.get().is_some() fallback=|| ()>
+                {move || {
+                    let job = job.get().unwrap();
+                    view! {
+                        <div class="detail-card">
+                            <h3>{format!("Check: {}", job.kind)}</h3>
+                            <p class="muted">{format!("job {} · {} · progress {}", job.id, format_job_state(job.state), job.progress)}</p>
+                            {job.error.map(|err| view! { <p class="error">{err}</p> })}
+                            {job.integrity.map(|r| view! {
+                                <p class="mono">"Stored root: " {r.stored_root.unwrap_or_default()}</p>
+                                <p class="mono">"Computed root: " {r.computed_root.unwrap_or_default()}</p>
+                                <p class="muted">{format!("{} nodes, {} leaves, {} mismatches", r.nodes_checked, r.leaves_checked, r.mismatches.len())}</p>
+                                <table class="diff-table">
+                                    <thead>
+                                        <tr>
+                                            <th>"Kind"</th>
+                                            <th>"Path"</th>
+                                            <th>"Expected"</th>
+                                            <th>"Found"</th>
+                                        </tr>
+                                    </thead>
+                                    <tbody>
+                                        {r.mismatches.into_iter().map(|m| view!{
+                                            <tr>
+                                                <td>{m.kind}</td>
+                                                <td class="mono">{format!("{} (len={})", m.path_hex, m.path_len)}</td>
+                                                <td class="mono">{m.expected.unwrap_or_default()}</td>
+                                                <td class="mono">{m.found.unwrap_or_default()}</td>
+                                            </tr>
+                                        }).collect_view()}
+                                    </tbody>
+                                </table>
+                            })}
+                            {job.consistency.map(|r| view! {
+                                <p class="muted">{format!(
+                                    "{} flat entries, {} trie leaves, {} matched, {} missing trie nodes, {} issues",
+                                    r.flat_entries, r.trie_leaves, r.matched, r.missing_nodes, r.issues.len()
+                                )}</p>
+                                <table class="diff-table">
+                                    <thead>
+                                        <tr>
+                                            <th>"Kind"</th>
+                                            <th>"Key"</th>
+                                            <th>"Flat"</th>
+                                            <th>"Trie"</th>
+                                        </tr>
+                                    </thead>
+                                    <tbody>
+                                        {r.issues.into_iter().map(|issue| view!{
+                                            <tr>
+                                                <td>{issue.kind}</td>
+                                                <td class="mono">{issue.key}</td>
+                                                <td class="mono">{issue.flat.unwrap_or_default()}</td>
+                                                <td class="mono">{issue.trie.unwrap_or_default()}</td>
+                                            </tr>
+                                        }).collect_view()}
+                                    </tbody>
+                                </table>
+                            })}
+                            {job.stats.map(|r| view! {
+                                <p class="muted">{format!(
+                                    "{} {}: {} binary, {} edge, {} leaves, {} missing nodes, {} node bytes",
+                                    format_trie(r.trie),
+                                    r.identifier.unwrap_or_default(),
+                                    r.binary_nodes, r.edge_nodes, r.leaves, r.missing_nodes, r.node_bytes
+                                )}</p>
+                                <p class="muted">{format!("Proof length: max {}, avg {:.2}", r.max_proof_len, r.avg_proof_len)}</p>
+                                <HistogramTable title="Leaf depth" buckets=r.leaf_depths />
+                                <HistogramTable title="Edge length (bits)" buckets=r.edge_lengths />
+                            })}
+                            {job.contracts.map(|r| {
+                                let mut ranked = r.contracts;
+                                ranked.sort_by_key(|contract| std::cmp::Reverse(contract.bytes));
+                                view! {
+                                    <p class="muted">{format!("{} contracts with storage; top {} by size", ranked.len(), ranked.len().min(TOP_CONTRACTS))}</p>
+                                    <table class="diff-table">
+                                        <thead>
+                                            <tr>
+                                                <th>"Contract"</th>
+                                                <th>"Leaves"</th>
+                                                <th>"Trie nodes"</th>
+                                                <th>"Bytes"</th>
+                                            </tr>
+                                        </thead>
+                                        <tbody>
+                                            {ranked.into_iter().take(TOP_CONTRACTS).map(|contract| {
+                                                let address = contract.address.clone();
+                                                view!{
+                                                <tr class="clickable" on:click=move |_| on_pick(address.clone())>
+                                                    <td class="mono">{contract.address}</td>
+                                                    <td>{contract.leaves}</td>
+                                                    <td>{contract.trie_nodes}</td>
+                                                    <td>{contract.bytes}</td>
+                                                </tr>
+                                            }}).collect_view()}
+                                        </tbody>
+                                    </table>
+                                }
+                            })}
+                        </div>
+                    }
+                }}
+            </Show>
             <Show when=move || cfs.get().is_some() fallback=|| view! { <div><p class="muted">"No CFs loaded."</p></div> }>
                 {move || {
                     let resp = cfs.get().unwrap();
@@ -911,10 +1903,35 @@ fn StatsView(cfs: ReadSignal<Option<CfsResponse>>, on_fetch: impl Fn() + 'static
     }
 }
 
+#[component]
+fn HistogramTable(title: &'static str, buckets: Vec<HistogramBucket>) -> impl IntoView {
+    view! {
+        <table class="diff-table">
+            <thead>
+                <tr>
+                    <th>{title}</th>
+                    <th>"Count"</th>
+                </tr>
+            </thead>
+            <tbody>
+                {buckets.into_iter().map(|b| view!{
+                    <tr>
+                        <td>{b.value}</td>
+                        <td>{b.count}</td>
+                    </tr>
+                }).collect_view()}
+            </tbody>
+        </table>
+    }
+}
+
 #[derive(Clone, Copy, PartialEq, Eq)]
 enum Tab {
     Tree,
     Path,
+    Leaves,
+    Contract,
+    Class,
     Diff,
     Proof,
     Stats,
@@ -928,6 +1945,201 @@ fn tab_class(active: ReadSignal<Tab>, tab: Tab) -> String {
     }
 }
 
+fn format_key_type(kind: LogKeyType) -> &'static str {
+    match kind {
+        LogKeyType::Trie => "trie",
+        LogKeyType::Flat => "flat",
+        LogKeyType::Unknown => "unknown",
+    }
+}
+
+fn format_job_state(state: JobState) -> &'static str {
+    match state {
+        JobState::Running => "running",
+        JobState::Done => "done",
+        JobState::Failed => "failed",
+    }
+}
+
+fn format_proof_status(verified: bool, membership: Option<ProofMembership>, value: Option<&str>) -> String {
+    match (verified, membership) {
+        (true, Some(ProofMembership::Member)) => {
+            format!("Verified membership, value {}", value.unwrap_or_default())
+        }
+        (true, Some(ProofMembership::NonMember)) => "Verified non-membership: key is absent".to_string(),
+        _ => "Not verified".to_string(),
+    }
+}
+
+/// A pasted proof, ready to post to the endpoint that checks it.
+enum PastedProof {
+    /// Goes to `/api/proof/verify-document`, which also checks the stated node hashes.
+    Document(ProofDocument),
+    /// Goes to `/api/proof/verify`.
+    Nodes(VerifyProofRequest),
+}
+
+/// Reads a pasted proof. A proof document (bare or inside a proof response) brings its
+/// own trie, root, key and value. Bare nodes or an RPC node map are checked against the
+/// sidebar's trie and key and the entered root and value.
+fn pasted_request(text: &str, trie: TrieKind, key: String, root: &str, value: &str) -> Result<PastedProof, String> {
+    let json: serde_json::Value = serde_json::from_str(text).map_err(|err| err.to_string())?;
+    let document = json.get("document").filter(|doc| !doc.is_null()).unwrap_or(&json);
+    if let Ok(doc) = serde_json::from_value::<ProofDocument>(document.clone()) {
+        doc.check()?;
+        return Ok(PastedProof::Document(doc));
+    }
+    if key.is_empty() {
+        return Err("enter the key in the sidebar".to_string());
+    }
+    let list = json.get("nodes").cloned().unwrap_or(json);
+    let (nodes, node_map) = match serde_json::from_value::<Vec<NodeHashToNode>>(list.clone()) {
+        Ok(node_map) => (None, Some(node_map)),
+        Err(_) => {
+            let nodes = serde_json::from_value::<Vec<ProofNodeJson>>(list)
+                .map_err(|_| "expected a proof document, a list of proof nodes or an RPC node map".to_string())?;
+            (Some(nodes), None)
+        }
+    };
+    Ok(PastedProof::Nodes(VerifyProofRequest {
+        trie,
+        root: root.trim().to_string(),
+        key,
+        value: Some(value.trim().to_string()).filter(|v| !v.is_empty()),
+        nodes,
+        node_map,
+    }))
+}
+
+/// CSS class and one-line summary for the state root check.
+fn format_state_root(resp: &StateRootResponse) -> (&'static str, String) {
+    let block = resp.block.map(|b| format!("block {b}")).unwrap_or_else(|| "latest".to_string());
+    match (resp.matches, &resp.error) {
+        (Some(true), _) => ("muted", format!("Matches the header of {block}")),
+        (Some(false), _) => (
+            "error",
+            format!(
+                "Diverged from the header of {block}: bonsai {}, header {}",
+                resp.state_root.clone().unwrap_or_default(),
+                resp.header_state_root.clone().unwrap_or_default()
+            ),
+        ),
+        (None, Some(err)) => ("muted", format!("Not checked: {err}")),
+        (None, None) => ("muted", "Not checked".to_string()),
+    }
+}
+
+/// Rows of the "top contracts by storage size" ranking.
+const TOP_CONTRACTS: usize = 50;
+
+/// Heatmap rows for an activity response: all tries, then each trie's trie and flat
+/// entries, then the listed contracts. Each row has one count per block from `start`.
+fn heatmap_rows(resp: &ActivityResponse) -> Vec<(String, Vec<u64>)> {
+    let mut rows = vec![("All tries".to_string(), resp.blocks.iter().map(|b| b.total).collect())];
+    for (index, trie) in [TrieKind::Contract, TrieKind::Storage, TrieKind::Class].into_iter().enumerate() {
+        rows.push((format!("{} trie", format_trie(trie)), resp.blocks.iter().map(|b| b.tries[index].trie_entries).collect()));
+        rows.push((format!("{} flat", format_trie(trie)), resp.blocks.iter().map(|b| b.tries[index].flat_entries).collect()));
+    }
+    for contract in &resp.contracts {
+        let mut counts = vec![0; resp.blocks.len()];
+        for cell in &contract.blocks {
+            if let Some(count) = cell.block.checked_sub(resp.start).and_then(|offset| counts.get_mut(offset as usize)) {
+                *count = cell.entries;
+            }
+        }
+        rows.push((contract.contract.clone(), counts));
+    }
+    rows
+}
+
+/// Heatmap cell opacity: empty cells stay faint, busy ones scale up to the row's maximum.
+fn heat_opacity(count: u64, max: u64) -> f64 {
+    if count == 0 {
+        0.06
+    } else {
+        0.25 + 0.75 * count as f64 / max as f64
+    }
+}
+
+fn format_declared_block(block: Option<u64>) -> String {
+    block.map(|block| block.to_string()).unwrap_or_else(|| "before the class log".to_string())
+}
+
+fn format_gaps(gaps: &[LogGap]) -> String {
+    if gaps.is_empty() {
+        return "none".to_string();
+    }
+    let shown: Vec<String> = gaps.iter().take(5).map(|g| format!("{}..{}", g.start, g.end)).collect();
+    if gaps.len() > shown.len() {
+        format!("{} (+{} more)", shown.join(", "), gaps.len() - shown.len())
+    } else {
+        shown.join(", ")
+    }
+}
+
+fn format_change_kind(kind: ChangeKind) -> &'static str {
+    match kind {
+        ChangeKind::Insert => "insert",
+        ChangeKind::Update => "update",
+        ChangeKind::Delete => "delete",
+    }
+}
+
+fn format_change_value(value: Option<ChangeValue>) -> String {
+    let Some(value) = value else {
+        return "—".to_string();
+    };
+    if let Some(felt) = value.felt {
+        return felt;
+    }
+    match value.node {
+        Some(node) if node.kind == "edge" => format!(
+            "edge h={} len={} child={}",
+            node.height,
+            node.path_len.unwrap_or_default(),
+            node.child.unwrap_or_else(|| "?".to_string())
+        ),
+        Some(node) => format!(
+            "binary h={} left={} right={}",
+            node.height,
+            node.left.unwrap_or_else(|| "?".to_string()),
+            node.right.unwrap_or_else(|| "?".to_string())
+        ),
+        None => value.raw.unwrap_or_default(),
+    }
+}
+
+/// Leaf keys are shown as felts; partial trie-node paths fall back to their encoding.
+fn display_log_key(key: Option<String>, path_hex: Option<String>, key_len: Option<usize>) -> String {
+    match (key, path_hex) {
+        (Some(key), _) => key,
+        (None, Some(path)) => format!("{path} (len={})", key_len.unwrap_or_default()),
+        (None, None) => "?".to_string(),
+    }
+}
+
+fn row_class(key: &Option<String>) -> &'static str {
+    if key.is_some() { "clickable" } else { "" }
+}
+
+fn select_key(
+    key: Option<String>,
+    contract: Option<String>,
+    on_select: impl Fn(String, Option<String>) + 'static + Copy + Send + Sync,
+) -> impl FnMut(leptos::ev::MouseEvent) + 'static {
+    move |_| {
+        if let Some(key) = key.clone() {
+            on_select(key, contract.clone());
+        }
+    }
+}
+
+/// Key box input the API resolves as a storage variable expression rather than a felt.
+/// Only storage tries take expressions.
+fn is_key_expression(trie: TrieKind, input: &str) -> bool {
+    trie == TrieKind::Storage && is_storage_expression(input)
+}
+
 fn format_trie(kind: TrieKind) -> &'static str {
     match kind {
         TrieKind::Contract => "contract",
@@ -936,6 +2148,13 @@ fn format_trie(kind: TrieKind) -> &'static str {
     }
 }
 
+fn block_param(block: &str) -> String {
+    match block.trim().parse::<u64>() {
+        Ok(block) => format!("&block={block}"),
+        Err(_) => String::new(),
+    }
+}
+
 fn felt_hex_to_bits(hex: &str) -> Vec<bool> {
     let mut bytes = hex_to_bytes(hex).unwrap_or_default();
     if bytes.len() < 32 {
@@ -995,7 +2214,7 @@ fn decode_path_bits(encoded: &[u8]) -> Vec<bool> {
 
 fn encode_path_bits(bits: &[bool]) -> Vec<u8> {
     let len = bits.len();
-    let mut out = Vec::with_capacity(1 + (len + 7) / 8);
+    let mut out = Vec::with_capacity(1 + len.div_ceil(8));
     out.push(len as u8);
     let mut current = 0u8;
     for (i, bit) in bits.iter().enumerate() {
@@ -1008,7 +2227,7 @@ fn encode_path_bits(bits: &[bool]) -> Vec<u8> {
             current = 0;
         }
     }
-    if len % 8 != 0 {
+    if !len.is_multiple_of(8) {
         out.push(current);
     }
     out
@@ -1031,7 +2250,7 @@ fn concat_paths(left_hex: &str, right_hex: &str) -> String {
 
 fn hex_to_bytes(hex: &str) -> Option<Vec<u8>> {
     let s = hex.trim().strip_prefix("0x").unwrap_or(hex.trim());
-    if s.len() % 2 != 0 {
+    if !s.len().is_multiple_of(2) {
         return None;
     }
     let mut out = Vec::with_capacity(s.len() / 2);
@@ -1055,3 +2274,51 @@ fn bytes_to_hex(bytes: &[u8]) -> String {
 pub fn main() {
     leptos::mount::mount_to_body(App);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bonsai_types::{ActivityCell, ActivityCount, BlockActivity, ContractActivity};
+
+    fn block(block: u64, counts: [(u64, u64); 3]) -> BlockActivity {
+        let tries = [TrieKind::Contract, TrieKind::Storage, TrieKind::Class]
+            .into_iter()
+            .zip(counts)
+            .map(|(trie, (trie_entries, flat_entries))| ActivityCount { trie, trie_entries, flat_entries })
+            .collect();
+        BlockActivity { block, total: counts.iter().map(|(trie, flat)| trie + flat).sum(), tries }
+    }
+
+    #[test]
+    fn heatmap_rows_line_up_with_blocks() {
+        let resp = ActivityResponse {
+            start: 10,
+            end: 12,
+            blocks: vec![block(10, [(1, 1), (0, 0), (0, 0)]), block(11, [(0, 0), (0, 0), (0, 0)]), block(12, [(0, 0), (2, 3), (0, 1)])],
+            contracts: vec![ContractActivity {
+                contract: "0x11".to_string(),
+                entries: 5,
+                blocks: vec![ActivityCell { block: 12, entries: 5 }, ActivityCell { block: 13, entries: 9 }],
+            }],
+            contract_count: 1,
+            error: None,
+        };
+        let rows = heatmap_rows(&resp);
+        let labels: Vec<&str> = rows.iter().map(|(label, _)| label.as_str()).collect();
+        assert_eq!(labels[0], "All tries");
+        assert_eq!(labels.len(), 1 + 6 + 1);
+        assert_eq!(rows[0].1, vec![2, 0, 6]);
+        assert_eq!(rows[1], (format!("{} trie", format_trie(TrieKind::Contract)), vec![1, 0, 0]));
+        assert_eq!(rows[4].1, vec![0, 0, 3]);
+        assert_eq!(rows[6].1, vec![0, 0, 1]);
+        // Cells outside the range are dropped rather than shifting the row.
+        assert_eq!(rows[7], ("0x11".to_string(), vec![0, 0, 5]));
+    }
+
+    #[test]
+    fn heat_opacity_scales_to_the_row_maximum() {
+        assert_eq!(heat_opacity(0, 4), 0.06);
+        assert_eq!(heat_opacity(4, 4), 1.0);
+        assert!(heat_opacity(1, 4) < heat_opacity(2, 4));
+    }
+}
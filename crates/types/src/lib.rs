@@ -16,6 +16,20 @@ impl TrieKind {
             TrieKind::Storage => b"",
         }
     }
+
+    pub fn hash_function(self) -> HashFunction {
+        match self {
+            TrieKind::Contract | TrieKind::Storage => HashFunction::Pedersen,
+            TrieKind::Class => HashFunction::Poseidon,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum HashFunction {
+    Pedersen,
+    Poseidon,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -34,51 +48,676 @@ pub struct NodeView {
 pub struct RootResponse {
     pub path_hex: String,
     pub node: Option<NodeView>,
+    pub error: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct NodeResponse {
     pub path_hex: String,
     pub node: Option<NodeView>,
+    pub error: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct LeafResponse {
     pub key: String,
     pub value: Option<String>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct LeafEntry {
+    pub key: String,
+    pub value: Option<String>,
+}
+
+/// A storage key expression and the felt it resolves to.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ResolvedKeyResponse {
+    pub expression: String,
+    pub key: Option<String>,
+    pub error: Option<String>,
+}
+
+/// Whether a storage key given as text is a storage variable expression (`balance`,
+/// `ERC20_balances[0xabc]`) rather than a felt. Expressions start with a letter or `_` and
+/// either index a mapping or are not hex, so unprefixed keys such as `deadbeef` stay felts.
+/// A variable whose name is all hex digits, such as `fee`, reads as a felt too.
+pub fn is_storage_expression(input: &str) -> bool {
+    let input = input.trim();
+    input.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && (input.contains('[') || !input.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+/// One page of leaves in ascending key order. Pass `next_cursor` back as `cursor`
+/// to fetch the following page; it is `None` on the last page.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct LeavesResponse {
+    pub leaves: Vec<LeafEntry>,
+    pub next_cursor: Option<String>,
+    pub error: Option<String>,
+}
+
+/// Whether a trie-log record belongs to the trie CF (node) or the flat CF (leaf value).
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LogKeyType {
+    Trie,
+    Flat,
+    Unknown,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct DiffEntry {
     pub block: u64,
-    pub key_type: String,
+    pub key_type: LogKeyType,
     pub change_type: String,
     pub key_len: Option<usize>,
+    /// Encoded node path, usable with `/api/trie/node`.
+    pub path_hex: Option<String>,
+    /// Full leaf key as a felt; only set for 251-bit paths.
+    pub key: Option<String>,
+    /// Contract address owning the storage trie, for storage-log records.
+    pub contract: Option<String>,
     pub value: String,
 }
 
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeKind {
+    Insert,
+    Update,
+    Delete,
+}
+
+/// A logged value: `felt` for flat records, `node` for trie records, `raw` hex if neither decodes.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ChangeValue {
+    pub felt: Option<String>,
+    pub node: Option<NodeView>,
+    pub raw: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct DiffChange {
+    pub block: u64,
+    pub key_type: LogKeyType,
+    pub kind: ChangeKind,
+    pub key_len: Option<usize>,
+    pub path_hex: Option<String>,
+    pub key: Option<String>,
+    pub contract: Option<String>,
+    pub before: Option<ChangeValue>,
+    pub after: Option<ChangeValue>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct DiffResponse {
     pub entries: Vec<DiffEntry>,
+    pub changes: Vec<DiffChange>,
+    pub error: Option<String>,
+}
+
+/// Inclusive run of blocks with no trie-log entries.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct LogGap {
+    pub start: u64,
+    pub end: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct LogRetentionView {
+    pub trie: TrieKind,
+    pub log_cf: String,
+    pub earliest: Option<u64>,
+    pub latest: Option<u64>,
+    pub logged_blocks: u64,
+    pub gaps: Vec<LogGap>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RetentionResponse {
+    pub logs: Vec<LogRetentionView>,
+}
+
+/// Log entries one trie logged in one block, split by key type.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ActivityCount {
+    pub trie: TrieKind,
+    pub trie_entries: u64,
+    pub flat_entries: u64,
+}
+
+/// Log entries of one block. `tries` always lists the contract, storage and class tries.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct BlockActivity {
+    pub block: u64,
+    pub total: u64,
+    pub tries: Vec<ActivityCount>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ActivityCell {
+    pub block: u64,
+    pub entries: u64,
+}
+
+/// Storage-log entries of one contract; `blocks` only lists blocks where it has any.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ContractActivity {
+    pub contract: String,
+    pub entries: u64,
+    pub blocks: Vec<ActivityCell>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ActivityResponse {
+    pub start: u64,
+    pub end: u64,
+    /// One entry per block of `start..=end`, including blocks with no entries.
+    pub blocks: Vec<BlockActivity>,
+    /// The busiest storage contracts, most entries first.
+    pub contracts: Vec<ContractActivity>,
+    /// Storage contracts with entries in the range, including those cut from `contracts`.
+    pub contract_count: u64,
+    pub error: Option<String>,
+}
+
+/// Net change of one key across a block range; `None` means the key did not exist.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RangeDiffEntry {
+    pub first_block: u64,
+    pub last_block: u64,
+    pub key_type: LogKeyType,
+    pub key_len: Option<usize>,
+    pub path_hex: Option<String>,
+    pub key: Option<String>,
+    pub contract: Option<String>,
+    pub old: Option<String>,
+    pub new: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RangeDiffResponse {
+    pub start: u64,
+    pub end: u64,
+    pub entries: Vec<RangeDiffEntry>,
+    pub error: Option<String>,
+}
+
+/// Query of `GET /api/bisect`: find the first block in `start..=end` where `key` holds
+/// `value`, or, given `root` instead, where the trie root differs from it.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct BisectRequest {
+    pub trie: TrieKind,
+    pub identifier: Option<String>,
+    pub start: u64,
+    pub end: u64,
+    pub key: Option<String>,
+    pub value: Option<String>,
+    pub root: Option<String>,
+}
+
+/// One block the bisection read, with what it found there.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct BisectStep {
+    pub block: u64,
+    pub holds: bool,
+    /// Leaf value or root hash at `block`.
+    pub observed: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct BisectResponse {
+    /// First block where the condition holds; `None` if it does not hold at `end`.
+    pub block: Option<u64>,
+    pub condition: String,
+    pub steps: Vec<BisectStep>,
+    pub error: Option<String>,
+}
+
+/// One block in which a leaf's value changed.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct HistoryEntry {
+    pub block: u64,
+    pub kind: ChangeKind,
+    pub before: Option<String>,
+    pub after: Option<String>,
+}
+
+/// Value changes of one leaf across the retained trie logs, oldest first.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct HistoryResponse {
+    pub key: String,
+    pub entries: Vec<HistoryEntry>,
+    /// First block the log still covers; changes before it were pruned.
+    pub earliest_logged: Option<u64>,
+    pub error: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct ProofNodeJson {
     pub kind: String,
+    /// The node's own hash.
+    pub hash: Option<String>,
     pub left: Option<String>,
     pub right: Option<String>,
     pub child: Option<String>,
     pub path_len: Option<usize>,
+    /// Edge path bits as a felt, `path_len` bits long.
+    pub path: Option<String>,
 }
 
+/// What a proof shows about its key.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ProofMembership {
+    /// The path reaches the leaf.
+    Member,
+    /// The path leaves the key at a diverging edge, or the trie is empty.
+    NonMember,
+}
+
+/// `verified` means the hash chain holds and the proven membership agrees with the
+/// stored leaf value; `error` explains why when it does not.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct ProofResponse {
     pub verified: bool,
+    pub membership: Option<ProofMembership>,
+    /// Leaf value the proof commits to, for membership proofs.
+    pub value: Option<String>,
+    pub nodes: Vec<ProofNodeJson>,
+    /// The proof as a standalone document, when it verified.
+    pub document: Option<ProofDocument>,
+    pub error: Option<String>,
+}
+
+/// `ProofDocument::version` this build writes and reads.
+pub const PROOF_DOCUMENT_VERSION: u32 = 1;
+
+/// A proof with everything needed to verify it again away from the database.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ProofDocument {
+    pub version: u32,
+    pub trie: TrieKind,
+    pub hash_function: HashFunction,
+    /// Contract address, for storage tries.
+    pub identifier: Option<String>,
+    /// Block the proof was built at; `None` for the latest state.
+    pub block: Option<u64>,
+    pub root: String,
+    pub key: String,
+    /// Leaf value, for membership proofs.
+    pub value: Option<String>,
+    pub membership: ProofMembership,
+    /// Root first, each with its hash and, for edges, its path bits.
     pub nodes: Vec<ProofNodeJson>,
 }
 
+impl ProofDocument {
+    /// Rejects documents this build cannot read, whose hash function does not fit the
+    /// trie, or whose membership claim disagrees with the value they carry.
+    pub fn check(&self) -> Result<(), String> {
+        if self.version != PROOF_DOCUMENT_VERSION {
+            return Err(format!("unsupported proof document version {} (expected {PROOF_DOCUMENT_VERSION})", self.version));
+        }
+        if self.hash_function != self.trie.hash_function() {
+            return Err(format!("{:?} tries hash with {:?}, not {:?}", self.trie, self.trie.hash_function(), self.hash_function));
+        }
+        match (self.membership, &self.value) {
+            (ProofMembership::Member, None) => Err("membership proof without a value".to_string()),
+            (ProofMembership::NonMember, Some(value)) => Err(format!("non-membership proof carries value {value}")),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl From<&ProofDocument> for VerifyProofRequest {
+    fn from(doc: &ProofDocument) -> Self {
+        Self {
+            trie: doc.trie,
+            root: doc.root.clone(),
+            key: doc.key.clone(),
+            value: doc.value.clone(),
+            nodes: Some(doc.nodes.clone()),
+            node_map: None,
+        }
+    }
+}
+
+/// One key's share of a batch proof.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct KeyProof {
+    pub key: String,
+    /// Indices into `BatchProofResponse::nodes`, root first.
+    pub nodes: Vec<usize>,
+    pub verified: bool,
+    pub membership: Option<ProofMembership>,
+    pub value: Option<String>,
+    pub error: Option<String>,
+}
+
+/// Proofs for several keys against `root`, with nodes shared between keys listed once.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct BatchProofResponse {
+    pub root: Option<String>,
+    pub nodes: Vec<ProofNodeJson>,
+    pub keys: Vec<KeyProof>,
+    pub error: Option<String>,
+}
+
+/// Starknet JSON-RPC `MERKLE_NODE`: an edge carries its path bits as a felt plus their count.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(untagged)]
+pub enum MerkleNode {
+    Binary { left: String, right: String },
+    Edge { path: String, length: usize, child: String },
+}
+
+/// One entry of a `NODE_HASH_TO_NODE_MAPPING`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct NodeHashToNode {
+    pub node_hash: String,
+    pub node: MerkleNode,
+}
+
+/// A proof from elsewhere to check against `root`. Exactly one of `nodes` (our format,
+/// root first) or `node_map` (the RPC node mapping) is set. `value` is the leaf value
+/// the proof should show; leave it out to expect a non-membership proof.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct VerifyProofRequest {
+    pub trie: TrieKind,
+    pub root: String,
+    pub key: String,
+    pub value: Option<String>,
+    pub nodes: Option<Vec<ProofNodeJson>>,
+    pub node_map: Option<Vec<NodeHashToNode>>,
+}
+
+/// One checked proof node: the hash its parent commits to against the node's own hash.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct VerifyStepView {
+    pub index: usize,
+    pub kind: String,
+    /// Key bits consumed above the node.
+    pub depth: usize,
+    pub expected: String,
+    pub computed: String,
+    pub ok: bool,
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct VerifyProofResponse {
+    pub verified: bool,
+    pub membership: Option<ProofMembership>,
+    pub value: Option<String>,
+    pub steps: Vec<VerifyStepView>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ContractLeafData {
+    pub nonce: String,
+    pub class_hash: String,
+    pub storage_root: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ContractsProof {
+    pub nodes: Vec<NodeHashToNode>,
+    /// One entry per requested contract address, in request order.
+    pub contract_leaves_data: Vec<ContractLeafData>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct GlobalRoots {
+    pub contracts_tree_root: String,
+    pub classes_tree_root: String,
+    pub block_hash: String,
+}
+
+/// A contract that has a storage trie, with how much it holds.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct StorageContractView {
+    pub address: String,
+    pub leaves: u64,
+    pub trie_nodes: u64,
+    /// Uncompressed key and value bytes in the storage trie and flat CFs.
+    pub bytes: u64,
+}
+
+/// Every contract with storage, sorted by address.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct StorageContractsResponse {
+    pub contracts: Vec<StorageContractView>,
+    pub error: Option<String>,
+}
+
+/// Body of `POST /api/storage/labels`: a contract and its class or ABI JSON, as text.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct StorageLabelsRequest {
+    pub contract: String,
+    pub layout: String,
+}
+
+/// A storage variable derived from the layout, with its base address.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct StorageVariableView {
+    pub name: String,
+    /// Mapping keys; 0 for a plain variable.
+    pub keys: usize,
+    pub value_type: String,
+    pub base: String,
+}
+
+/// A storage leaf matched to a variable, e.g. `ERC20_balances[0xabc].low`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct StorageLabel {
+    pub key: String,
+    pub label: String,
+    pub value: Option<String>,
+    /// `value` read as the variable's type.
+    pub decoded: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct StorageLabelsResponse {
+    pub variables: Vec<StorageVariableView>,
+    pub labels: Vec<StorageLabel>,
+    /// Storage leaves scanned; those without a label matched no variable.
+    pub leaves: usize,
+    /// Felts from the trie logs tried as mapping keys.
+    pub mapping_keys: usize,
+    pub error: Option<String>,
+}
+
+/// A contract's leaf in the contract trie next to the state it should commit to.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ContractResponse {
+    pub address: String,
+    /// Leaf value proven against the contract trie root; `None` if the contract is absent.
+    pub leaf: Option<String>,
+    pub state: Option<ContractLeafData>,
+    /// `Pedersen(Pedersen(Pedersen(class_hash, storage_root), nonce), 0)` over `state`.
+    pub computed_leaf: Option<String>,
+    /// `leaf` equals `computed_leaf`.
+    pub matches: bool,
+    pub error: Option<String>,
+}
+
+/// A class trie leaf next to the compiled class hash it should commit to.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ClassResponse {
+    pub class_hash: String,
+    /// Leaf value in the class trie; `None` if the class was never declared.
+    pub leaf: Option<String>,
+    pub compiled_class_hash: Option<String>,
+    /// `Poseidon("CONTRACT_CLASS_LEAF_V0", compiled_class_hash)`.
+    pub computed_leaf: Option<String>,
+    /// `leaf` equals `computed_leaf`.
+    pub matches: bool,
+    /// First block whose class trie log inserts this leaf; `None` if that predates the logs.
+    pub declared_block: Option<u64>,
+    pub error: Option<String>,
+}
+
+/// One page of class trie leaves, each checked like [`ClassResponse`].
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ClassesResponse {
+    pub classes: Vec<ClassResponse>,
+    pub next_cursor: Option<String>,
+    pub error: Option<String>,
+}
+
+/// A storage slot proven against the contract trie root: the contract's leaf in the
+/// contract trie, that leaf's preimage, and the slot in the contract's storage trie.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ContractStorageProofResponse {
+    /// Both proofs verify, `leaf` hashes to the proven contract leaf, and the storage
+    /// proof's root is `leaf.storage_root`.
+    pub verified: bool,
+    pub contract_proof: ProofResponse,
+    pub leaf: Option<ContractLeafData>,
+    /// `Pedersen(Pedersen(Pedersen(class_hash, storage_root), nonce), 0)` over `leaf`.
+    pub leaf_hash: Option<String>,
+    pub storage_proof: ProofResponse,
+    pub error: Option<String>,
+}
+
+/// Global state root computed from the contract and class trie roots, compared with the
+/// state root committed in Madara's block header.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct StateRootResponse {
+    /// Block whose header was compared; the latest logged block when none was asked for.
+    pub block: Option<u64>,
+    pub contract_root: Option<String>,
+    pub class_root: Option<String>,
+    pub state_root: Option<String>,
+    pub header_state_root: Option<String>,
+    /// `None` when there was no header to compare against.
+    pub matches: Option<bool>,
+    pub error: Option<String>,
+}
+
+/// `starknet_getStorageProof` request entry: storage keys of one contract.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ContractStorageKeys {
+    pub contract_address: String,
+    pub storage_keys: Vec<String>,
+}
+
+/// `starknet_getStorageProof` result.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct StorageProofResult {
+    pub classes_proof: Vec<NodeHashToNode>,
+    pub contracts_proof: ContractsProof,
+    /// One node mapping per `contracts_storage_keys` entry, in request order.
+    pub contracts_storage_proofs: Vec<Vec<NodeHashToNode>>,
+    pub global_roots: GlobalRoots,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CfsResponse {
     pub total: usize,
     pub names: Vec<String>,
 }
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct FollowResponse {
+    pub mode: String,
+    pub secondary_path: Option<String>,
+    pub interval_secs: Option<u64>,
+    pub catch_ups: u64,
+    pub last_catch_up_ms: Option<u64>,
+    pub last_error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct IntegrityMismatch {
+    pub path_hex: String,
+    pub path_len: usize,
+    /// One of "node_hash", "child_hash", "missing_node", "missing_leaf".
+    pub kind: String,
+    pub expected: Option<String>,
+    pub found: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct IntegrityResponse {
+    pub trie: TrieKind,
+    pub nodes_checked: u64,
+    pub leaves_checked: u64,
+    pub stored_root: Option<String>,
+    pub computed_root: Option<String>,
+    pub mismatches: Vec<IntegrityMismatch>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ConsistencyIssue {
+    pub key: String,
+    /// One of "flat_only", "trie_only", "value_mismatch".
+    pub kind: String,
+    pub flat: Option<String>,
+    pub trie: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ConsistencyResponse {
+    pub trie: TrieKind,
+    pub flat_entries: u64,
+    pub trie_leaves: u64,
+    pub matched: u64,
+    pub missing_nodes: u64,
+    pub issues: Vec<ConsistencyIssue>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct HistogramBucket {
+    pub value: u64,
+    pub count: u64,
+}
+
+/// Shape of one trie (one contract, for storage tries).
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TrieStatsResponse {
+    pub trie: TrieKind,
+    pub identifier: Option<String>,
+    pub binary_nodes: u64,
+    pub edge_nodes: u64,
+    pub leaves: u64,
+    pub missing_nodes: u64,
+    pub node_bytes: u64,
+    /// Proof lengths are counted in nodes from the root down to the leaf.
+    pub max_proof_len: u64,
+    pub avg_proof_len: f64,
+    /// Leaf depth (proof length) to number of leaves.
+    pub leaf_depths: Vec<HistogramBucket>,
+    /// Edge path length in bits to number of edge nodes.
+    pub edge_lengths: Vec<HistogramBucket>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum JobState {
+    Running,
+    Done,
+    Failed,
+}
+
+/// A long-running check started through `/api/jobs/*`; poll `/api/jobs/status?id=N` until it leaves `running`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct JobResponse {
+    pub id: u64,
+    pub kind: String,
+    pub state: JobState,
+    pub progress: u64,
+    pub error: Option<String>,
+    pub integrity: Option<IntegrityResponse>,
+    pub consistency: Option<ConsistencyResponse>,
+    pub stats: Option<TrieStatsResponse>,
+    pub contracts: Option<StorageContractsResponse>,
+}
@@ -1,17 +1,27 @@
 use eframe::egui;
 use egui::CollapsingHeader;
-use serde::Serialize;
 use std::path::PathBuf;
-use crate::bonsai::diff_reader::read_block_log;
+use std::sync::atomic::AtomicU64;
+use crate::bonsai::consistency::{check_flat_consistency, ConsistencyReport};
+use crate::bonsai::discovery::{discover_storage_contracts, StorageContract};
+use crate::bonsai::diff_reader::{aggregate_range, check_block_logged, earliest_logged_block, key_history, latest_logged_block, pair_changes, read_block_log, LogValue, TrieChange};
+use crate::bonsai::historical::historical_reader;
+use crate::bonsai::integrity::{check_integrity, IntegrityReport};
 use crate::bonsai::node::Node;
-use crate::bonsai::path::{felt_to_path, PathBits};
-use crate::bonsai::proof::{build_proof, verify_proof, ProofNode};
+use crate::bonsai::path::{felt_to_path, path_to_felt, PathBits, LEAF_PATH_LEN};
+use crate::bonsai::proof::{build_proof, proof_document, verify_proof, Membership, ProofNode};
+use crate::bonsai::stats::{collect_stats, TrieStats};
 use crate::bonsai::trie_reader::{TrieReader, TrieSpec};
 use crate::db::cf_map;
 use crate::db::RocksDb;
-use crate::model::TrieKind;
+use crate::model::{is_storage_expression, ChangeKind, ProofDocument, TrieKind};
 use crate::util::hex::{bytes_to_hex, decode_felt_scale, format_felt_short, parse_felt_hex};
+use crate::util::storage_key::parse_trie_key;
 use crate::Args;
+use starknet_types_core::felt::Felt;
+
+/// How many contracts the "largest storage" ranking lists.
+const TOP_CONTRACTS: usize = 50;
 
 pub struct BonsaiApp {
     args: Args,
@@ -24,14 +34,22 @@ pub struct BonsaiApp {
 
     tree_trie: TrieKind,
     storage_identifier_input: String,
+    block_input: String,
 
     key_input: String,
     path_trace_status: String,
+    key_history: Option<Vec<TrieChange>>,
     proof_status: String,
     proof_output_path: String,
     last_proof: Vec<ProofNode>,
 
+    integrity_report: Option<Result<IntegrityReport, String>>,
+    consistency_report: Option<Result<ConsistencyReport, String>>,
+    trie_stats: Option<Result<TrieStats, String>>,
+    storage_contracts: Option<Result<Vec<StorageContract>, String>>,
+
     diff_block_input: String,
+    diff_end_input: String,
     diff_trie: TrieKind,
 
     screenshot_dir: String,
@@ -51,6 +69,12 @@ enum Tab {
 impl BonsaiApp {
     pub fn new(_cc: &eframe::CreationContext<'_>, args: Args) -> Self {
         let db_path_input = args.db_path.clone().unwrap_or_default();
+        let block_input = args.block.map(|b| b.to_string()).unwrap_or_default();
+        let (diff_block_input, diff_end_input) = match args.diff.as_deref() {
+            Some([start, end]) => (start.to_string(), end.to_string()),
+            _ => (String::new(), String::new()),
+        };
+        let active_tab = if args.diff.is_some() { Tab::Diff } else { Tab::Tree };
         let status = if db_path_input.is_empty() {
             "DB path not set".to_string()
         } else {
@@ -59,18 +83,25 @@ impl BonsaiApp {
         Self {
             args,
             status,
-            active_tab: Tab::Tree,
+            active_tab,
             db_path_input,
             db: None,
             db_error: None,
             tree_trie: TrieKind::Contract,
             storage_identifier_input: String::new(),
+            block_input,
             key_input: String::new(),
             path_trace_status: String::new(),
+            key_history: None,
             proof_status: String::new(),
             proof_output_path: "assets/screenshots/proof.json".to_string(),
             last_proof: Vec::new(),
-            diff_block_input: String::new(),
+            integrity_report: None,
+            consistency_report: None,
+            trie_stats: None,
+            storage_contracts: None,
+            diff_block_input,
+            diff_end_input,
             diff_trie: TrieKind::Contract,
             screenshot_dir: "assets/screenshots".to_string(),
             pending_screenshot: false,
@@ -132,6 +163,16 @@ impl BonsaiApp {
         })
     }
 
+    /// Reader for the latest state, or for the block in the Block box rewound via trie logs.
+    fn open_reader(&mut self, db: &RocksDb, spec: TrieSpec) -> Result<TrieReader, String> {
+        let block = self.block_input.trim();
+        if block.is_empty() {
+            return Ok(TrieReader::new(db.clone(), spec));
+        }
+        let block: u64 = block.parse().map_err(|_| format!("invalid block: {block}"))?;
+        historical_reader(db.clone(), spec, block).map_err(|e| e.to_string())
+    }
+
     fn render_status(&mut self, ui: &mut egui::Ui) {
         ui.horizontal(|ui| {
             ui.label("DB Path:");
@@ -140,6 +181,8 @@ impl BonsaiApp {
                 self.db = None;
                 self.ensure_db();
             }
+            ui.label("Block:");
+            ui.text_edit_singleline(&mut self.block_input);
         });
 
         if let Some(err) = &self.db_error {
@@ -152,7 +195,7 @@ impl BonsaiApp {
         self.render_status(ui);
         ui.separator();
 
-        let Some(db) = &self.db else {
+        let Some(db) = self.db.clone() else {
             ui.label("DB not opened.");
             return;
         };
@@ -169,6 +212,208 @@ impl BonsaiApp {
                 }
             }
         });
+
+        ui.separator();
+        ui.heading("Integrity");
+        ui.horizontal(|ui| {
+            ui.label(format!("Trie: {:?} (from the Tree tab)", self.tree_trie));
+            if ui.button("Recompute all hashes").clicked() {
+                self.integrity_report = Some(self.run_integrity_check(&db));
+            }
+            if ui.button("Check flat vs trie").clicked() {
+                self.consistency_report = Some(self.run_consistency_check(&db));
+            }
+            if ui.button("Trie shape").clicked() {
+                self.trie_stats = Some(self.run_trie_stats(&db));
+            }
+            if ui.button("Discover contracts").clicked() {
+                self.storage_contracts =
+                    Some(discover_storage_contracts(&db, &AtomicU64::new(0)).map_err(|err| err.to_string()));
+            }
+        });
+        match &self.integrity_report {
+            None => {}
+            Some(Err(err)) => {
+                ui.colored_label(egui::Color32::RED, err);
+            }
+            Some(Ok(report)) => {
+                ui.label(format!(
+                    "{} nodes, {} leaves; stored root {}, computed root {}",
+                    report.nodes_checked,
+                    report.leaves_checked,
+                    report.stored_root.map(|h| format_felt_short(&h)).unwrap_or_else(|| "-".to_string()),
+                    report.computed_root.map(|h| format_felt_short(&h)).unwrap_or_else(|| "-".to_string()),
+                ));
+                if report.mismatches.is_empty() {
+                    ui.label("No mismatches.");
+                }
+                ui.push_id("integrity", |ui| {
+                    egui::ScrollArea::vertical().max_height(240.0).show(ui, |ui| {
+                        for m in &report.mismatches {
+                            ui.colored_label(
+                                egui::Color32::RED,
+                                format!(
+                                    "{:?} at {} (len {}): expected {}, found {}",
+                                    m.kind,
+                                    bytes_to_hex(&m.path.to_bytes()),
+                                    m.path.len(),
+                                    m.expected.map(|h| format!("{h:#x}")).unwrap_or_else(|| "-".to_string()),
+                                    m.found.map(|h| format!("{h:#x}")).unwrap_or_else(|| "-".to_string()),
+                                ),
+                            );
+                        }
+                    });
+                });
+            }
+        }
+        self.render_consistency_report(ui);
+        self.render_trie_stats(ui);
+        self.render_storage_contracts(ui);
+    }
+
+    fn render_storage_contracts(&mut self, ui: &mut egui::Ui) {
+        let mut picked = None;
+        match &self.storage_contracts {
+            None => {}
+            Some(Err(err)) => {
+                ui.colored_label(egui::Color32::RED, err);
+            }
+            Some(Ok(contracts)) => {
+                ui.label(format!("{} contracts with storage; largest first:", contracts.len()));
+                let mut ranked: Vec<&StorageContract> = contracts.iter().collect();
+                ranked.sort_by_key(|contract| std::cmp::Reverse(contract.bytes));
+                ui.push_id("storage_contracts", |ui| {
+                    egui::ScrollArea::vertical().max_height(240.0).show(ui, |ui| {
+                        for contract in ranked.into_iter().take(TOP_CONTRACTS) {
+                            let label = format!(
+                                "{}  {} leaves, {} nodes, {} bytes",
+                                format_felt_short(&contract.address),
+                                contract.leaves,
+                                contract.trie_nodes,
+                                contract.bytes
+                            );
+                            if ui.link(label).clicked() {
+                                picked = Some(contract.address);
+                            }
+                        }
+                    });
+                });
+            }
+        }
+        if let Some(address) = picked {
+            self.storage_identifier_input = format!("{address:#x}");
+            self.tree_trie = TrieKind::Storage;
+        }
+    }
+
+    /// Address input for the storage trie, with a picker once contracts have been discovered.
+    fn render_storage_identifier(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Contract address:");
+            ui.text_edit_singleline(&mut self.storage_identifier_input);
+            let Some(Ok(contracts)) = &self.storage_contracts else {
+                return;
+            };
+            let mut picked = None;
+            egui::ComboBox::from_label("Discovered")
+                .selected_text("Pick")
+                .show_ui(ui, |ui| {
+                    for contract in contracts {
+                        if ui.selectable_label(false, format!("{:#x}", contract.address)).clicked() {
+                            picked = Some(contract.address);
+                        }
+                    }
+                });
+            if let Some(address) = picked {
+                self.storage_identifier_input = format!("{address:#x}");
+            }
+        });
+    }
+
+    fn render_trie_stats(&self, ui: &mut egui::Ui) {
+        match &self.trie_stats {
+            None => {}
+            Some(Err(err)) => {
+                ui.colored_label(egui::Color32::RED, err);
+            }
+            Some(Ok(stats)) => {
+                ui.label(format!(
+                    "{} binary, {} edge, {} leaves, {} missing nodes, {} node bytes",
+                    stats.binary_nodes, stats.edge_nodes, stats.leaves, stats.missing_nodes, stats.node_bytes
+                ));
+                ui.label(format!("Proof length: max {}, avg {:.2}", stats.max_proof_len(), stats.avg_proof_len()));
+                ui.push_id("trie_stats", |ui| {
+                    egui::ScrollArea::vertical().max_height(240.0).show(ui, |ui| {
+                        ui.label("Leaf depth:");
+                        for (depth, count) in &stats.leaf_depths {
+                            ui.monospace(format!("{depth:>4}  {count}"));
+                        }
+                        ui.label("Edge length (bits):");
+                        for (len, count) in &stats.edge_lengths {
+                            ui.monospace(format!("{len:>4}  {count}"));
+                        }
+                    });
+                });
+            }
+        }
+    }
+
+    fn render_consistency_report(&self, ui: &mut egui::Ui) {
+        match &self.consistency_report {
+            None => {}
+            Some(Err(err)) => {
+                ui.colored_label(egui::Color32::RED, err);
+            }
+            Some(Ok(report)) => {
+                ui.label(format!(
+                    "{} flat entries, {} trie leaves, {} matched, {} missing trie nodes",
+                    report.flat_entries, report.trie_leaves, report.matched, report.missing_nodes
+                ));
+                if report.issues.is_empty() {
+                    ui.label("Flat CF and trie agree.");
+                }
+                ui.push_id("consistency", |ui| {
+                    egui::ScrollArea::vertical().max_height(240.0).show(ui, |ui| {
+                        for issue in &report.issues {
+                            ui.colored_label(
+                                egui::Color32::RED,
+                                format!(
+                                    "{:?} {:#x}: flat {}, trie {}",
+                                    issue.kind,
+                                    issue.key,
+                                    issue.flat.map(|v| format!("{v:#x}")).unwrap_or_else(|| "-".to_string()),
+                                    issue.trie.map(|v| format!("{v:#x}")).unwrap_or_else(|| "-".to_string()),
+                                ),
+                            );
+                        }
+                    });
+                });
+            }
+        }
+    }
+
+    fn run_consistency_check(&mut self, db: &RocksDb) -> Result<ConsistencyReport, String> {
+        let spec = self
+            .build_spec(self.tree_trie)
+            .ok_or_else(|| "Provide a valid contract address for storage trie.".to_string())?;
+        let reader = self.open_reader(db, spec)?;
+        Ok(check_flat_consistency(&reader, &AtomicU64::new(0)))
+    }
+
+    fn run_trie_stats(&mut self, db: &RocksDb) -> Result<TrieStats, String> {
+        let spec = self
+            .build_spec(self.tree_trie)
+            .ok_or_else(|| "Provide a valid contract address for storage trie.".to_string())?;
+        let reader = self.open_reader(db, spec)?;
+        Ok(collect_stats(&reader, &AtomicU64::new(0)))
+    }
+
+    fn run_integrity_check(&mut self, db: &RocksDb) -> Result<IntegrityReport, String> {
+        let spec = self
+            .build_spec(self.tree_trie)
+            .ok_or_else(|| "Provide a valid contract address for storage trie.".to_string())?;
+        let reader = self.open_reader(db, spec)?;
+        Ok(check_integrity(&reader, self.tree_trie, &AtomicU64::new(0)))
     }
 
     fn render_screenshot_controls(&mut self, ui: &mut egui::Ui, ctx: &egui::Context) {
@@ -192,7 +437,7 @@ impl BonsaiApp {
         self.render_status(ui);
         ui.separator();
 
-        let Some(db) = &self.db else {
+        let Some(db) = self.db.clone() else {
             ui.label("DB not opened.");
             return;
         };
@@ -205,10 +450,7 @@ impl BonsaiApp {
         });
 
         if self.tree_trie == TrieKind::Storage {
-            ui.horizontal(|ui| {
-                ui.label("Contract address:");
-                ui.text_edit_singleline(&mut self.storage_identifier_input);
-            });
+            self.render_storage_identifier(ui);
         }
 
         let Some(spec) = self.build_spec(self.tree_trie) else {
@@ -216,7 +458,13 @@ impl BonsaiApp {
             return;
         };
 
-        let mut reader = TrieReader::new(db.clone(), spec);
+        let mut reader = match self.open_reader(&db, spec) {
+            Ok(reader) => reader,
+            Err(err) => {
+                ui.colored_label(egui::Color32::RED, err);
+                return;
+            }
+        };
         let Some(root) = reader.load_root_node() else {
             ui.label("Empty trie or root missing.");
             return;
@@ -235,7 +483,7 @@ impl BonsaiApp {
         self.render_status(ui);
         ui.separator();
 
-        let Some(db) = &self.db else {
+        let Some(db) = self.db.clone() else {
             ui.label("DB not opened.");
             return;
         };
@@ -247,17 +495,16 @@ impl BonsaiApp {
             ui.selectable_value(&mut self.tree_trie, TrieKind::Class, "Class");
         });
         if self.tree_trie == TrieKind::Storage {
-            ui.horizontal(|ui| {
-                ui.label("Contract address:");
-                ui.text_edit_singleline(&mut self.storage_identifier_input);
-            });
+            self.render_storage_identifier(ui);
         }
 
+        let mut trace_clicked = false;
         ui.horizontal(|ui| {
-            ui.label("Key (hex felt):");
+            ui.label("Key (felt or name[key]):");
             ui.text_edit_singleline(&mut self.key_input);
             if ui.button("Trace").clicked() {
                 self.path_trace_status.clear();
+                trace_clicked = true;
             }
         });
 
@@ -265,17 +512,32 @@ impl BonsaiApp {
             ui.label("Provide a valid contract address for storage trie.");
             return;
         };
-        let mut reader = TrieReader::new(db.clone(), spec);
+        let log_cf = spec.log_cf.clone();
+        let mut trie_key = spec.identifier.clone();
+        let mut reader = match self.open_reader(&db, spec) {
+            Ok(reader) => reader,
+            Err(err) => {
+                ui.colored_label(egui::Color32::RED, err);
+                return;
+            }
+        };
 
-        let felt = match parse_felt_hex(&self.key_input) {
+        let felt = match parse_trie_key(self.tree_trie, &self.key_input) {
             Ok(felt) => felt,
             Err(err) => {
                 ui.colored_label(egui::Color32::RED, err);
                 return;
             }
         };
+        if self.tree_trie == TrieKind::Storage && is_storage_expression(&self.key_input) {
+            ui.monospace(format!("{} = {felt:#x}", self.key_input.trim()));
+        }
 
         let key_path = felt_to_path(&felt);
+        if trace_clicked {
+            trie_key.extend_from_slice(&key_path.to_bytes());
+            self.key_history = Some(key_history(&db, &log_cf, &trie_key));
+        }
         let mut path = PathBits::default();
         let mut steps: Vec<String> = Vec::new();
 
@@ -349,6 +611,25 @@ impl BonsaiApp {
             ui.separator();
             ui.label(&self.path_trace_status);
         }
+        if let Some(history) = &self.key_history {
+            ui.separator();
+            ui.heading("Value history");
+            if history.is_empty() {
+                ui.label("No changes in the trie log.");
+            }
+            ui.push_id("key_history", |ui| {
+                egui::ScrollArea::vertical().max_height(240.0).show(ui, |ui| {
+                    for change in history {
+                        ui.monospace(format!(
+                            "block {}: {} -> {}",
+                            change.block,
+                            log_value_display(change.before.as_ref()),
+                            log_value_display(change.after.as_ref())
+                        ));
+                    }
+                });
+            });
+        }
         let ctx = ui.ctx().clone();
         self.render_screenshot_controls(ui, &ctx);
     }
@@ -366,6 +647,8 @@ impl BonsaiApp {
         ui.horizontal(|ui| {
             ui.label("Block:");
             ui.text_edit_singleline(&mut self.diff_block_input);
+            ui.label("End (optional):");
+            ui.text_edit_singleline(&mut self.diff_end_input);
             ui.label("Trie:");
             ui.selectable_value(&mut self.diff_trie, TrieKind::Contract, "Contract");
             ui.selectable_value(&mut self.diff_trie, TrieKind::Storage, "Storage");
@@ -386,36 +669,128 @@ impl BonsaiApp {
             TrieKind::Class => cf_map::BONSAI_CLASS_LOG,
         };
 
-        let entries = read_block_log(db, log_cf, block);
+        match (earliest_logged_block(db, log_cf), latest_logged_block(db, log_cf)) {
+            (Some(earliest), Some(latest)) => ui.label(format!("Logged blocks: {earliest}..={latest}")),
+            _ => ui.label("No trie logs in this CF."),
+        };
+        if let Err(err) = check_block_logged(db, log_cf, block) {
+            ui.colored_label(egui::Color32::RED, err.to_string());
+            return;
+        }
+
+        let end = self.diff_end_input.trim();
+        if !end.is_empty() {
+            let Ok(end) = end.parse::<u64>() else {
+                ui.label("Enter a valid end block.");
+                return;
+            };
+            if end < block {
+                ui.label("End block must not be before the start block.");
+                return;
+            }
+            if let Err(err) = check_block_logged(db, log_cf, end) {
+                ui.colored_label(egui::Color32::RED, err.to_string());
+                return;
+            }
+            let changes = aggregate_range(db, log_cf, block, end);
+            ui.separator();
+            ui.label(format!("Net changes {block}..={end}: {}", changes.len()));
+            egui::ScrollArea::vertical().max_height(320.0).show(ui, |ui| {
+                for change in changes {
+                    let key_bits = change
+                        .key_bits
+                        .as_ref()
+                        .map(|k| format!("len={}", k.len()))
+                        .unwrap_or_else(|| "unknown".to_string());
+                    let key_type = match change.key_type {
+                        0 => "trie",
+                        1 => "flat",
+                        _ => "unknown",
+                    };
+                    let display = |value: &Option<Vec<u8>>| match value {
+                        Some(bytes) => decode_felt_scale(bytes)
+                            .map(|felt| format_felt_short(&felt))
+                            .unwrap_or_else(|| bytes_to_hex(bytes)),
+                        None => "-".to_string(),
+                    };
+                    ui.label(format!(
+                        "{}..{} {:?} key_type={} key={} {} -> {}",
+                        change.first_block,
+                        change.last_block,
+                        change.trie_kind,
+                        key_type,
+                        key_bits,
+                        display(&change.old),
+                        display(&change.new)
+                    ));
+                }
+            });
+            let ctx = ui.ctx().clone();
+            self.render_screenshot_controls(ui, &ctx);
+            return;
+        }
+
+        let changes = pair_changes(read_block_log(db, log_cf, block));
 
         ui.separator();
-        ui.label(format!("Entries: {}", entries.len()));
+        ui.label(format!("Changed keys: {}", changes.len()));
+        let mut selected: Option<(TrieKind, String, Option<String>)> = None;
         egui::ScrollArea::vertical().max_height(320.0).show(ui, |ui| {
-            for entry in entries {
-                let key_bits = entry
+            for change in changes {
+                let leaf_key = change
                     .key_bits
                     .as_ref()
-                    .map(|k| format!("len={}", k.len()))
-                    .unwrap_or_else(|| "unknown".to_string());
-                let change = match entry.change_type {
-                    0 => "new",
-                    1 => "old",
-                    _ => "unknown",
+                    .filter(|k| k.len() == LEAF_PATH_LEN)
+                    .map(|k| format!("{:#x}", path_to_felt(k)));
+                let contract = (change.trie_kind == TrieKind::Storage && change.identifier.len() == 32)
+                    .then(|| format!("{:#x}", Felt::from_bytes_be_slice(&change.identifier)));
+                let key_bits = match (&leaf_key, change.key_bits.as_ref()) {
+                    (Some(key), _) => key.clone(),
+                    (None, Some(k)) => format!("{} (len={})", bytes_to_hex(&k.to_bytes()), k.len()),
+                    (None, None) => "unknown".to_string(),
+                };
+                let contract_display = contract
+                    .as_ref()
+                    .map(|c| format!(" contract={c}"))
+                    .unwrap_or_default();
+                let kind = match change.kind {
+                    ChangeKind::Insert => "insert",
+                    ChangeKind::Update => "update",
+                    ChangeKind::Delete => "delete",
                 };
-                let key_type = match entry.key_type {
+                let key_type = match change.key_type {
                     0 => "trie",
                     1 => "flat",
                     _ => "unknown",
                 };
-                let value_display = decode_felt_scale(&entry.value)
-                    .map(|felt| format_felt_short(&felt))
-                    .unwrap_or_else(|| bytes_to_hex(&entry.value));
-                ui.label(format!(
-                    "{} {:?} key_type={} change={} key={} value={}",
-                    entry.block, entry.trie_kind, key_type, change, key_bits, value_display
-                ));
+                ui.horizontal(|ui| {
+                    ui.label(format!(
+                        "{} {:?} {} key_type={} key={}{} {} -> {}",
+                        change.block,
+                        change.trie_kind,
+                        kind,
+                        key_type,
+                        key_bits,
+                        contract_display,
+                        log_value_display(change.before.as_ref()),
+                        log_value_display(change.after.as_ref())
+                    ));
+                    if let Some(key) = &leaf_key {
+                        if ui.small_button("Trace").clicked() {
+                            selected = Some((change.trie_kind, key.clone(), contract.clone()));
+                        }
+                    }
+                });
             }
         });
+        if let Some((trie, key, contract)) = selected {
+            self.tree_trie = trie;
+            if let Some(contract) = contract {
+                self.storage_identifier_input = contract;
+            }
+            self.key_input = key;
+            self.active_tab = Tab::Path;
+        }
         let ctx = ui.ctx().clone();
         self.render_screenshot_controls(ui, &ctx);
     }
@@ -425,7 +800,7 @@ impl BonsaiApp {
         self.render_status(ui);
         ui.separator();
 
-        let Some(db) = &self.db else {
+        let Some(db) = self.db.clone() else {
             ui.label("DB not opened.");
             return;
         };
@@ -437,14 +812,11 @@ impl BonsaiApp {
             ui.selectable_value(&mut self.tree_trie, TrieKind::Class, "Class");
         });
         if self.tree_trie == TrieKind::Storage {
-            ui.horizontal(|ui| {
-                ui.label("Contract address:");
-                ui.text_edit_singleline(&mut self.storage_identifier_input);
-            });
+            self.render_storage_identifier(ui);
         }
 
         ui.horizontal(|ui| {
-            ui.label("Key (hex felt):");
+            ui.label("Key (felt or name[key]):");
             ui.text_edit_singleline(&mut self.key_input);
             if ui.button("Build Proof").clicked() {
                 self.proof_status.clear();
@@ -455,24 +827,31 @@ impl BonsaiApp {
             ui.label("Provide a valid contract address for storage trie.");
             return;
         };
-        let mut reader = TrieReader::new(db.clone(), spec);
+        let mut reader = match self.open_reader(&db, spec) {
+            Ok(reader) => reader,
+            Err(err) => {
+                ui.colored_label(egui::Color32::RED, err);
+                return;
+            }
+        };
 
-        let felt = match parse_felt_hex(&self.key_input) {
+        let felt = match parse_trie_key(self.tree_trie, &self.key_input) {
             Ok(felt) => felt,
             Err(err) => {
                 ui.colored_label(egui::Color32::RED, err);
                 return;
             }
         };
+        if self.tree_trie == TrieKind::Storage && is_storage_expression(&self.key_input) {
+            ui.monospace(format!("{} = {felt:#x}", self.key_input.trim()));
+        }
         let key_path = felt_to_path(&felt);
 
-        let Some(root_node) = reader.load_root_node() else {
-            ui.label("Empty trie.");
-            return;
-        };
-        let root_hash = match root_node {
-            Node::Binary(bin) => bin.hash,
-            Node::Edge(edge) => edge.hash,
+        // An empty trie has no root node and commits to zero.
+        let root_hash = match reader.load_root_node() {
+            None => Some(Felt::ZERO),
+            Some(Node::Binary(bin)) => bin.hash,
+            Some(Node::Edge(edge)) => edge.hash,
         };
 
         let Some(root_hash) = root_hash else {
@@ -488,11 +867,12 @@ impl BonsaiApp {
             }
         };
 
-        let verified = verify_proof(root_hash, &key_path, &proof, self.tree_trie);
-        self.proof_status = if verified {
-            "Proof verified".to_string()
-        } else {
-            "Proof failed".to_string()
+        let leaf_value = reader.load_flat_value(&key_path);
+        let verified = verify_proof(root_hash, &key_path, &proof, self.tree_trie, leaf_value);
+        self.proof_status = match &verified {
+            Ok(Membership::Member(value)) => format!("Membership proof verified, value {value:#x}"),
+            Ok(Membership::NonMember) => "Non-membership proof verified: key is absent".to_string(),
+            Err(err) => format!("Proof failed: {err}"),
         };
         self.last_proof = proof.clone();
 
@@ -503,11 +883,19 @@ impl BonsaiApp {
             ui.label("Export JSON:");
             ui.text_edit_singleline(&mut self.proof_output_path);
             if ui.button("Save").clicked() {
-                if let Err(err) = save_proof_json(&self.proof_output_path, &proof, self.tree_trie) {
-                    self.proof_status = format!("Save failed: {err}");
-                } else {
-                    self.proof_status = "Saved proof JSON".to_string();
-                }
+                let saved = match &verified {
+                    Ok(membership) => {
+                        let identifier = (self.tree_trie == TrieKind::Storage).then(|| self.storage_identifier_input.trim().to_string());
+                        let block = self.block_input.trim().parse().ok();
+                        let doc = proof_document(self.tree_trie, identifier, block, root_hash, felt, *membership, &proof);
+                        save_proof_json(&self.proof_output_path, &doc)
+                    }
+                    Err(_) => Err("only verified proofs can be exported".to_string()),
+                };
+                self.proof_status = match saved {
+                    Ok(()) => "Saved proof JSON".to_string(),
+                    Err(err) => format!("Save failed: {err}"),
+                };
             }
         });
         egui::ScrollArea::vertical().max_height(320.0).show(ui, |ui| {
@@ -684,40 +1072,17 @@ fn chrono_stamp() -> String {
     nanos.to_string()
 }
 
-#[derive(Serialize)]
-struct ProofNodeJson {
-    kind: &'static str,
-    left: Option<String>,
-    right: Option<String>,
-    child: Option<String>,
-    path_len: Option<usize>,
+fn save_proof_json(path: &str, doc: &ProofDocument) -> Result<(), String> {
+    std::fs::write(path, serde_json::to_vec_pretty(doc).map_err(|e| e.to_string())?)
+        .map_err(|e| e.to_string())
 }
 
-fn save_proof_json(path: &str, proof: &[ProofNode], kind: TrieKind) -> Result<(), String> {
-    let nodes: Vec<ProofNodeJson> = proof
-        .iter()
-        .map(|node| match node {
-            ProofNode::Binary { left, right } => ProofNodeJson {
-                kind: "binary",
-                left: Some(format!("{left:#x}")),
-                right: Some(format!("{right:#x}")),
-                child: None,
-                path_len: None,
-            },
-            ProofNode::Edge { child, path } => ProofNodeJson {
-                kind: "edge",
-                left: None,
-                right: None,
-                child: Some(format!("{child:#x}")),
-                path_len: Some(path.len()),
-            },
-        })
-        .collect();
-
-    let payload = serde_json::json!({
-        "trie": format!("{:?}", kind),
-        "nodes": nodes
-    });
-    std::fs::write(path, serde_json::to_vec_pretty(&payload).map_err(|e| e.to_string())?)
-        .map_err(|e| e.to_string())
+fn log_value_display(value: Option<&LogValue>) -> String {
+    match value {
+        None => "-".to_string(),
+        Some(LogValue::Felt(felt)) => format_felt_short(felt),
+        Some(LogValue::Node(Node::Binary(binary))) => format!("binary h={}", binary.height),
+        Some(LogValue::Node(Node::Edge(edge))) => format!("edge h={} len={}", edge.height, edge.path.len()),
+        Some(LogValue::Raw(bytes)) => bytes_to_hex(bytes),
+    }
 }
@@ -0,0 +1,96 @@
+use std::cmp::Ordering as KeyOrder;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use starknet_types_core::felt::Felt;
+
+use crate::bonsai::path::path_to_felt;
+use crate::bonsai::trie_reader::TrieReader;
+use crate::bonsai::walker::{TrieWalker, WalkItem};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IssueKind {
+    /// The flat CF has the key but the trie has no leaf for it.
+    FlatOnly,
+    /// The trie has a leaf but the flat CF has no entry for it.
+    TrieOnly,
+    /// Both sides have the key with different values.
+    ValueMismatch,
+}
+
+#[derive(Debug, Clone)]
+pub struct ConsistencyIssue {
+    pub key: Felt,
+    pub kind: IssueKind,
+    pub flat: Option<Felt>,
+    pub trie: Option<Felt>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ConsistencyReport {
+    pub flat_entries: u64,
+    pub trie_leaves: u64,
+    pub matched: u64,
+    /// Trie nodes referenced by a parent but absent from the trie CF; leaves below them are not visited.
+    pub missing_nodes: u64,
+    pub issues: Vec<ConsistencyIssue>,
+}
+
+/// Cross-checks the flat CF against the trie leaves for `reader`'s identifier.
+///
+/// Both sides come out in ascending key order (a prefix scan of the flat CF and a
+/// left-first trie walk), so they are merge-joined without holding either in memory.
+/// `progress` counts keys compared.
+pub fn check_flat_consistency(reader: &TrieReader, progress: &AtomicU64) -> ConsistencyReport {
+    let mut report = ConsistencyReport::default();
+    let mut flat = match reader.flat_leaves_from(&Felt::ZERO) {
+        Ok(iter) => iter.peekable(),
+        Err(_) => return report,
+    };
+
+    let mut missing_nodes = 0u64;
+    let mut trie = TrieWalker::new(reader)
+        .filter_map(|item| match item {
+            WalkItem::Leaf { path, hash, .. } => Some((path_to_felt(&path), hash)),
+            WalkItem::Missing { .. } => {
+                missing_nodes += 1;
+                None
+            }
+            WalkItem::Node { .. } => None,
+        })
+        .peekable();
+
+    loop {
+        let order = match (flat.peek(), trie.peek()) {
+            (None, None) => break,
+            (Some(_), None) => KeyOrder::Less,
+            (None, Some(_)) => KeyOrder::Greater,
+            (Some((flat_key, _)), Some((trie_key, _))) => flat_key.cmp(trie_key),
+        };
+        progress.fetch_add(1, Ordering::Relaxed);
+        match order {
+            KeyOrder::Less => {
+                let Some((key, value)) = flat.next() else { break };
+                report.flat_entries += 1;
+                report.issues.push(ConsistencyIssue { key, kind: IssueKind::FlatOnly, flat: value, trie: None });
+            }
+            KeyOrder::Greater => {
+                let Some((key, hash)) = trie.next() else { break };
+                report.trie_leaves += 1;
+                report.issues.push(ConsistencyIssue { key, kind: IssueKind::TrieOnly, flat: None, trie: hash });
+            }
+            KeyOrder::Equal => {
+                let (Some((key, value)), Some((_, hash))) = (flat.next(), trie.next()) else { break };
+                report.flat_entries += 1;
+                report.trie_leaves += 1;
+                if value.is_some() && value == hash {
+                    report.matched += 1;
+                } else {
+                    report.issues.push(ConsistencyIssue { key, kind: IssueKind::ValueMismatch, flat: value, trie: hash });
+                }
+            }
+        }
+    }
+    drop(trie);
+    report.missing_nodes = missing_nodes;
+    report
+}
@@ -1,6 +1,27 @@
+use std::collections::BTreeMap;
+
+use parity_scale_codec::Decode;
+use starknet_types_core::felt::Felt;
+
+use crate::bonsai::node::Node;
 use crate::bonsai::path::PathBits;
 use crate::db::RocksDb;
-use crate::model::TrieKind;
+use crate::model::{ChangeKind, TrieKind};
+
+pub const KEY_TYPE_TRIE: u8 = 0;
+pub const KEY_TYPE_FLAT: u8 = 1;
+pub const CHANGE_NEW: u8 = 0;
+pub const CHANGE_OLD: u8 = 1;
+
+#[derive(Debug, thiserror::Error)]
+pub enum LogsUnavailable {
+    #[error("logs unavailable for block {block}: no trie logs in {log_cf}")]
+    NoLogs { block: u64, log_cf: String },
+    #[error("logs unavailable for block {block}: pruned, earliest logged block is {earliest}")]
+    Pruned { block: u64, earliest: u64 },
+    #[error("logs unavailable for block {block}: latest logged block is {latest}")]
+    NotLogged { block: u64, latest: u64 },
+}
 
 #[derive(Debug, Clone)]
 pub struct TrieLogEntry {
@@ -8,51 +29,247 @@ pub struct TrieLogEntry {
     pub trie_kind: TrieKind,
     pub identifier: Vec<u8>,
     pub key_bits: Option<PathBits>,
+    /// Raw key as stored in the trie/flat CF: identifier followed by the encoded path.
+    pub trie_key: Vec<u8>,
     pub key_type: u8,
     pub change_type: u8,
     pub value: Vec<u8>,
 }
 
+/// Net effect of a block range on one key: the value before the range and after it.
+#[derive(Debug, Clone)]
+pub struct RangeChange {
+    pub trie_kind: TrieKind,
+    pub identifier: Vec<u8>,
+    pub key_bits: Option<PathBits>,
+    pub trie_key: Vec<u8>,
+    pub key_type: u8,
+    pub first_block: u64,
+    pub last_block: u64,
+    pub old: Option<Vec<u8>>,
+    pub new: Option<Vec<u8>>,
+}
+
+/// A trie-log value decoded according to its key type.
+#[derive(Debug, Clone)]
+pub enum LogValue {
+    Felt(Felt),
+    Node(Node),
+    Raw(Vec<u8>),
+}
+
+impl LogValue {
+    pub fn decode(key_type: u8, bytes: &[u8]) -> Self {
+        let decoded = match key_type {
+            KEY_TYPE_FLAT => Felt::decode(&mut &bytes[..]).ok().map(LogValue::Felt),
+            KEY_TYPE_TRIE => Node::decode(&mut &bytes[..]).ok().map(LogValue::Node),
+            _ => None,
+        };
+        decoded.unwrap_or_else(|| LogValue::Raw(bytes.to_vec()))
+    }
+}
+
+/// The `old` and `new` records a block logged for one key, joined into a single change.
+#[derive(Debug, Clone)]
+pub struct TrieChange {
+    pub block: u64,
+    pub trie_kind: TrieKind,
+    pub identifier: Vec<u8>,
+    pub key_bits: Option<PathBits>,
+    pub trie_key: Vec<u8>,
+    pub key_type: u8,
+    pub kind: ChangeKind,
+    pub before: Option<LogValue>,
+    pub after: Option<LogValue>,
+}
+
 pub fn read_block_log(
     db: &RocksDb,
     log_cf: &str,
     block: u64,
 ) -> Vec<TrieLogEntry> {
-    let mut prefix = block.to_be_bytes().to_vec();
-    prefix.push(0x00);
+    read_log_range(db, log_cf, block, Some(block))
+}
 
+/// Reads every log entry from block `from` up to `to` (inclusive, or the end of the CF).
+pub fn read_log_range(
+    db: &RocksDb,
+    log_cf: &str,
+    from: u64,
+    to: Option<u64>,
+) -> Vec<TrieLogEntry> {
     let mut entries = Vec::new();
-    let iter = match db.iter_cf_from(log_cf, &prefix) {
+    let iter = match db.iter_cf_from(log_cf, &from.to_be_bytes()) {
         Ok(iter) => iter,
         Err(_) => return entries,
     };
 
     for (key, value) in iter {
-        if !key.starts_with(&prefix) {
+        let Some(block) = log_key_block(&key) else {
+            continue;
+        };
+        if to.is_some_and(|to| block > to) {
             break;
         }
-        if key.len() < 8 + 1 + 2 {
-            continue;
+        if let Some(entry) = parse_log_entry(block, &key, value) {
+            entries.push(entry);
         }
-        let key_type = key[key.len() - 2];
-        let change_type = key[key.len() - 1];
-        let trie_key_bytes = &key[(8 + 1)..(key.len() - 2)];
-
-        let (trie_kind, identifier, key_bits) = parse_trie_key(trie_key_bytes);
-        entries.push(TrieLogEntry {
-            block,
-            trie_kind,
-            identifier,
-            key_bits,
-            key_type,
-            change_type,
-            value,
-        });
     }
 
     entries
 }
 
+/// Joins log entries that share a block and key. A key with only a `new` record was
+/// inserted, one with only an `old` record was deleted.
+pub fn pair_changes(entries: Vec<TrieLogEntry>) -> Vec<TrieChange> {
+    struct Pending {
+        entry: TrieLogEntry,
+        old: Option<Vec<u8>>,
+        new: Option<Vec<u8>>,
+    }
+
+    let mut pending: BTreeMap<(u64, u8, Vec<u8>), Pending> = BTreeMap::new();
+    for entry in entries {
+        let slot = pending
+            .entry((entry.block, entry.key_type, entry.trie_key.clone()))
+            .or_insert_with(|| Pending { entry: entry.clone(), old: None, new: None });
+        match entry.change_type {
+            CHANGE_OLD => slot.old = Some(entry.value),
+            CHANGE_NEW => slot.new = Some(entry.value),
+            _ => {}
+        }
+    }
+
+    pending
+        .into_values()
+        .filter_map(|Pending { entry, old, new }| {
+            let kind = match (&old, &new) {
+                (None, Some(_)) => ChangeKind::Insert,
+                (Some(_), Some(_)) => ChangeKind::Update,
+                (Some(_), None) => ChangeKind::Delete,
+                (None, None) => return None,
+            };
+            Some(TrieChange {
+                block: entry.block,
+                trie_kind: entry.trie_kind,
+                identifier: entry.identifier,
+                key_bits: entry.key_bits,
+                trie_key: entry.trie_key,
+                key_type: entry.key_type,
+                kind,
+                before: old.map(|v| LogValue::decode(entry.key_type, &v)),
+                after: new.map(|v| LogValue::decode(entry.key_type, &v)),
+            })
+        })
+        .collect()
+}
+
+/// Every entry of `log_cf` in block order, read lazily; empty if the CF is missing.
+pub fn log_entries<'a>(db: &'a RocksDb, log_cf: &str) -> impl Iterator<Item = TrieLogEntry> + 'a {
+    log_entries_from(db, log_cf, 0)
+}
+
+/// Like [`log_entries`], starting at block `from`.
+pub fn log_entries_from<'a>(db: &'a RocksDb, log_cf: &str, from: u64) -> impl Iterator<Item = TrieLogEntry> + 'a {
+    db.iter_cf_from(log_cf, &from.to_be_bytes())
+        .into_iter()
+        .flatten()
+        .filter_map(|(key, value)| parse_log_entry(log_key_block(&key)?, &key, value))
+}
+
+/// Every change `log_cf` records for one leaf, oldest first. The log is keyed by block,
+/// so this scans all of it.
+pub fn key_history(db: &RocksDb, log_cf: &str, trie_key: &[u8]) -> Vec<TrieChange> {
+    let entries = log_entries(db, log_cf)
+        .filter(|entry| entry.key_type == KEY_TYPE_FLAT && entry.trie_key == trie_key)
+        .collect();
+    pair_changes(entries)
+}
+
+/// Merges every log entry in `[start, end]` into one change per key, keeping the
+/// `old` value of the first block and the `new` value of the last block that touched it.
+/// Keys that end up where they started are left out.
+pub fn aggregate_range(db: &RocksDb, log_cf: &str, start: u64, end: u64) -> Vec<RangeChange> {
+    let mut changes: BTreeMap<(u8, Vec<u8>), RangeChange> = BTreeMap::new();
+    for entry in read_log_range(db, log_cf, start, Some(end)) {
+        let change = changes
+            .entry((entry.key_type, entry.trie_key.clone()))
+            .or_insert_with(|| RangeChange {
+                trie_kind: entry.trie_kind,
+                identifier: entry.identifier.clone(),
+                key_bits: entry.key_bits.clone(),
+                trie_key: entry.trie_key.clone(),
+                key_type: entry.key_type,
+                first_block: entry.block,
+                last_block: entry.block,
+                old: None,
+                new: None,
+            });
+        if entry.block > change.last_block {
+            // A later block supersedes the new value; a missing `new` record means a delete.
+            change.last_block = entry.block;
+            change.new = None;
+        }
+        match entry.change_type {
+            CHANGE_OLD if entry.block == change.first_block => change.old = Some(entry.value),
+            CHANGE_NEW => change.new = Some(entry.value),
+            _ => {}
+        }
+    }
+    changes.into_values().filter(|c| c.old != c.new).collect()
+}
+
+/// First block that still has log entries in `log_cf`.
+pub fn earliest_logged_block(db: &RocksDb, log_cf: &str) -> Option<u64> {
+    let mut iter = db.iter_cf_from(log_cf, &[]).ok()?;
+    iter.find_map(|(key, _)| log_key_block(&key))
+}
+
+/// Last block that has log entries in `log_cf`.
+pub fn latest_logged_block(db: &RocksDb, log_cf: &str) -> Option<u64> {
+    db.last_key_cf(log_cf).ok()?.and_then(|key| log_key_block(&key))
+}
+
+/// Fails unless `block` lies between the earliest and latest logged blocks.
+pub fn check_block_logged(db: &RocksDb, log_cf: &str, block: u64) -> Result<(), LogsUnavailable> {
+    let (Some(earliest), Some(latest)) = (earliest_logged_block(db, log_cf), latest_logged_block(db, log_cf)) else {
+        return Err(LogsUnavailable::NoLogs { block, log_cf: log_cf.to_string() });
+    };
+    if block < earliest {
+        return Err(LogsUnavailable::Pruned { block, earliest });
+    }
+    if block > latest {
+        return Err(LogsUnavailable::NotLogged { block, latest });
+    }
+    Ok(())
+}
+
+fn log_key_block(key: &[u8]) -> Option<u64> {
+    let block_bytes: [u8; 8] = key.get(..8)?.try_into().ok()?;
+    Some(u64::from_be_bytes(block_bytes))
+}
+
+fn parse_log_entry(block: u64, key: &[u8], value: Vec<u8>) -> Option<TrieLogEntry> {
+    if key.len() < 8 + 1 + 2 || key[8] != 0x00 {
+        return None;
+    }
+    let key_type = key[key.len() - 2];
+    let change_type = key[key.len() - 1];
+    let trie_key_bytes = &key[(8 + 1)..(key.len() - 2)];
+
+    let (trie_kind, identifier, key_bits) = parse_trie_key(trie_key_bytes);
+    Some(TrieLogEntry {
+        block,
+        trie_kind,
+        identifier,
+        key_bits,
+        trie_key: trie_key_bytes.to_vec(),
+        key_type,
+        change_type,
+        value,
+    })
+}
+
 fn parse_trie_key(bytes: &[u8]) -> (TrieKind, Vec<u8>, Option<PathBits>) {
     let contract_prefix = TrieKind::Contract.identifier();
     if bytes.starts_with(contract_prefix) {
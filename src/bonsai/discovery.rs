@@ -0,0 +1,46 @@
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use starknet_types_core::felt::Felt;
+
+use crate::db::cf_map;
+use crate::db::RocksDb;
+
+/// Storage trie and flat keys start with the owning contract's address, big-endian.
+const IDENTIFIER_LEN: usize = 32;
+
+#[derive(Debug, Clone)]
+pub struct StorageContract {
+    pub address: Felt,
+    pub leaves: u64,
+    pub trie_nodes: u64,
+    /// Key plus value bytes across the storage trie and flat CFs, before compression.
+    pub bytes: u64,
+}
+
+/// Lists every contract that has storage, by scanning the storage flat and trie CFs once
+/// and grouping keys by their identifier. Sorted by address; `progress` counts keys.
+pub fn discover_storage_contracts(db: &RocksDb, progress: &AtomicU64) -> Result<Vec<StorageContract>, rocksdb::Error> {
+    let mut contracts: BTreeMap<[u8; IDENTIFIER_LEN], StorageContract> = BTreeMap::new();
+    for (cf, flat) in [(cf_map::BONSAI_CONTRACT_STORAGE_FLAT, true), (cf_map::BONSAI_CONTRACT_STORAGE_TRIE, false)] {
+        for (key, value) in db.iter_cf_from(cf, &[])? {
+            progress.fetch_add(1, Ordering::Relaxed);
+            let Some(identifier) = key.get(..IDENTIFIER_LEN).and_then(|id| <[u8; IDENTIFIER_LEN]>::try_from(id).ok()) else {
+                continue;
+            };
+            let contract = contracts.entry(identifier).or_insert_with(|| StorageContract {
+                address: Felt::from_bytes_be(&identifier),
+                leaves: 0,
+                trie_nodes: 0,
+                bytes: 0,
+            });
+            if flat {
+                contract.leaves += 1;
+            } else {
+                contract.trie_nodes += 1;
+            }
+            contract.bytes += (key.len() + value.len()) as u64;
+        }
+    }
+    Ok(contracts.into_values().collect())
+}
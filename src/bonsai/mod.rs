@@ -1,5 +1,11 @@
+pub mod consistency;
 pub mod diff_reader;
+pub mod discovery;
+pub mod historical;
+pub mod integrity;
 pub mod node;
 pub mod path;
 pub mod proof;
+pub mod stats;
 pub mod trie_reader;
+pub mod walker;
@@ -30,14 +30,22 @@ impl PathBits {
         self.0.extend_from_bitslice(bits);
     }
 
+    /// Storage encoding: length byte, then the bits. Full 251-bit keys are stored
+    /// right-aligned (the felt's big-endian bytes), shorter node paths left-aligned.
     pub fn to_bytes(&self) -> Vec<u8> {
         if self.0.is_empty() {
             return vec![0u8];
         }
         let len = self.0.len() as u8;
-        let mut out = Vec::with_capacity(1 + self.0.as_raw_slice().len());
+        if self.0.len() == LEAF_PATH_LEN {
+            let mut out = vec![len];
+            out.extend_from_slice(&path_to_felt(self).to_bytes_be());
+            return out;
+        }
+        let bits: BitVec<u8, Msb0> = self.0.iter().by_vals().collect();
+        let mut out = Vec::with_capacity(1 + bits.as_raw_slice().len());
         out.push(len);
-        out.extend_from_slice(self.0.as_raw_slice());
+        out.extend_from_slice(bits.as_raw_slice());
         out
     }
 
@@ -46,7 +54,11 @@ impl PathBits {
             return Self::default();
         }
         let len = bytes[0] as usize;
-        let mut bits = BitSlice::<u8, Msb0>::from_slice(&bytes[1..]).to_bitvec();
+        let raw = BitSlice::<u8, Msb0>::from_slice(&bytes[1..]);
+        if len == LEAF_PATH_LEN && raw.len() == 256 {
+            return Self(raw[256 - LEAF_PATH_LEN..].to_bitvec());
+        }
+        let mut bits = raw.to_bitvec();
         bits.truncate(len);
         Self(bits)
     }
@@ -58,6 +70,9 @@ impl PathBits {
     }
 }
 
+/// Bit length of a full trie key (a felt without its 5 unused high bits).
+pub const LEAF_PATH_LEN: usize = 251;
+
 pub fn felt_to_path(felt: &Felt) -> PathBits {
     let bytes = felt.to_bytes_be();
     let bits: BitVec<u8, Msb0> = bytes.as_bits()[5..].to_owned();
@@ -71,3 +86,54 @@ pub fn path_to_felt(path: &PathBits) -> Felt {
         .copy_from_bitslice(&path.0);
     Felt::from_bytes_be(&bytes)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // bonsai-trie writes a flat key as the length byte followed by the raw bytes of
+    // the key's `[5..]` bit view, which are the felt's big-endian bytes.
+    fn bonsai_flat_key(felt: &Felt) -> Vec<u8> {
+        let mut key = vec![LEAF_PATH_LEN as u8];
+        key.extend_from_slice(&felt.to_bytes_be());
+        key
+    }
+
+    #[test]
+    fn leaf_path_matches_bonsai_flat_key() {
+        for felt in [Felt::ONE, Felt::from(0xabcdefu64), Felt::from_hex_unchecked("0x400000000000000000000000000000000000000000000000000000000000000")] {
+            let encoded = felt_to_path(&felt).to_bytes();
+            assert_eq!(encoded, bonsai_flat_key(&felt));
+            let decoded = PathBits::from_encoded(&encoded);
+            assert_eq!(decoded.len(), LEAF_PATH_LEN);
+            assert_eq!(path_to_felt(&decoded), felt);
+        }
+        let mut expected = vec![251u8];
+        expected.extend_from_slice(&[0; 31]);
+        expected.push(1);
+        assert_eq!(felt_to_path(&Felt::ONE).to_bytes(), expected);
+    }
+
+    #[test]
+    fn node_path_is_left_aligned() {
+        let mut path = PathBits::default();
+        for bit in [true, false, true] {
+            path.push(bit);
+        }
+        assert_eq!(path.to_bytes(), vec![3, 0b1010_0000]);
+        assert_eq!(PathBits::from_encoded(&[3, 0b1010_0000]), path);
+
+        // A prefix sliced out of a leaf path keeps that path's bit offset in memory;
+        // the encoding must not.
+        let top = Felt::from_hex_unchecked("0x400000000000000000000000000000000000000000000000000000000000000");
+        let prefix = PathBits(felt_to_path(&top).0[..10].to_bitvec());
+        assert_eq!(prefix.to_bytes(), vec![10, 0b1000_0000, 0]);
+        assert_eq!(PathBits::from_encoded(&prefix.to_bytes()), prefix);
+    }
+
+    #[test]
+    fn root_path_is_a_single_zero_byte() {
+        assert_eq!(PathBits::default().to_bytes(), vec![0]);
+        assert!(PathBits::from_encoded(&[0]).is_empty());
+    }
+}
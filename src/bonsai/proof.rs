@@ -4,7 +4,7 @@ use starknet_types_core::hash::{Pedersen, Poseidon, StarkHash};
 use crate::bonsai::node::Node;
 use crate::bonsai::path::{path_to_felt, PathBits};
 use crate::bonsai::trie_reader::TrieReader;
-use crate::model::TrieKind;
+use crate::model::{ProofDocument, ProofMembership, ProofNodeJson, TrieKind, PROOF_DOCUMENT_VERSION};
 
 #[derive(Debug, Clone)]
 pub enum ProofNode {
@@ -19,13 +19,98 @@ impl ProofNode {
             TrieKind::Class => hash_node::<Poseidon>(self),
         }
     }
+
+    /// The node as exported in proof documents and API responses.
+    pub fn to_json(&self, kind: TrieKind) -> ProofNodeJson {
+        let hash = Some(format!("{:#x}", self.hash(kind)));
+        match self {
+            ProofNode::Binary { left, right } => ProofNodeJson {
+                kind: "binary".to_string(),
+                hash,
+                left: Some(format!("{left:#x}")),
+                right: Some(format!("{right:#x}")),
+                child: None,
+                path_len: None,
+                path: None,
+            },
+            ProofNode::Edge { child, path } => ProofNodeJson {
+                kind: "edge".to_string(),
+                hash,
+                left: None,
+                right: None,
+                child: Some(format!("{child:#x}")),
+                path_len: Some(path.len()),
+                path: Some(format!("{:#x}", path_to_felt(path))),
+            },
+        }
+    }
+}
+
+/// What a valid proof shows about its key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Membership {
+    /// The path reaches the full key; carries the leaf value the last node commits to.
+    Member(Felt),
+    /// The last edge leaves the key's path (or the trie is empty), so no leaf exists under the key.
+    NonMember,
+}
+
+/// The standalone document for a verified proof of `key` against `root`.
+pub fn proof_document(
+    kind: TrieKind,
+    identifier: Option<String>,
+    block: Option<u64>,
+    root: Felt,
+    key: Felt,
+    membership: Membership,
+    proof: &[ProofNode],
+) -> ProofDocument {
+    let (membership, value) = match membership {
+        Membership::Member(value) => (ProofMembership::Member, Some(format!("{value:#x}"))),
+        Membership::NonMember => (ProofMembership::NonMember, None),
+    };
+    ProofDocument {
+        version: PROOF_DOCUMENT_VERSION,
+        trie: kind,
+        hash_function: kind.hash_function(),
+        identifier,
+        block,
+        root: format!("{root:#x}"),
+        key: format!("{key:#x}"),
+        value,
+        membership,
+        nodes: proof.iter().map(|node| node.to_json(kind)).collect(),
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ProofError {
+    #[error("proof node {index} does not hash to the value its parent commits to")]
+    HashMismatch { index: usize },
+    #[error("proof ends before it reaches the key or diverges from it")]
+    Incomplete,
+    #[error("proof continues after node {index}, where it already ends")]
+    TrailingNodes { index: usize },
+    #[error("proof shows the key with value {proven:#x}, but the leaf value is {}", display_value(.value))]
+    ValueMismatch { proven: Felt, value: Option<Felt> },
+    #[error("proof shows the key is absent, but it has leaf value {0:#x}")]
+    UnexpectedValue(Felt),
+}
+
+fn display_value(value: &Option<Felt>) -> String {
+    value.map(|v| format!("{v:#x}")).unwrap_or_else(|| "missing".to_string())
 }
 
+/// Collects the nodes from the root towards `key`. The proof stops at the leaf, or at
+/// the first edge that diverges from `key`, which proves the key absent.
+/// An empty trie yields an empty proof.
 pub fn build_proof(reader: &mut TrieReader, key: &PathBits) -> Option<Vec<ProofNode>> {
     let mut path = PathBits::default();
     let mut proof = Vec::new();
 
-    let mut current_node = reader.load_root_node()?;
+    let Some(mut current_node) = reader.load_root_node() else {
+        return Some(proof);
+    };
 
     loop {
         match &current_node {
@@ -48,44 +133,78 @@ pub fn build_proof(reader: &mut TrieReader, key: &PathBits) -> Option<Vec<ProofN
                     child,
                     path: edge_bits.clone(),
                 });
-                path.extend_from_bitslice(&edge_bits.0);
-                if path.len() >= key.len() {
+                if key.0.get(path.len()..path.len() + edge_bits.len()) != Some(&edge_bits.0) {
                     return Some(proof);
                 }
+                path.extend_from_bitslice(&edge_bits.0);
             }
         }
+        if path.len() >= key.len() {
+            return Some(proof);
+        }
 
         current_node = reader.load_node_by_path(&path)?;
     }
 }
 
-pub fn verify_proof(root: Felt, key: &PathBits, proof: &[ProofNode], kind: TrieKind) -> bool {
+/// Checks the hash chain from `root` along `key` and works out whether the proof shows
+/// membership or non-membership, then checks that against `value`, the key's leaf
+/// value in storage (`None` when it has none).
+pub fn verify_proof(
+    root: Felt,
+    key: &PathBits,
+    proof: &[ProofNode],
+    kind: TrieKind,
+    value: Option<Felt>,
+) -> Result<Membership, ProofError> {
+    let membership = walk_proof(root, key, proof, kind)?;
+    match (membership, value) {
+        (Membership::Member(proven), Some(value)) if proven == value => Ok(membership),
+        (Membership::Member(proven), value) => Err(ProofError::ValueMismatch { proven, value }),
+        (Membership::NonMember, None) => Ok(membership),
+        (Membership::NonMember, Some(value)) => Err(ProofError::UnexpectedValue(value)),
+    }
+}
+
+fn walk_proof(root: Felt, key: &PathBits, proof: &[ProofNode], kind: TrieKind) -> Result<Membership, ProofError> {
+    if proof.is_empty() {
+        // Only an empty trie, whose root is zero, has nothing to show.
+        return if root == Felt::ZERO { Ok(Membership::NonMember) } else { Err(ProofError::Incomplete) };
+    }
     let mut current_hash = root;
     let mut current_path = PathBits::default();
 
-    for node in proof {
+    for (index, node) in proof.iter().enumerate() {
+        if current_path.len() >= key.len() {
+            return Err(ProofError::TrailingNodes { index: index.saturating_sub(1) });
+        }
         if node.hash(kind) != current_hash {
-            return false;
+            return Err(ProofError::HashMismatch { index });
         }
         match node {
             ProofNode::Binary { left, right } => {
-                if current_path.len() >= key.len() {
-                    return false;
-                }
                 let direction = key.0[current_path.len()];
                 current_path.push(direction);
                 current_hash = if direction { *right } else { *left };
             }
             ProofNode::Edge { child, path } => {
                 if key.0.get(current_path.len()..(current_path.len() + path.len())) != Some(&path.0) {
-                    return false;
+                    return if index + 1 == proof.len() {
+                        Ok(Membership::NonMember)
+                    } else {
+                        Err(ProofError::TrailingNodes { index })
+                    };
                 }
                 current_path.extend_from_bitslice(&path.0);
                 current_hash = *child;
             }
         }
     }
-    true
+    if current_path.len() == key.len() {
+        Ok(Membership::Member(current_hash))
+    } else {
+        Err(ProofError::Incomplete)
+    }
 }
 
 fn hash_node<H: StarkHash>(node: &ProofNode) -> Felt {
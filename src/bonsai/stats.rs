@@ -0,0 +1,62 @@
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use parity_scale_codec::Encode;
+
+use crate::bonsai::node::Node;
+use crate::bonsai::trie_reader::TrieReader;
+use crate::bonsai::walker::{TrieWalker, WalkItem};
+
+#[derive(Debug, Clone, Default)]
+pub struct TrieStats {
+    pub binary_nodes: u64,
+    pub edge_nodes: u64,
+    pub leaves: u64,
+    pub missing_nodes: u64,
+    /// SCALE-encoded size of every stored node, as held in the trie CF.
+    pub node_bytes: u64,
+    /// Leaf depth (= proof length in nodes) to number of leaves at that depth.
+    pub leaf_depths: BTreeMap<usize, u64>,
+    /// Edge path length in bits to number of edges with that length.
+    pub edge_lengths: BTreeMap<usize, u64>,
+}
+
+impl TrieStats {
+    pub fn max_proof_len(&self) -> usize {
+        self.leaf_depths.keys().next_back().copied().unwrap_or(0)
+    }
+
+    pub fn avg_proof_len(&self) -> f64 {
+        if self.leaves == 0 {
+            return 0.0;
+        }
+        let total: u64 = self.leaf_depths.iter().map(|(depth, count)| *depth as u64 * count).sum();
+        total as f64 / self.leaves as f64
+    }
+}
+
+/// Walks the whole trie once and tallies its shape. `progress` counts visited nodes.
+pub fn collect_stats(reader: &TrieReader, progress: &AtomicU64) -> TrieStats {
+    let mut stats = TrieStats::default();
+    for item in TrieWalker::new(reader) {
+        match item {
+            WalkItem::Node { node, .. } => {
+                progress.fetch_add(1, Ordering::Relaxed);
+                stats.node_bytes += node.encoded_size() as u64;
+                match &node {
+                    Node::Binary(_) => stats.binary_nodes += 1,
+                    Node::Edge(edge) => {
+                        stats.edge_nodes += 1;
+                        *stats.edge_lengths.entry(edge.path.len()).or_default() += 1;
+                    }
+                }
+            }
+            WalkItem::Leaf { depth, .. } => {
+                stats.leaves += 1;
+                *stats.leaf_depths.entry(depth).or_default() += 1;
+            }
+            WalkItem::Missing { .. } => stats.missing_nodes += 1,
+        }
+    }
+    stats
+}
@@ -1,12 +1,18 @@
+use std::cmp::Ordering as KeyOrder;
 use std::collections::HashMap;
+use std::sync::Arc;
 
 use parity_scale_codec::Decode;
 use starknet_types_core::felt::Felt;
 
+use crate::bonsai::historical::LogOverlay;
 use crate::bonsai::node::Node;
-use crate::bonsai::path::PathBits;
+use crate::bonsai::path::{felt_to_path, path_to_felt, PathBits, LEAF_PATH_LEN};
 use crate::db::RocksDb;
 
+/// Flat-CF leaves as (key, value); `None` when the value does not decode.
+pub type FlatLeafIter<'a> = Box<dyn Iterator<Item = (Felt, Option<Felt>)> + 'a>;
+
 #[derive(Clone, Debug)]
 pub struct TrieSpec {
     pub identifier: Vec<u8>,
@@ -20,6 +26,7 @@ pub struct TrieReader {
     db: RocksDb,
     spec: TrieSpec,
     cache: HashMap<Vec<u8>, Node>,
+    overlay: Option<Arc<LogOverlay>>,
 }
 
 impl TrieReader {
@@ -28,9 +35,21 @@ impl TrieReader {
             db,
             spec,
             cache: HashMap::new(),
+            overlay: None,
         }
     }
 
+    /// Reads through `overlay` first, so the reader sees the trie as of `overlay.block()`.
+    pub fn with_overlay(mut self, overlay: Arc<LogOverlay>) -> Self {
+        self.overlay = Some(overlay);
+        self.cache.clear();
+        self
+    }
+
+    pub fn block(&self) -> Option<u64> {
+        self.overlay.as_ref().map(|o| o.block())
+    }
+
     pub fn db(&self) -> &RocksDb {
         &self.db
     }
@@ -55,16 +74,72 @@ impl TrieReader {
             return Some(node.clone());
         }
 
-        let value = self.db.get_cf(&self.spec.trie_cf, &key).ok()??;
-        let node = Node::decode(&mut value.as_slice()).ok()?;
+        let node = self.read_node(&key)?;
         self.cache.insert(key, node.clone());
         Some(node)
     }
 
+    /// Loads a node without caching it, for full-trie walks that visit each node once.
+    pub fn load_node_uncached(&self, path: &PathBits) -> Option<Node> {
+        let mut key = self.spec.identifier.clone();
+        key.extend_from_slice(&path.to_bytes());
+        self.read_node(&key)
+    }
+
+    fn read_node(&self, key: &[u8]) -> Option<Node> {
+        let value = match self.overlay.as_ref().and_then(|o| o.trie_value(key)) {
+            Some(value) => value?.to_vec(),
+            None => self.db.get_cf(&self.spec.trie_cf, key).ok()??,
+        };
+        Node::decode(&mut value.as_slice()).ok()
+    }
+
     pub fn load_flat_value(&self, key_bits: &PathBits) -> Option<Felt> {
         let mut key = self.spec.identifier.clone();
         key.extend_from_slice(&key_bits.to_bytes());
-        let value = self.db.get_cf(&self.spec.flat_cf, &key).ok()??;
+        let value = match self.overlay.as_ref().and_then(|o| o.flat_value(&key)) {
+            Some(value) => value?.to_vec(),
+            None => self.db.get_cf(&self.spec.flat_cf, &key).ok()??,
+        };
         Felt::decode(&mut value.as_slice()).ok()
     }
+
+    /// Flat-CF leaves with keys `>= from`, in ascending key order, as of the reader's
+    /// block: keys the overlay pins to a value are merged in, keys pinned absent dropped.
+    pub fn flat_leaves_from(&self, from: &Felt) -> Result<FlatLeafIter<'_>, rocksdb::Error> {
+        let prefix = self.spec.identifier.clone();
+        let mut seek = prefix.clone();
+        seek.extend_from_slice(&felt_to_path(from).to_bytes());
+        let pinned = self.overlay.as_ref().map(|o| o.flat_entries_from(&prefix, &seek)).unwrap_or_default();
+        let mut pinned = pinned.into_iter().peekable();
+        let mut stored = self
+            .db
+            .iter_cf_from(&self.spec.flat_cf, &seek)?
+            .take_while(move |(key, _)| key.starts_with(&prefix))
+            .peekable();
+        let merged = std::iter::from_fn(move || loop {
+            let order = match (stored.peek(), pinned.peek()) {
+                (None, None) => return None,
+                (Some(_), None) => KeyOrder::Less,
+                (None, Some(_)) => KeyOrder::Greater,
+                (Some((stored_key, _)), Some((pinned_key, _))) => stored_key.cmp(pinned_key),
+            };
+            match order {
+                KeyOrder::Less => return stored.next(),
+                // The overlay's value wins over the stored one.
+                KeyOrder::Equal => {
+                    stored.next();
+                }
+                KeyOrder::Greater => {}
+            }
+            if let Some((key, Some(value))) = pinned.next() {
+                return Some((key, value));
+            }
+        });
+        let iter = merged.filter_map(move |(key, value)| {
+            let path = PathBits::from_encoded(&key[self.spec.identifier.len()..]);
+            (path.len() == LEAF_PATH_LEN).then(|| (path_to_felt(&path), Felt::decode(&mut value.as_slice()).ok()))
+        });
+        Ok(Box::new(iter))
+    }
 }
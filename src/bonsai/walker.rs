@@ -0,0 +1,71 @@
+use starknet_types_core::felt::Felt;
+
+use crate::bonsai::node::{Node, NodeHandle};
+use crate::bonsai::path::{PathBits, LEAF_PATH_LEN};
+use crate::bonsai::trie_reader::TrieReader;
+
+/// Something the walker reached. Items come depth-first, left child first,
+/// so leaves are yielded in ascending key order. `depth` counts the nodes above
+/// the item, so a leaf's depth is the length of its proof.
+#[derive(Debug, Clone)]
+pub enum WalkItem {
+    Node { path: PathBits, node: Node, depth: usize },
+    /// A 251-bit child; `hash` is the value its parent commits to.
+    Leaf { path: PathBits, hash: Option<Felt>, depth: usize },
+    /// A node its parent points to that the trie CF does not hold.
+    Missing { path: PathBits, depth: usize },
+}
+
+enum Pending {
+    Node(PathBits, usize),
+    Leaf(PathBits, Option<Felt>, usize),
+}
+
+/// Streams every node and leaf of a trie without caching, for full-trie sweeps.
+pub struct TrieWalker<'a> {
+    reader: &'a TrieReader,
+    stack: Vec<Pending>,
+}
+
+impl<'a> TrieWalker<'a> {
+    pub fn new(reader: &'a TrieReader) -> Self {
+        Self { reader, stack: vec![Pending::Node(TrieReader::root_path(), 0)] }
+    }
+
+    fn push_child(&mut self, path: PathBits, handle: NodeHandle, depth: usize) {
+        if path.len() >= LEAF_PATH_LEN {
+            self.stack.push(Pending::Leaf(path, handle.as_hash(), depth));
+        } else {
+            self.stack.push(Pending::Node(path, depth));
+        }
+    }
+}
+
+impl Iterator for TrieWalker<'_> {
+    type Item = WalkItem;
+
+    fn next(&mut self) -> Option<WalkItem> {
+        let (path, node, depth) = match self.stack.pop()? {
+            Pending::Leaf(path, hash, depth) => return Some(WalkItem::Leaf { path, hash, depth }),
+            Pending::Node(path, depth) => match self.reader.load_node_uncached(&path) {
+                Some(node) => (path, node, depth),
+                // An absent root is an empty trie, not a dangling reference.
+                None if path.is_empty() => return None,
+                None => return Some(WalkItem::Missing { path, depth }),
+            },
+        };
+        match &node {
+            Node::Binary(binary) => {
+                // Right first so the left subtree is popped, and yielded, first.
+                self.push_child(path.with_bit(true), binary.right, depth + 1);
+                self.push_child(path.with_bit(false), binary.left, depth + 1);
+            }
+            Node::Edge(edge) => {
+                let mut child_path = path.clone();
+                child_path.extend_from_bitslice(&edge.path.0);
+                self.push_child(child_path, edge.child, depth + 1);
+            }
+        }
+        Some(WalkItem::Node { path, node, depth })
+    }
+}
@@ -2,8 +2,9 @@ use std::collections::BTreeSet;
 use std::path::Path;
 
 use rocksdb::{ColumnFamily, Options, DB};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex, MutexGuard};
 
+use crate::bonsai::historical::OverlayCache;
 use crate::db::cf_map::BONSAI_COLUMNS;
 
 #[derive(Debug, thiserror::Error)]
@@ -18,6 +19,7 @@ pub enum DbOpenError {
 pub struct RocksDb {
     db: Arc<DB>,
     cf_names: Vec<String>,
+    overlays: Arc<Mutex<OverlayCache>>,
 }
 
 impl Clone for RocksDb {
@@ -25,6 +27,7 @@ impl Clone for RocksDb {
         Self {
             db: Arc::clone(&self.db),
             cf_names: self.cf_names.clone(),
+            overlays: Arc::clone(&self.overlays),
         }
     }
 }
@@ -50,7 +53,12 @@ impl RocksDb {
 
         let db = Arc::new(DB::open_cf_for_read_only(&opts, path, &cf_names, false)?);
 
-        Ok(Self { db, cf_names })
+        Ok(Self { db, cf_names, overlays: Arc::default() })
+    }
+
+    /// Historical overlays built against this DB, shared by its clones.
+    pub fn overlay_cache(&self) -> MutexGuard<'_, OverlayCache> {
+        self.overlays.lock().unwrap_or_else(|e| e.into_inner())
     }
 
     pub fn cf_names(&self) -> &[String] {
@@ -83,4 +91,15 @@ impl RocksDb {
             .map(|(k, v)| (k.to_vec(), v.to_vec()));
         Ok(Box::new(iter))
     }
+
+    /// Last key of a column family in byte order.
+    pub fn last_key_cf(&self, name: &str) -> Result<Option<Vec<u8>>, rocksdb::Error> {
+        let Some(cf) = self.db.cf_handle(name) else {
+            return Ok(None);
+        };
+        match self.db.iterator_cf(cf, rocksdb::IteratorMode::End).next() {
+            Some(res) => res.map(|(k, _)| Some(k.to_vec())),
+            None => Ok(None),
+        }
+    }
 }
@@ -0,0 +1,9 @@
+/// Whether a storage key given as text is a storage variable expression (`balance`,
+/// `ERC20_balances[0xabc]`) rather than a felt. Expressions start with a letter or `_` and
+/// either index a mapping or are not hex, so unprefixed keys such as `deadbeef` stay felts.
+/// A variable whose name is all hex digits, such as `fee`, reads as a felt too.
+pub fn is_storage_expression(input: &str) -> bool {
+    let input = input.trim();
+    input.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && (input.contains('[') || !input.chars().all(|c| c.is_ascii_hexdigit()))
+}
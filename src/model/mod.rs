@@ -1,3 +1,9 @@
+mod change;
+mod key;
+mod proof;
 mod trie;
 
-pub use trie::TrieKind;
+pub use change::ChangeKind;
+pub use key::is_storage_expression;
+pub use proof::{ProofDocument, ProofMembership, ProofNodeJson, PROOF_DOCUMENT_VERSION};
+pub use trie::{HashFunction, TrieKind};
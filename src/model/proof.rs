@@ -0,0 +1,50 @@
+//! The proof document shared with the web API, so proofs saved here verify there too.
+
+use serde::{Deserialize, Serialize};
+
+use super::{HashFunction, TrieKind};
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ProofNodeJson {
+    pub kind: String,
+    /// The node's own hash.
+    pub hash: Option<String>,
+    pub left: Option<String>,
+    pub right: Option<String>,
+    pub child: Option<String>,
+    pub path_len: Option<usize>,
+    /// Edge path bits as a felt, `path_len` bits long.
+    pub path: Option<String>,
+}
+
+/// What a proof shows about its key.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ProofMembership {
+    /// The path reaches the leaf.
+    Member,
+    /// The path leaves the key at a diverging edge, or the trie is empty.
+    NonMember,
+}
+
+/// `ProofDocument::version` this build writes and reads.
+pub const PROOF_DOCUMENT_VERSION: u32 = 1;
+
+/// A proof with everything needed to verify it again away from the database.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ProofDocument {
+    pub version: u32,
+    pub trie: TrieKind,
+    pub hash_function: HashFunction,
+    /// Contract address, for storage tries.
+    pub identifier: Option<String>,
+    /// Block the proof was built at; `None` for the latest state.
+    pub block: Option<u64>,
+    pub root: String,
+    pub key: String,
+    /// Leaf value, for membership proofs.
+    pub value: Option<String>,
+    pub membership: ProofMembership,
+    /// Root first, each with its hash and, for edges, its path bits.
+    pub nodes: Vec<ProofNodeJson>,
+}
@@ -1,4 +1,7 @@
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+use serde::{Deserialize, Serialize};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum TrieKind {
     Contract,
     Storage,
@@ -21,4 +24,18 @@ impl TrieKind {
             TrieKind::Storage => b"",
         }
     }
+
+    pub fn hash_function(self) -> HashFunction {
+        match self {
+            TrieKind::Contract | TrieKind::Storage => HashFunction::Pedersen,
+            TrieKind::Class => HashFunction::Poseidon,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HashFunction {
+    Pedersen,
+    Poseidon,
 }
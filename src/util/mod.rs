@@ -1 +1,2 @@
 pub mod hex;
+pub mod storage_key;
@@ -0,0 +1,69 @@
+use sha3::{Digest, Keccak256};
+use starknet_types_core::felt::Felt;
+use starknet_types_core::hash::{Pedersen, StarkHash};
+
+use crate::util::hex::parse_felt_hex;
+use crate::model::{is_storage_expression, TrieKind};
+
+/// Storage addresses are reduced below `2^251 - 256`.
+const ADDR_BOUND: Felt = Felt::from_hex_unchecked("0x7ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff00");
+
+/// Parses a storage key given either as a felt or as a storage variable expression:
+/// `balance`, `ERC20_balances[0xabc]`, `allowances[0xa][0xb]`. See
+/// [`is_storage_expression`] for which input is an expression; mapping keys inside
+/// brackets are felts.
+pub fn parse_storage_key(input: &str) -> Result<Felt, String> {
+    let input = input.trim();
+    if !is_storage_expression(input) {
+        return parse_felt_hex(input);
+    }
+    let (name, mut rest) = input.split_at(input.find('[').unwrap_or(input.len()));
+    let name = name.trim();
+    if !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        return Err(format!("invalid storage variable name: {name}"));
+    }
+    let mut keys = Vec::new();
+    while !rest.is_empty() {
+        let Some(inner) = rest.strip_prefix('[') else {
+            return Err(format!("expected '[' at {rest}"));
+        };
+        let Some(end) = inner.find(']') else {
+            return Err(format!("missing ']' after {inner}"));
+        };
+        keys.push(parse_felt_hex(&inner[..end]).map_err(|err| format!("mapping key {}: {err}", &inner[..end]))?);
+        rest = inner[end + 1..].trim_start();
+    }
+    Ok(storage_var_address(name, &keys))
+}
+
+/// Parses a key of `trie`. Only storage tries take storage variable expressions; contract
+/// addresses and class hashes are always felts.
+pub fn parse_trie_key(trie: TrieKind, input: &str) -> Result<Felt, String> {
+    match trie {
+        TrieKind::Storage => parse_storage_key(input),
+        TrieKind::Contract | TrieKind::Class => parse_felt_hex(input),
+    }
+}
+
+/// Address of storage variable `name`, or of its entry at `keys` for a mapping:
+/// `sn_keccak(name)` Pedersen-chained with each key, reduced mod `2^251 - 256`.
+pub fn storage_var_address(name: &str, keys: &[Felt]) -> Felt {
+    storage_address(sn_keccak(name.as_bytes()), keys)
+}
+
+/// Entry at `keys` of the mapping whose base address is `base` (`sn_keccak` of its name).
+pub fn storage_address(base: Felt, keys: &[Felt]) -> Felt {
+    let hash = keys.iter().fold(base, |hash, key| Pedersen::hash(&hash, key));
+    if hash >= ADDR_BOUND {
+        hash - ADDR_BOUND
+    } else {
+        hash
+    }
+}
+
+/// Keccak-256 truncated to its low 250 bits, as Starknet uses for selectors and variable names.
+pub fn sn_keccak(data: &[u8]) -> Felt {
+    let mut hash: [u8; 32] = Keccak256::digest(data).into();
+    hash[0] &= 0x03;
+    Felt::from_bytes_be(&hash)
+}